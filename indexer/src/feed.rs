@@ -0,0 +1,133 @@
+use std::{fs, path::Path, str::FromStr};
+
+use rps::event::{GameEvent, LOG_PREFIX};
+use rps::instruction::GameInstruction;
+use serde::Deserialize;
+use serde_json::Value;
+use solana_program::pubkey::Pubkey;
+
+use crate::IndexerError;
+
+// The parts of an RPC getTransaction response (json encoding) the indexer
+// reads. Only legacy messages are handled: addresses loaded from lookup
+// tables are not resolved.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfirmedTransaction {
+  pub slot: u64,
+  pub block_time: Option<i64>,
+  pub transaction: TransactionJson,
+  pub meta: Option<MetaJson>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct TransactionJson {
+  pub signatures: Vec<String>,
+  pub message: MessageJson,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageJson {
+  pub account_keys: Vec<String>,
+  pub instructions: Vec<InstructionJson>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct InstructionJson {
+  pub program_id_index: u8,
+  pub accounts: Vec<u8>,
+  pub data: String,//base58
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MetaJson {
+  pub err: Option<Value>,
+  #[serde(default)]
+  pub log_messages: Vec<String>,
+}
+
+/// Reads a JSON array of transactions, or one transaction per line as a
+/// validator stand-in would stream them.
+pub fn read_transactions(path: &Path) -> Result<Vec<ConfirmedTransaction>, IndexerError> {
+  let text: String = fs::read_to_string(path)?;
+  if text.trim_start().starts_with('[') {
+    return Ok(serde_json::from_str(&text)?)
+  }
+  text.lines()
+    .filter(|line| !line.trim().is_empty())
+    .map(|line| Ok(serde_json::from_str(line)?))
+    .collect()
+}
+
+impl ConfirmedTransaction {
+  pub fn signature(&self) -> &str {
+    self.transaction.signatures.first().map(|s| s.as_str()).unwrap_or("")
+  }
+
+  pub fn succeeded(&self) -> bool {
+    match &self.meta {
+      Some(meta) => meta.err.is_none(),
+      None => false,
+    }
+  }
+
+  /// Top level instructions sent to `program_id`, decoded, with their accounts.
+  pub fn instructions(&self, program_id: &Pubkey) -> Result<Vec<(GameInstruction, Vec<Pubkey>)>, IndexerError> {
+    let keys: Vec<Pubkey> = self.transaction.message.account_keys.iter()
+      .map(|key| Pubkey::from_str(key).map_err(|_| IndexerError::InvalidKey(key.clone())))
+      .collect::<Result<_, _>>()?;
+    let key_at = |index: u8| -> Result<Pubkey, IndexerError> {
+      keys.get(index as usize).copied().ok_or_else(|| IndexerError::InvalidKey(index.to_string()))
+    };
+
+    let mut decoded: Vec<(GameInstruction, Vec<Pubkey>)> = Vec::new();
+    for (index, ix) in self.transaction.message.instructions.iter().enumerate() {
+      if &key_at(ix.program_id_index)? != program_id {continue}
+
+      let invalid = || IndexerError::InvalidInstruction{ signature: self.signature().to_string(), index };
+      let data: Vec<u8> = bs58::decode(&ix.data).into_vec().map_err(|_| invalid())?;
+      let instruction: GameInstruction = GameInstruction::unpack(&data).map_err(|_| invalid())?;
+      let accounts: Vec<Pubkey> = ix.accounts.iter().map(|a| key_at(*a)).collect::<Result<_, _>>()?;
+      decoded.push((instruction, accounts));
+    }
+    Ok(decoded)
+  }
+
+  /// Events logged by `program_id` itself, in order. Data logged by programs
+  /// it invokes is left out.
+  pub fn events(&self, program_id: &Pubkey) -> Vec<GameEvent> {
+    match &self.meta {
+      Some(meta) => GameEvent::from_logs(&program_data(&meta.log_messages, program_id)),
+      None => Vec::new(),
+    }
+  }
+}
+
+// "Program data:" lines written while `program_id` is the innermost running program.
+pub fn program_data<'a>(logs: &'a [String], program_id: &Pubkey) -> Vec<&'a str> {
+  let id: String = program_id.to_string();
+  let mut running: Vec<&str> = Vec::new();
+  let mut lines: Vec<&str> = Vec::new();
+
+  for line in logs {
+    if line.starts_with(LOG_PREFIX) {
+      if running.last() == Some(&id.as_str()) {lines.push(line)}
+      continue
+    }
+    // "Program <id> invoke [n]", then "Program <id> success" or "Program <id> failed: ..."
+    let mut words = match line.strip_prefix("Program ") {
+      Some(rest) => rest.split(' '),
+      None => continue,
+    };
+    let (program, action) = (words.next().unwrap_or(""), words.next().unwrap_or(""));
+    match action {
+      "invoke" => running.push(program),
+      "success" | "failed:" => {running.pop();}
+      _ => {}
+    }
+  }
+  lines
+}
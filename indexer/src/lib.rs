@@ -0,0 +1,46 @@
+pub mod feed;
+pub mod records;
+pub mod store;
+
+use solana_program::pubkey::Pubkey;
+use thiserror::Error;
+
+use crate::feed::ConfirmedTransaction;
+use crate::store::Store;
+
+#[derive(Error, Debug)]
+pub enum IndexerError {
+  #[error("Could Not Read Transactions: {0}")]
+  Io(#[from] std::io::Error),
+  #[error("Transaction Is Not Valid JSON: {0}")]
+  Json(#[from] serde_json::Error),
+  #[error("Store Error: {0}")]
+  Sqlite(#[from] rusqlite::Error),
+  #[error("Invalid Account Key {0}")]
+  InvalidKey(String),
+  #[error("Instruction {index} Of {signature} Does Not Decode")]
+  InvalidInstruction{ signature: String, index: usize },
+}
+
+// Feeds confirmed transactions of one deployment of the program into a store.
+pub struct Indexer {
+  pub program_id: Pubkey,
+  pub store: Store,
+}
+
+impl Indexer {
+  pub fn new(program_id: Pubkey, store: Store) -> Indexer {
+    Indexer{ program_id, store }
+  }
+
+  /// Indexes one transaction. Failed transactions and ones already in the
+  /// store are skipped, so a feed can be replayed from the start.
+  pub fn ingest(&mut self, tx: &ConfirmedTransaction) -> Result<bool, IndexerError> {
+    if !tx.succeeded() {return Ok(false)}
+    if self.store.has_transaction(tx.signature())? {return Ok(false)}
+
+    let records = records::records(tx, &self.program_id)?;
+    self.store.apply(tx.signature(), tx.slot, &records)?;
+    Ok(true)
+  }
+}
@@ -0,0 +1,36 @@
+use std::{env, path::Path, process, str::FromStr};
+
+use rps_indexer::feed::{self, ConfirmedTransaction};
+use rps_indexer::store::Store;
+use rps_indexer::{Indexer, IndexerError};
+use solana_program::pubkey::Pubkey;
+
+const USAGE: &str = "usage: rps-indexer <program id> <transactions.json> <store.sqlite>";
+
+fn run(program_id: &str, transactions: &str, store: &str) -> Result<(usize, usize), IndexerError> {
+  let program_id: Pubkey = Pubkey::from_str(program_id).map_err(|_| IndexerError::InvalidKey(program_id.to_string()))?;
+  let transactions: Vec<ConfirmedTransaction> = feed::read_transactions(Path::new(transactions))?;
+  let mut indexer = Indexer::new(program_id, Store::open(Path::new(store))?);
+
+  let mut indexed: usize = 0;
+  for tx in &transactions {
+    if indexer.ingest(tx)? {indexed += 1}
+  }
+  Ok((indexed, transactions.len()))
+}
+
+fn main() {
+  let args: Vec<String> = env::args().skip(1).collect();
+  if args.len() != 3 {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+  }
+
+  match run(&args[0], &args[1], &args[2]) {
+    Ok((indexed, total)) => println!("indexed {} of {} transactions", indexed, total),
+    Err(error) => {
+      eprintln!("{}", error);
+      process::exit(1);
+    }
+  }
+}
@@ -0,0 +1,148 @@
+use rps::event::GameEvent;
+use rps::instruction::GameInstruction;
+use solana_program::pubkey::Pubkey;
+
+use crate::feed::ConfirmedTransaction;
+use crate::IndexerError;
+
+// Row level changes one transaction makes to the store. Keys are base58
+// addresses: games and tournaments by their PDA. `mint` is the token a game
+// or tournament wagers, None for SOL.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Record {
+  Instruction{ index: usize, tag: u8 },
+  Game{ address: String, initializer: String, host: String, lamports: u64, game_ends: u8, ruleset: u8, mint: Option<String> },
+  Joined{ game: String, guest: String },
+  Round{ game: String, round: u16, initializer_move: u8, guest_move: u8, winner: u8 },
+  Finished{ game: String, status: Status, winner: Winner, host_fee: u64 },
+  Tournament{ address: String, tournament_id: String, entrance_fee: u64, tournament_size: u8, winner_get: u64, starts_at: u64, mint: Option<String> },
+  Player{ tournament: String, player: String, player_no: u32, level: u8 },
+  Match{ game: String, tournament: String, initializer: String, opponent_no: u32 },
+  Prize{ tournament: String, player: String, amount: u64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+  Won,
+  Drawn,
+  Timeout,
+  Aborted,
+}
+
+impl Status {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Status::Won => "won",
+      Status::Drawn => "drawn",
+      Status::Timeout => "timeout",
+      Status::Aborted => "aborted",
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Winner {
+  Initializer,
+  Guest,
+  Player(String),
+  Nobody,
+}
+
+fn key(bytes: [u8; 32]) -> String {
+  Pubkey::new_from_array(bytes).to_string()
+}
+
+fn mint(bytes: [u8; 32]) -> Option<String> {
+  if bytes == [0; 32] {None} else {Some(key(bytes))}
+}
+
+/// Everything a successful transaction changed. Instructions come first, in
+/// order, then the program's events in the order they were logged.
+pub fn records(tx: &ConfirmedTransaction, program_id: &Pubkey) -> Result<Vec<Record>, IndexerError> {
+  let mut out: Vec<Record> = Vec::new();
+  // tournament matches have no GameWon, the winner shows up as PlayerAdvanced
+  let mut decided_match: Option<(String, Status)> = None;
+  // GameCreated does not say what a game wagers, InitGame does
+  let mut game_mints: Vec<(String, Option<String>)> = Vec::new();
+
+  for (index, (instruction, accounts)) in tx.instructions(program_id)?.into_iter().enumerate() {
+    let data: Vec<u8> = instruction.pack().map_err(|_| IndexerError::InvalidInstruction{ signature: tx.signature().to_string(), index })?;
+    out.push(Record::Instruction{ index, tag: data[0] });
+
+    // tournament terms are only in the instruction, the event just names the account
+    if let (GameInstruction::EliminateForMv, Some(game)) = (&instruction, accounts.get(4)) {
+      decided_match = Some((game.to_string(), Status::Timeout));
+    }
+    if let (GameInstruction::InitGame{ init }, Some(game)) = (&instruction, accounts.get(1)) {
+      game_mints.push((game.to_string(), mint(init.mint)));
+    }
+    if let (GameInstruction::InitTournament{ t }, Some(tournament)) = (&instruction, accounts.get(1)) {
+      out.push(Record::Tournament{
+        address: tournament.to_string(),
+        tournament_id: t.tournament_id.clone(),
+        entrance_fee: t.entrance_fee,
+        tournament_size: t.tournament_size,
+        winner_get: t.winner_get,
+        starts_at: t.starts_at,
+        mint: mint(t.mint),
+      });
+    }
+  }
+
+  for event in tx.events(program_id) {
+    if let GameEvent::RoundResolved{ game, .. } = &event {
+      decided_match = Some((key(*game), Status::Won));
+    }
+    if let GameEvent::PlayerAdvanced{ player, .. } = &event {
+      if let Some((game, status)) = decided_match.take() {
+        out.push(Record::Finished{ game, status, winner: Winner::Player(key(*player)), host_fee: 0 });
+      }
+    }
+
+    let record: Option<Record> = match event {
+      GameEvent::GameCreated{ game, initializer, host, lamports, game_ends, ruleset } => Some(Record::Game{
+        mint: game_mints.iter().find(|(address, _)| *address == key(game)).and_then(|(_, mint)| mint.clone()),
+        address: key(game), initializer: key(initializer), host: key(host), lamports, game_ends, ruleset,
+      }),
+      GameEvent::GameJoined{ game, guest } => Some(Record::Joined{ game: key(game), guest: key(guest) }),
+      GameEvent::RoundResolved{ game, round, initializer_move, guest_move, winner, .. } => Some(Record::Round{
+        game: key(game), round, initializer_move, guest_move, winner,
+      }),
+      GameEvent::GameWon{ game, winner, host_fee } => Some(Record::Finished{
+        game: key(game),
+        status: if winner == 0 {Status::Drawn} else {Status::Won},
+        winner: match winner {
+          1 => Winner::Initializer,
+          2 => Winner::Guest,
+          _ => Winner::Nobody,
+        },
+        host_fee,
+      }),
+      GameEvent::TimeoutClaimed{ game, winner, host_fee } => Some(Record::Finished{
+        game: key(game), status: Status::Timeout, winner: Winner::Player(key(winner)), host_fee,
+      }),
+      GameEvent::Aborted{ game, .. } => Some(Record::Finished{
+        game: key(game), status: Status::Aborted, winner: Winner::Nobody, host_fee: 0,
+      }),
+      GameEvent::TournamentJoined{ tournament, player, player_no } => Some(Record::Player{
+        tournament: key(tournament), player: key(player), player_no, level: 0,
+      }),
+      GameEvent::PlayerAdvanced{ tournament, player, player_no, level } => Some(Record::Player{
+        tournament: key(tournament), player: key(player), player_no, level,
+      }),
+      GameEvent::MatchCreated{ game, tournament, initializer, opponent_no } => Some(Record::Match{
+        game: key(game), tournament: key(tournament), initializer: key(initializer), opponent_no,
+      }),
+      GameEvent::PrizeClaimed{ tournament, player, prize } => Some(Record::Prize{
+        tournament: key(tournament), player: key(player), amount: prize,
+      }),
+      GameEvent::MoveRevealed{ .. }
+      | GameEvent::TournamentCreated{ .. }
+      | GameEvent::ChatPosted{ .. }
+      | GameEvent::ConfigUpdated{ .. } => None,
+    };
+    out.extend(record);
+  }
+
+  Ok(out)
+}
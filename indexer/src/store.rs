@@ -0,0 +1,357 @@
+use std::path::Path;
+
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+
+use crate::records::{Record, Status, Winner};
+use crate::IndexerError;
+
+// A game account can be reused once its game is over (the initializer picks
+// the same seed again), so games have their own id and records always go to
+// the latest game at an address. Tournament matches are games too, with
+// `tournament` set. Amounts are in lamports, or in base units of `mint` for
+// token games and tournaments.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS transactions (
+  signature TEXT PRIMARY KEY,
+  slot INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS instructions (
+  signature TEXT NOT NULL,
+  idx INTEGER NOT NULL,
+  tag INTEGER NOT NULL,
+  slot INTEGER NOT NULL,
+  PRIMARY KEY (signature, idx)
+);
+CREATE TABLE IF NOT EXISTS games (
+  id INTEGER PRIMARY KEY,
+  address TEXT NOT NULL,
+  tournament TEXT,
+  initializer TEXT NOT NULL,
+  host TEXT,
+  guest TEXT,
+  lamports INTEGER NOT NULL DEFAULT 0,
+  mint TEXT,
+  game_ends INTEGER,
+  ruleset INTEGER,
+  opponent_no INTEGER,
+  status TEXT NOT NULL DEFAULT 'open',
+  winner TEXT,
+  host_fee INTEGER NOT NULL DEFAULT 0,
+  created_slot INTEGER NOT NULL,
+  finished_slot INTEGER
+);
+CREATE INDEX IF NOT EXISTS games_by_address ON games (address);
+CREATE TABLE IF NOT EXISTS rounds (
+  game_id INTEGER NOT NULL REFERENCES games (id),
+  round INTEGER NOT NULL,
+  initializer_move INTEGER NOT NULL,
+  guest_move INTEGER NOT NULL,
+  winner INTEGER NOT NULL,
+  slot INTEGER NOT NULL,
+  PRIMARY KEY (game_id, round)
+);
+CREATE TABLE IF NOT EXISTS tournaments (
+  address TEXT PRIMARY KEY,
+  tournament_id TEXT NOT NULL,
+  entrance_fee INTEGER NOT NULL,
+  tournament_size INTEGER NOT NULL,
+  winner_get INTEGER NOT NULL,
+  starts_at INTEGER NOT NULL,
+  mint TEXT,
+  created_slot INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS players (
+  tournament TEXT NOT NULL,
+  player TEXT NOT NULL,
+  player_no INTEGER NOT NULL,
+  level INTEGER NOT NULL,
+  slot INTEGER NOT NULL,
+  PRIMARY KEY (tournament, player)
+);
+CREATE TABLE IF NOT EXISTS payouts (
+  signature TEXT NOT NULL,
+  slot INTEGER NOT NULL,
+  source TEXT NOT NULL,
+  account TEXT NOT NULL,
+  kind TEXT NOT NULL,
+  amount INTEGER NOT NULL,
+  mint TEXT
+);
+";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameRow {
+  pub address: String,
+  pub tournament: Option<String>,
+  pub initializer: String,
+  pub guest: Option<String>,
+  pub lamports: u64,
+  pub mint: Option<String>,
+  pub status: String,
+  pub winner: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoundRow {
+  pub round: u16,
+  pub initializer_move: u8,
+  pub guest_move: u8,
+  pub winner: u8,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerRow {
+  pub player: String,
+  pub player_no: u32,
+  pub level: u8,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PayoutRow {
+  pub signature: String,
+  pub source: String,
+  pub kind: String,
+  pub amount: u64,
+  pub mint: Option<String>,
+}
+
+pub struct Store {
+  conn: Connection,
+}
+
+impl Store {
+  pub fn open(path: &Path) -> Result<Store, IndexerError> {
+    Store::init(Connection::open(path)?)
+  }
+
+  pub fn open_in_memory() -> Result<Store, IndexerError> {
+    Store::init(Connection::open_in_memory()?)
+  }
+
+  fn init(conn: Connection) -> Result<Store, IndexerError> {
+    conn.execute_batch(SCHEMA)?;
+    Ok(Store{ conn })
+  }
+
+  pub fn has_transaction(&self, signature: &str) -> Result<bool, IndexerError> {
+    let found: Option<i64> = self.conn
+      .query_row("SELECT slot FROM transactions WHERE signature = ?1", params![signature], |row| row.get(0))
+      .optional()?;
+    Ok(found.is_some())
+  }
+
+  /// Writes the records of one transaction atomically.
+  pub fn apply(&mut self, signature: &str, slot: u64, records: &[Record]) -> Result<(), IndexerError> {
+    let db: Transaction = self.conn.transaction()?;
+    let slot: i64 = slot as i64;
+
+    db.execute("INSERT INTO transactions (signature, slot) VALUES (?1, ?2)", params![signature, slot])?;
+    for record in records {
+      apply_record(&db, signature, slot, record)?;
+    }
+
+    db.commit()?;
+    Ok(())
+  }
+
+  /// The latest game played at `address`.
+  pub fn game(&self, address: &str) -> Result<Option<GameRow>, IndexerError> {
+    Ok(self.conn.query_row(
+      "SELECT address, tournament, initializer, guest, lamports, mint, status, winner
+       FROM games WHERE address = ?1 ORDER BY id DESC LIMIT 1",
+      params![address],
+      |row| Ok(GameRow{
+        address: row.get(0)?,
+        tournament: row.get(1)?,
+        initializer: row.get(2)?,
+        guest: row.get(3)?,
+        lamports: row.get::<_, i64>(4)? as u64,
+        mint: row.get(5)?,
+        status: row.get(6)?,
+        winner: row.get(7)?,
+      }),
+    ).optional()?)
+  }
+
+  /// Rounds of the latest game at `address`, in order.
+  pub fn rounds(&self, address: &str) -> Result<Vec<RoundRow>, IndexerError> {
+    let mut statement = self.conn.prepare(
+      "SELECT round, initializer_move, guest_move, winner FROM rounds
+       WHERE game_id = (SELECT MAX(id) FROM games WHERE address = ?1) ORDER BY round",
+    )?;
+    let rows = statement.query_map(params![address], |row| Ok(RoundRow{
+      round: row.get(0)?,
+      initializer_move: row.get(1)?,
+      guest_move: row.get(2)?,
+      winner: row.get(3)?,
+    }))?;
+    Ok(rows.collect::<Result<Vec<RoundRow>, rusqlite::Error>>()?)
+  }
+
+  /// Players by number of games won, most first.
+  pub fn leaderboard(&self, limit: u32) -> Result<Vec<(String, u32)>, IndexerError> {
+    let mut statement = self.conn.prepare(
+      "SELECT winner, COUNT(*) AS wins FROM games WHERE winner IS NOT NULL
+       GROUP BY winner ORDER BY wins DESC, winner LIMIT ?1",
+    )?;
+    let rows = statement.query_map(params![limit], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    Ok(rows.collect::<Result<Vec<(String, u32)>, rusqlite::Error>>()?)
+  }
+
+  /// Where every player of a tournament stands, furthest first.
+  pub fn bracket(&self, tournament: &str) -> Result<Vec<PlayerRow>, IndexerError> {
+    let mut statement = self.conn.prepare(
+      "SELECT player, player_no, level FROM players WHERE tournament = ?1 ORDER BY level DESC, player_no",
+    )?;
+    let rows = statement.query_map(params![tournament], |row| Ok(PlayerRow{
+      player: row.get(0)?,
+      player_no: row.get(1)?,
+      level: row.get(2)?,
+    }))?;
+    Ok(rows.collect::<Result<Vec<PlayerRow>, rusqlite::Error>>()?)
+  }
+
+  pub fn payouts(&self, account: &str) -> Result<Vec<PayoutRow>, IndexerError> {
+    let mut statement = self.conn.prepare(
+      "SELECT signature, source, kind, amount, mint FROM payouts WHERE account = ?1 ORDER BY slot, rowid",
+    )?;
+    let rows = statement.query_map(params![account], |row| Ok(PayoutRow{
+      signature: row.get(0)?,
+      source: row.get(1)?,
+      kind: row.get(2)?,
+      amount: row.get::<_, i64>(3)? as u64,
+      mint: row.get(4)?,
+    }))?;
+    Ok(rows.collect::<Result<Vec<PayoutRow>, rusqlite::Error>>()?)
+  }
+}
+
+const LATEST_GAME: &str = "(SELECT MAX(id) FROM games WHERE address = ?1)";
+
+fn apply_record(db: &Transaction, signature: &str, slot: i64, record: &Record) -> Result<(), rusqlite::Error> {
+  match record {
+    Record::Instruction{ index, tag } => {
+      db.execute(
+        "INSERT INTO instructions (signature, idx, tag, slot) VALUES (?1, ?2, ?3, ?4)",
+        params![signature, *index as i64, tag, slot],
+      )?;
+    }
+    Record::Game{ address, initializer, host, lamports, game_ends, ruleset, mint } => {
+      db.execute(
+        "INSERT INTO games (address, initializer, host, lamports, mint, game_ends, ruleset, created_slot)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![address, initializer, host, *lamports as i64, mint, game_ends, ruleset, slot],
+      )?;
+    }
+    Record::Match{ game, tournament, initializer, opponent_no } => {
+      db.execute(
+        "INSERT INTO games (address, tournament, initializer, opponent_no, created_slot) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![game, tournament, initializer, opponent_no, slot],
+      )?;
+    }
+    Record::Joined{ game, guest } => {
+      db.execute(
+        &format!("UPDATE games SET guest = ?2, status = 'playing' WHERE id = {}", LATEST_GAME),
+        params![game, guest],
+      )?;
+    }
+    Record::Round{ game, round, initializer_move, guest_move, winner } => {
+      db.execute(
+        &format!(
+          "INSERT INTO rounds (game_id, round, initializer_move, guest_move, winner, slot)
+           SELECT {}, ?2, ?3, ?4, ?5, ?6",
+          LATEST_GAME,
+        ),
+        params![game, round, initializer_move, guest_move, winner, slot],
+      )?;
+    }
+    Record::Finished{ game, status, winner, host_fee } => {
+      let (side, player): (u8, Option<&String>) = match winner {
+        Winner::Initializer => (1, None),
+        Winner::Guest => (2, None),
+        Winner::Player(player) => (0, Some(player)),
+        Winner::Nobody => (0, None),
+      };
+      db.execute(
+        &format!(
+          "UPDATE games SET status = ?2, host_fee = ?3, finished_slot = ?4,
+             winner = CASE ?5 WHEN 1 THEN initializer WHEN 2 THEN guest ELSE ?6 END
+           WHERE id = {}",
+          LATEST_GAME,
+        ),
+        params![game, status.as_str(), *host_fee as i64, slot, side, player],
+      )?;
+      pay_out(db, signature, slot, game, *status, *host_fee)?;
+    }
+    Record::Tournament{ address, tournament_id, entrance_fee, tournament_size, winner_get, starts_at, mint } => {
+      db.execute(
+        "INSERT OR REPLACE INTO tournaments (address, tournament_id, entrance_fee, tournament_size, winner_get, starts_at, mint, created_slot)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![address, tournament_id, *entrance_fee as i64, tournament_size, *winner_get as i64, *starts_at as i64, mint, slot],
+      )?;
+    }
+    Record::Player{ tournament, player, player_no, level } => {
+      db.execute(
+        "INSERT INTO players (tournament, player, player_no, level, slot) VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT (tournament, player) DO UPDATE SET player_no = ?3, level = ?4, slot = ?5",
+        params![tournament, player, player_no, level, slot],
+      )?;
+    }
+    Record::Prize{ tournament, player, amount } => {
+      db.execute(
+        "INSERT INTO payouts (signature, slot, source, account, kind, amount, mint)
+         SELECT ?1, ?2, ?3, ?4, 'prize', ?5, (SELECT mint FROM tournaments WHERE address = ?3)",
+        params![signature, slot, tournament, player, *amount as i64],
+      )?;
+    }
+  }
+  Ok(())
+}
+
+// address, host, initializer, guest, winner, lamports, mint
+type FinishedGame = (String, Option<String>, String, Option<String>, Option<String>, i64, Option<String>);
+
+// Stakes leave a finished game the way Processor pays them out. Tournament
+// matches hold no stake and pay nothing here.
+fn pay_out(db: &Transaction, signature: &str, slot: i64, game: &str, status: Status, host_fee: u64) -> Result<(), rusqlite::Error> {
+  let finished: Option<FinishedGame> = db.query_row(
+    &format!("SELECT address, host, initializer, guest, winner, lamports, mint FROM games WHERE id = {}", LATEST_GAME),
+    params![game],
+    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?, row.get(6)?)),
+  ).optional()?;
+  let (source, host, initializer, guest, winner, lamports, mint) = match finished {
+    Some(row) => row,
+    None => return Ok(()),
+  };
+  let lamports: u64 = lamports as u64;
+  if lamports == 0 {return Ok(())}
+
+  let mut payouts: Vec<(Option<String>, &str, u64)> = Vec::new();
+  match status {
+    Status::Won | Status::Timeout => {
+      payouts.push((host, "host_fee", host_fee));
+      payouts.push((winner, "winnings", 2 * lamports - host_fee));
+    }
+    Status::Drawn => {
+      payouts.push((host, "host_fee", host_fee));
+      payouts.push((Some(initializer), "refund", lamports - host_fee / 2));
+      payouts.push((guest, "refund", lamports - host_fee / 2));
+    }
+    Status::Aborted => {
+      payouts.push((Some(initializer), "refund", lamports));
+    }
+  }
+
+  for (account, kind, amount) in payouts {
+    let account: String = match account {
+      Some(account) => account,
+      None => continue,
+    };
+    if amount == 0 {continue}
+    db.execute(
+      "INSERT INTO payouts (signature, slot, source, account, kind, amount, mint) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+      params![signature, slot, source, account, kind, amount as i64, mint],
+    )?;
+  }
+  Ok(())
+}
@@ -0,0 +1,218 @@
+use base64::{prelude::BASE64_STANDARD, Engine};
+use borsh::BorshSerialize;
+use rps::client;
+use rps::event::{GameEvent, LOG_PREFIX};
+use rps::state::{Init, InitializerPlay};
+use rps_indexer::feed::{self, ConfirmedTransaction};
+use rps_indexer::records::{records, Record, Status, Winner};
+use rps_indexer::store::{PayoutRow, Store};
+use rps_indexer::Indexer;
+use serde_json::{json, Value};
+use solana_program::{instruction::Instruction, pubkey::Pubkey};
+
+const STAKE: u64 = 1_000_000_000;
+const ROCK: u8 = 1;
+const PAPER: u8 = 3;
+
+struct Keys {
+  program_id: Pubkey,
+  initializer: Pubkey,
+  guest: Pubkey,
+  host: Pubkey,
+  rent_data: Pubkey,
+  game: Pubkey,
+}
+
+fn keys() -> Keys {
+  Keys{
+    program_id: Pubkey::new_unique(),
+    initializer: Pubkey::new_unique(),
+    guest: Pubkey::new_unique(),
+    host: Pubkey::new_unique(),
+    rent_data: Pubkey::new_unique(),
+    game: Pubkey::new_unique(),
+  }
+}
+
+fn data_line(event: &GameEvent) -> String {
+  format!("{}{}", LOG_PREFIX, BASE64_STANDARD.encode(event.try_to_vec().unwrap()))
+}
+
+// A getTransaction response as the RPC returns it with json encoding.
+fn transaction(slot: u64, signature: &str, keys: &Keys, instructions: &[Instruction], events: &[GameEvent], err: Option<Value>) -> ConfirmedTransaction {
+  let mut account_keys: Vec<Pubkey> = Vec::new();
+  let mut index_of = |key: &Pubkey| -> usize {
+    match account_keys.iter().position(|k| k == key) {
+      Some(index) => index,
+      None => {
+        account_keys.push(*key);
+        account_keys.len() - 1
+      }
+    }
+  };
+  let compiled: Vec<Value> = instructions.iter().map(|ix| json!({
+    "programIdIndex": index_of(&ix.program_id),
+    "accounts": ix.accounts.iter().map(|meta| index_of(&meta.pubkey)).collect::<Vec<usize>>(),
+    "data": bs58::encode(&ix.data).into_string(),
+  })).collect();
+
+  let id: String = keys.program_id.to_string();
+  let mut logs: Vec<String> = vec![format!("Program {} invoke [1]", id)];
+  logs.extend(events.iter().map(data_line));
+  logs.push(format!("Program {} success", id));
+
+  serde_json::from_value(json!({
+    "slot": slot,
+    "blockTime": null,
+    "transaction": {
+      "signatures": [signature],
+      "message": {
+        "accountKeys": account_keys.iter().map(|k| k.to_string()).collect::<Vec<String>>(),
+        "instructions": compiled,
+      },
+    },
+    "meta": { "err": err, "logMessages": logs },
+  })).unwrap()
+}
+
+fn guest_play(keys: &Keys, lastmove: u8) -> Instruction {
+  client::guest_play(
+    &keys.program_id,
+    &keys.initializer,
+    &keys.guest,
+    &keys.host,
+    &keys.game,
+    &keys.rent_data,
+    InitializerPlay{ salt: [0; 32], lastmove, new_game_hash: [0; 32] },
+  )
+}
+
+fn created(keys: &Keys) -> GameEvent {
+  GameEvent::GameCreated{
+    game: keys.game.to_bytes(),
+    initializer: keys.initializer.to_bytes(),
+    host: keys.host.to_bytes(),
+    lamports: STAKE,
+    game_ends: 1,
+    ruleset: 0,
+  }
+}
+
+#[test]
+fn data_logged_by_invoked_programs_is_left_out() {
+  let program_id = Pubkey::new_unique();
+  let other = Pubkey::new_unique();
+  let logs: Vec<String> = vec![
+    format!("Program {} invoke [1]", program_id),
+    format!("{}AAAA", LOG_PREFIX),
+    format!("Program {} invoke [2]", other),
+    format!("{}BBBB", LOG_PREFIX),
+    format!("Program {} success", other),
+    format!("{}CCCC", LOG_PREFIX),
+    format!("Program {} success", program_id),
+    format!("{}DDDD", LOG_PREFIX),
+  ];
+  assert_eq!(
+    feed::program_data(&logs, &program_id),
+    vec![format!("{}AAAA", LOG_PREFIX), format!("{}CCCC", LOG_PREFIX)],
+  );
+}
+
+#[test]
+fn instructions_and_events_become_records() {
+  let keys = keys();
+  let game = keys.game.to_string();
+  let tx = transaction(7, "play", &keys, &[guest_play(&keys, PAPER)], &[
+    GameEvent::MoveRevealed{ game: keys.game.to_bytes(), player: keys.guest.to_bytes(), round: 0 },
+    GameEvent::RoundResolved{ game: keys.game.to_bytes(), round: 0, initializer_move: ROCK, guest_move: PAPER, winner: 2, score_i: 0, score_g: 1 },
+    GameEvent::GameWon{ game: keys.game.to_bytes(), winner: 2, host_fee: STAKE / 50 },
+  ], None);
+
+  assert_eq!(records(&tx, &keys.program_id).unwrap(), vec![
+    Record::Instruction{ index: 0, tag: guest_play(&keys, PAPER).data[0] },
+    Record::Round{ game: game.clone(), round: 0, initializer_move: ROCK, guest_move: PAPER, winner: 2 },
+    Record::Finished{ game, status: Status::Won, winner: Winner::Guest, host_fee: STAKE / 50 },
+  ]);
+}
+
+#[test]
+fn a_tournament_match_ends_when_a_player_advances() {
+  let keys = keys();
+  let tournament = Pubkey::new_unique();
+  let tx = transaction(9, "advance", &keys, &[], &[
+    GameEvent::RoundResolved{ game: keys.game.to_bytes(), round: 0, initializer_move: PAPER, guest_move: ROCK, winner: 1, score_i: 1, score_g: 0 },
+    GameEvent::PlayerAdvanced{ tournament: tournament.to_bytes(), player: keys.initializer.to_bytes(), player_no: 3, level: 1 },
+  ], None);
+
+  let found = records(&tx, &keys.program_id).unwrap();
+  assert!(found.contains(&Record::Finished{
+    game: keys.game.to_string(),
+    status: Status::Won,
+    winner: Winner::Player(keys.initializer.to_string()),
+    host_fee: 0,
+  }));
+  assert!(found.contains(&Record::Player{ tournament: tournament.to_string(), player: keys.initializer.to_string(), player_no: 3, level: 1 }));
+}
+
+#[test]
+fn a_finished_game_is_stored_with_its_payouts() {
+  let keys = keys();
+  let game = keys.game.to_string();
+  let mut indexer = Indexer::new(keys.program_id, Store::open_in_memory().unwrap());
+
+  let feed = [
+    transaction(1, "init", &keys, &[], &[created(&keys)], None),
+    transaction(2, "join", &keys, &[], &[GameEvent::GameJoined{ game: keys.game.to_bytes(), guest: keys.guest.to_bytes() }], None),
+    transaction(3, "failed", &keys, &[], &[GameEvent::Aborted{ game: keys.game.to_bytes(), refund: STAKE }], Some(json!({ "InstructionError": [0, { "Custom": 1 }] }))),
+    transaction(4, "play", &keys, &[guest_play(&keys, PAPER)], &[
+      GameEvent::RoundResolved{ game: keys.game.to_bytes(), round: 0, initializer_move: ROCK, guest_move: PAPER, winner: 2, score_i: 0, score_g: 1 },
+      GameEvent::GameWon{ game: keys.game.to_bytes(), winner: 2, host_fee: STAKE / 50 },
+    ], None),
+  ];
+  let indexed: Vec<bool> = feed.iter().map(|tx| indexer.ingest(tx).unwrap()).collect();
+  assert_eq!(indexed, vec![true, true, false, true]);
+  // replaying the feed changes nothing
+  assert!(feed.iter().all(|tx| !indexer.ingest(tx).unwrap()));
+
+  let row = indexer.store.game(&game).unwrap().unwrap();
+  assert_eq!(row.status, "won");
+  assert_eq!(row.guest, Some(keys.guest.to_string()));
+  assert_eq!(row.winner, Some(keys.guest.to_string()));
+  assert_eq!(indexer.store.rounds(&game).unwrap().len(), 1);
+  assert_eq!(indexer.store.leaderboard(10).unwrap(), vec![(keys.guest.to_string(), 1)]);
+
+  assert_eq!(indexer.store.payouts(&keys.guest.to_string()).unwrap(), vec![PayoutRow{
+    signature: "play".to_string(),
+    source: game.clone(),
+    kind: "winnings".to_string(),
+    amount: 2 * STAKE - STAKE / 50,
+    mint: None,
+  }]);
+  assert_eq!(indexer.store.payouts(&keys.host.to_string()).unwrap()[0].amount, STAKE / 50);
+}
+
+#[test]
+fn token_games_keep_their_mint() {
+  let keys = keys();
+  let mint = Pubkey::new_unique();
+  let mut indexer = Indexer::new(keys.program_id, Store::open_in_memory().unwrap());
+
+  let init = client::init_game(&keys.program_id, &keys.initializer, &keys.game, &keys.host, &keys.rent_data, Init{
+    bump: 0, game_ends: 1, gameseed: "game000001".to_string(), lamports: STAKE, game_hash: [0; 32], max_rounds: 0, sudden_death: 0,
+    ruleset: 0, moves: 0, ranked: 0, min_rating: 0, max_rating: 0, invited: [0; 32], allowlist_root: [0; 32], mint: mint.to_bytes(), lobby_page: 0,
+  });
+  let feed = [
+    transaction(1, "init", &keys, &[init], &[created(&keys)], None),
+    transaction(2, "join", &keys, &[], &[GameEvent::GameJoined{ game: keys.game.to_bytes(), guest: keys.guest.to_bytes() }], None),
+    transaction(3, "play", &keys, &[guest_play(&keys, PAPER)], &[
+      GameEvent::GameWon{ game: keys.game.to_bytes(), winner: 2, host_fee: STAKE / 50 },
+    ], None),
+  ];
+  for tx in &feed {
+    indexer.ingest(tx).unwrap();
+  }
+
+  assert_eq!(indexer.store.game(&keys.game.to_string()).unwrap().unwrap().mint, Some(mint.to_string()));
+  let payouts = indexer.store.payouts(&keys.guest.to_string()).unwrap();
+  assert_eq!((payouts[0].amount, payouts[0].mint.clone()), (2 * STAKE - STAKE / 50, Some(mint.to_string())));
+}
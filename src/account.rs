@@ -0,0 +1,128 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{entrypoint::ProgramResult, program_error::ProgramError};
+use crate::error::GameError;
+use crate::state::{
+  ChatGlobal, Config, CounterFinder, FinderFinder, GameState, Lobby, MatchQueue, PlayerProfile, TGameState, Tournament,
+  TournamentAccount, TournamentCounter, UpdateRent,
+};
+
+pub const DISCRIMINATOR_LEN: usize = 8;
+pub const HEADER_LEN: usize = DISCRIMINATOR_LEN + 1;//discriminator then layout version
+
+/// Every account the program owns starts with the discriminator of its type
+/// and the version of the layout that follows, so that one type is never
+/// read as another. Discriminators are the first 8 bytes of
+/// sha256("account:<type name>").
+pub trait AccountData: BorshSerialize + BorshDeserialize {
+  const DISCRIMINATOR: [u8; DISCRIMINATOR_LEN];
+  const VERSION: u8;
+
+  /// Reads the body of an account written with an older layout `version`.
+  /// A type bumps VERSION when its layout changes and decodes the layouts it
+  /// still accepts here, the next `store` writes the current one.
+  fn migrate(version: u8, body: &[u8]) -> Result<Self, ProgramError> {
+    let _ = (version, body);
+    Err(GameError::UnsupportedVersion.into())
+  }
+
+  fn load(data: &[u8]) -> Result<Self, ProgramError> {
+    if data.len() < HEADER_LEN || data[..DISCRIMINATOR_LEN] != Self::DISCRIMINATOR {return Err(GameError::WrongAccountType.into())}
+
+    let version: u8 = data[DISCRIMINATOR_LEN];
+    let body: &[u8] = &data[HEADER_LEN..];
+
+    if version > Self::VERSION {return Err(GameError::UnsupportedVersion.into())}
+    if version < Self::VERSION {return Self::migrate(version, body)}
+
+    Ok(Self::try_from_slice(body)?)
+  }
+
+  fn store(&self, data: &mut [u8]) -> ProgramResult {
+    if data.len() < HEADER_LEN {return Err(ProgramError::AccountDataTooSmall)}
+
+    data[..DISCRIMINATOR_LEN].copy_from_slice(&Self::DISCRIMINATOR);
+    data[DISCRIMINATOR_LEN] = Self::VERSION;
+    self.serialize(&mut &mut data[HEADER_LEN..])?;
+
+    Ok(())
+  }
+
+  /// The whole account, header included, as `store` writes it.
+  fn to_account_data(&self) -> Result<Vec<u8>, ProgramError> {
+    let mut data: Vec<u8> = vec![0; HEADER_LEN];
+    data[..DISCRIMINATOR_LEN].copy_from_slice(&Self::DISCRIMINATOR);
+    data[DISCRIMINATOR_LEN] = Self::VERSION;
+    self.serialize(&mut data)?;
+    Ok(data)
+  }
+
+  fn space(&self) -> Result<usize, ProgramError> {
+    Ok(self.to_account_data()?.len())
+  }
+}
+
+impl AccountData for GameState {
+  const DISCRIMINATOR: [u8; DISCRIMINATOR_LEN] = [144, 94, 208, 172, 248, 99, 134, 120];
+  const VERSION: u8 = 1;
+}
+
+impl AccountData for TGameState {
+  const DISCRIMINATOR: [u8; DISCRIMINATOR_LEN] = [8, 138, 44, 90, 123, 11, 116, 94];
+  const VERSION: u8 = 1;
+}
+
+impl AccountData for Tournament {
+  const DISCRIMINATOR: [u8; DISCRIMINATOR_LEN] = [175, 139, 119, 242, 115, 194, 57, 92];
+  const VERSION: u8 = 1;
+}
+
+impl AccountData for TournamentAccount {
+  const DISCRIMINATOR: [u8; DISCRIMINATOR_LEN] = [60, 80, 64, 99, 120, 6, 22, 117];
+  const VERSION: u8 = 1;
+}
+
+impl AccountData for TournamentCounter {
+  const DISCRIMINATOR: [u8; DISCRIMINATOR_LEN] = [8, 185, 227, 84, 22, 27, 170, 217];
+  const VERSION: u8 = 1;
+}
+
+impl AccountData for CounterFinder {
+  const DISCRIMINATOR: [u8; DISCRIMINATOR_LEN] = [34, 39, 98, 69, 61, 220, 56, 53];
+  const VERSION: u8 = 1;
+}
+
+impl AccountData for FinderFinder {
+  const DISCRIMINATOR: [u8; DISCRIMINATOR_LEN] = [29, 160, 104, 125, 218, 223, 29, 41];
+  const VERSION: u8 = 1;
+}
+
+// Records from before the header are read by Processor::load_rent.
+impl AccountData for UpdateRent {
+  const DISCRIMINATOR: [u8; DISCRIMINATOR_LEN] = [126, 146, 133, 72, 14, 51, 102, 204];
+  const VERSION: u8 = 1;
+}
+
+impl AccountData for ChatGlobal {
+  const DISCRIMINATOR: [u8; DISCRIMINATOR_LEN] = [48, 211, 112, 104, 169, 220, 85, 33];
+  const VERSION: u8 = 1;
+}
+
+impl AccountData for Config {
+  const DISCRIMINATOR: [u8; DISCRIMINATOR_LEN] = [155, 12, 170, 224, 30, 250, 204, 130];
+  const VERSION: u8 = 1;
+}
+
+impl AccountData for Lobby {
+  const DISCRIMINATOR: [u8; DISCRIMINATOR_LEN] = [167, 194, 217, 163, 92, 92, 103, 49];
+  const VERSION: u8 = 1;
+}
+
+impl AccountData for MatchQueue {
+  const DISCRIMINATOR: [u8; DISCRIMINATOR_LEN] = [186, 184, 159, 96, 38, 234, 164, 181];
+  const VERSION: u8 = 1;
+}
+
+impl AccountData for PlayerProfile {
+  const DISCRIMINATOR: [u8; DISCRIMINATOR_LEN] = [82, 226, 99, 87, 164, 130, 181, 80];
+  const VERSION: u8 = 1;
+}
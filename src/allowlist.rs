@@ -0,0 +1,51 @@
+use solana_program::{keccak, pubkey::Pubkey};
+
+// Merkle allowlists of private games. Leaves are domain separated so a leaf
+// can never be passed off as an inner node.
+pub const LEAF_DOMAIN: &[u8] = b"rps:allowlist-leaf:v1";
+pub const MAX_PROOF_LEN: usize = 16;//allowlists of up to 65536 players
+
+pub fn leaf(player: &Pubkey) -> [u8; 32] {
+  keccak::hashv(&[LEAF_DOMAIN, player.as_ref()]).0
+}
+
+// Pairs are hashed in sorted order, so proofs need no left/right flags.
+fn parent(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+  if a <= b {keccak::hashv(&[a, b]).0} else {keccak::hashv(&[b, a]).0}
+}
+
+/// Whether `proof` shows `player` is on the allowlist with this root.
+pub fn verify(root: &[u8; 32], player: &Pubkey, proof: &[[u8; 32]]) -> bool {
+  proof.len() <= MAX_PROOF_LEN && proof.iter().fold(leaf(player), |node, sibling| parent(&node, sibling)) == *root
+}
+
+// Every level of the tree, leaves first. A node without a sibling is carried
+// up unchanged.
+fn levels(players: &[Pubkey]) -> Vec<Vec<[u8; 32]>> {
+  let mut levels: Vec<Vec<[u8; 32]>> = vec![players.iter().map(leaf).collect()];
+  while levels[levels.len() - 1].len() > 1 {
+    let next: Vec<[u8; 32]> = levels[levels.len() - 1].chunks(2)
+      .map(|pair| if pair.len() == 2 {parent(&pair[0], &pair[1])} else {pair[0]})
+      .collect();
+    levels.push(next);
+  }
+  levels
+}
+
+/// Root to put in `Init::allowlist_root`, all zero for an empty list.
+pub fn root(players: &[Pubkey]) -> [u8; 32] {
+  if players.is_empty() {return [0; 32]}
+  let tree: Vec<Vec<[u8; 32]>> = levels(players);
+  tree[tree.len() - 1][0]
+}
+
+/// Proof that `player` is on the list, None if they are not.
+pub fn proof(players: &[Pubkey], player: &Pubkey) -> Option<Vec<[u8; 32]>> {
+  let mut index: usize = players.iter().position(|p| p == player)?;
+  let mut proof: Vec<[u8; 32]> = Vec::new();
+  for level in levels(players).iter().take_while(|level| level.len() > 1) {
+    if let Some(sibling) = level.get(index ^ 1) {proof.push(*sibling)}
+    index /= 2;
+  }
+  Some(proof)
+}
@@ -0,0 +1,642 @@
+use crate::instruction::GameInstruction;
+use crate::state::{Chat, Commit, CounterFinder, FinderFinder, GameState, Init, InitConfig, InitTournamentCounter, InitializerPlay, Join, ProposeAuthority, RoundRecord, Tournament, UpdateRent, CONFIG_SEED, HISTORY_LEN, PROFILE_SEED, ESCROW_SEED, GAME_SEED, Lobby, LobbyEntry, LOBBY_SEED, Enqueue, MatchQueue, QUEUE_SEED, stake_bracket};
+
+use solana_program::{
+  instruction::{AccountMeta, Instruction},
+  pubkey::Pubkey,
+  system_program,
+  bpf_loader_upgradeable,
+};
+
+// Instruction builders for off-chain clients. Account order mirrors the
+// next_account_info calls of the matching Processor function.
+
+// Instruction data for a builder. Its arguments are sized by the caller and
+// never reach the lengths GameInstruction::pack rejects.
+fn pack(instruction: GameInstruction) -> Vec<u8> {
+  instruction.pack().expect("instruction payload does not encode")
+}
+
+pub fn find_config_address(program_id: &Pubkey) -> (Pubkey, u8) {
+  Pubkey::find_program_address(&[CONFIG_SEED], program_id)
+}
+
+// Account of the upgradeable loader naming the program's upgrade authority,
+// the only signer InitConfig accepts.
+pub fn find_program_data_address(program_id: &Pubkey) -> Pubkey {
+  Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id()).0
+}
+
+pub fn find_game_address(program_id: &Pubkey, initializer: &Pubkey, gameseed: &str) -> (Pubkey, u8) {
+  Pubkey::find_program_address(&[GAME_SEED, initializer.as_ref(), gameseed.as_bytes()], program_id)
+}
+
+pub fn find_profile_address(program_id: &Pubkey, player: &Pubkey) -> (Pubkey, u8) {
+  Pubkey::find_program_address(&[PROFILE_SEED, player.as_ref()], program_id)
+}
+
+// Adds the profiles of `players` to a game or tournament instruction, in the
+// order the handler lists its players. Missing profiles are created when the
+// instruction's signer plays a move or joins a tournament.
+pub fn with_profiles(mut instruction: Instruction, players: &[Pubkey]) -> Instruction {
+  for player in players {
+    let (profile, _) = find_profile_address(&instruction.program_id, player);
+    instruction.accounts.push(AccountMeta::new(profile, false));
+  }
+  if !instruction.accounts.iter().any(|meta| meta.pubkey == system_program::id()) {
+    instruction.accounts.push(AccountMeta::new_readonly(system_program::id(), false));
+  }
+  instruction
+}
+
+pub fn find_escrow_address(program_id: &Pubkey, holder: &Pubkey) -> (Pubkey, u8) {
+  Pubkey::find_program_address(&[ESCROW_SEED, holder.as_ref()], program_id)
+}
+
+// Adds the token program, the escrow of `holder` (the game or tournament
+// account) and the payees' token accounts to an instruction of a token game
+// or tournament, payees in the order the handler lists its wallets. Add them
+// before any profiles.
+pub fn with_tokens(mut instruction: Instruction, holder: &Pubkey, token_accounts: &[Pubkey]) -> Instruction {
+  let (escrow, _) = find_escrow_address(&instruction.program_id, holder);
+  instruction.accounts.push(AccountMeta::new_readonly(spl_token::id(), false));
+  instruction.accounts.push(AccountMeta::new(escrow, false));
+  for token_account in token_accounts {
+    instruction.accounts.push(AccountMeta::new(*token_account, false));
+  }
+  instruction
+}
+
+// InitGame and InitTournament create the escrow, so they also take the mint.
+pub fn with_new_escrow(instruction: Instruction, holder: &Pubkey, mint: &Pubkey, token_accounts: &[Pubkey]) -> Instruction {
+  let mut instruction: Instruction = with_tokens(instruction, holder, token_accounts);
+  instruction.accounts.push(AccountMeta::new_readonly(*mint, false));
+  if !instruction.accounts.iter().any(|meta| meta.pubkey == system_program::id()) {
+    instruction.accounts.push(AccountMeta::new_readonly(system_program::id(), false));
+  }
+  instruction
+}
+
+pub fn find_lobby_address(program_id: &Pubkey, page: u16) -> (Pubkey, u8) {
+  Pubkey::find_program_address(&[LOBBY_SEED, &page.to_le_bytes()], program_id)
+}
+
+// Adds lobby page `page` to InitGame, JoinGame or Abort of a game listed on
+// it. Add it after any token accounts and before any profiles.
+pub fn with_lobby(mut instruction: Instruction, page: u16) -> Instruction {
+  let (lobby, _) = find_lobby_address(&instruction.program_id, page);
+  instruction.accounts.push(AccountMeta::new(lobby, false));
+  instruction
+}
+
+// Games still open on a lobby page, oldest slot first.
+pub fn open_games(lobby: &Lobby) -> Vec<LobbyEntry> {
+  lobby.games.iter().filter(|entry| entry.game != [0; 32]).copied().collect()
+}
+
+// The queue of the stake bracket holding `lamports`, see `state::stake_bracket`.
+pub fn find_queue_address(program_id: &Pubkey, lamports: u64) -> (Pubkey, u8) {
+  Pubkey::find_program_address(&[QUEUE_SEED, &[stake_bracket(lamports)]], program_id)
+}
+
+// Adds the queue of a game's stake to JoinGame or Abort of the game waiting
+// in it. Add it after any lobby page and before any profiles.
+pub fn with_queue(mut instruction: Instruction, lamports: u64) -> Instruction {
+  let (queue, _) = find_queue_address(&instruction.program_id, lamports);
+  instruction.accounts.push(AccountMeta::new(queue, false));
+  instruction
+}
+
+// The game an enqueuing player commits to and its bump: the waiting game if
+// the queue has one, otherwise the player's own game at `gameseed`.
+pub fn queued_game(program_id: &Pubkey, player: &Pubkey, gameseed: &str, queue: Option<&MatchQueue>) -> (Pubkey, u8) {
+  match queue {
+    Some(queue) if queue.game != [0; 32] => (Pubkey::new_from_array(queue.game), 0),
+    _ => find_game_address(program_id, player, gameseed),
+  }
+}
+
+// Rounds still held in the game's history, oldest first.
+pub fn round_history(state: &GameState) -> Vec<RoundRecord> {
+  let played: usize = state.rounds_played as usize;
+  (played.saturating_sub(HISTORY_LEN)..played).map(|round| state.history[round % HISTORY_LEN]).collect()
+}
+
+pub fn init_game(
+  program_id: &Pubkey,
+  initializer: &Pubkey,
+  game_state: &Pubkey,
+  host: &Pubkey,
+  rent_data: &Pubkey,
+  init: Init) -> Instruction {
+
+  Instruction {
+    program_id: *program_id,
+    accounts: vec![
+      AccountMeta::new(*initializer, true),
+      AccountMeta::new(*game_state, false),
+      AccountMeta::new_readonly(*host, false),
+      AccountMeta::new_readonly(*rent_data, false),
+      AccountMeta::new_readonly(system_program::id(), false),
+    ],
+    data: pack(GameInstruction::InitGame{ init }),
+  }
+}
+
+pub fn join_game(
+  program_id: &Pubkey,
+  guest: &Pubkey,
+  game_state: &Pubkey,
+  host: &Pubkey,
+  join: Commit) -> Instruction {
+
+  Instruction {
+    program_id: *program_id,
+    accounts: vec![
+      AccountMeta::new(*guest, true),
+      AccountMeta::new(*game_state, false),
+      AccountMeta::new_readonly(system_program::id(), false),
+      AccountMeta::new_readonly(*host, false),
+    ],
+    data: pack(GameInstruction::JoinGame{ join }),
+  }
+}
+
+pub fn initializer_play(
+  program_id: &Pubkey,
+  initializer: &Pubkey,
+  guest: &Pubkey,
+  host: &Pubkey,
+  game_state: &Pubkey,
+  rent_data: &Pubkey,
+  play: InitializerPlay) -> Instruction {
+
+  Instruction {
+    program_id: *program_id,
+    accounts: vec![
+      AccountMeta::new(*initializer, true),
+      AccountMeta::new(*guest, false),
+      AccountMeta::new(*host, false),
+      AccountMeta::new(*game_state, false),
+      AccountMeta::new_readonly(*rent_data, false),
+    ],
+    data: pack(GameInstruction::IPlay{ play }),
+  }
+}
+
+pub fn guest_play(
+  program_id: &Pubkey,
+  initializer: &Pubkey,
+  guest: &Pubkey,
+  host: &Pubkey,
+  game_state: &Pubkey,
+  rent_data: &Pubkey,
+  play: InitializerPlay) -> Instruction {
+
+  Instruction {
+    program_id: *program_id,
+    accounts: vec![
+      AccountMeta::new(*initializer, false),
+      AccountMeta::new(*guest, true),
+      AccountMeta::new(*host, false),
+      AccountMeta::new(*game_state, false),
+      AccountMeta::new_readonly(*rent_data, false),
+    ],
+    data: pack(GameInstruction::GPlay{ play }),
+  }
+}
+
+pub fn claim_victory(
+  program_id: &Pubkey,
+  initializer: &Pubkey,
+  guest: &Pubkey,
+  host: &Pubkey,
+  game_state: &Pubkey,
+  rent_data: &Pubkey) -> Instruction {
+
+  Instruction {
+    program_id: *program_id,
+    accounts: vec![
+      AccountMeta::new(*initializer, false),
+      AccountMeta::new(*guest, false),
+      AccountMeta::new(*host, false),
+      AccountMeta::new(*game_state, false),
+      AccountMeta::new_readonly(*rent_data, false),
+    ],
+    data: pack(GameInstruction::ClaimVictory),
+  }
+}
+
+pub fn abort(
+  program_id: &Pubkey,
+  initializer: &Pubkey,
+  game_state: &Pubkey) -> Instruction {
+
+  Instruction {
+    program_id: *program_id,
+    accounts: vec![
+      AccountMeta::new(*initializer, true),
+      AccountMeta::new(*game_state, false),
+    ],
+    data: pack(GameInstruction::Abort),
+  }
+}
+
+// `game_state` and `host` are those of the waiting game when the queue has
+// one, see `queued_game`.
+pub fn enqueue(
+  program_id: &Pubkey,
+  player: &Pubkey,
+  game_state: &Pubkey,
+  host: &Pubkey,
+  rent_data: &Pubkey,
+  q: Enqueue) -> Instruction {
+
+  let (queue, _) = find_queue_address(program_id, q.lamports);
+  Instruction {
+    program_id: *program_id,
+    accounts: vec![
+      AccountMeta::new(*player, true),
+      AccountMeta::new(queue, false),
+      AccountMeta::new(*game_state, false),
+      AccountMeta::new_readonly(*host, false),
+      AccountMeta::new_readonly(*rent_data, false),
+      AccountMeta::new_readonly(system_program::id(), false),
+    ],
+    data: pack(GameInstruction::Enqueue{ q }),
+  }
+}
+
+pub fn init_tournament(
+  program_id: &Pubkey,
+  initializer: &Pubkey,
+  tournament: &Pubkey,
+  config: &Pubkey,
+  t: Tournament) -> Instruction {
+
+  Instruction {
+    program_id: *program_id,
+    accounts: vec![
+      AccountMeta::new(*initializer, true),
+      AccountMeta::new(*tournament, false),
+      AccountMeta::new_readonly(*config, false),
+      AccountMeta::new_readonly(system_program::id(), false),
+    ],
+    data: pack(GameInstruction::InitTournament{ t }),
+  }
+}
+
+pub fn join_tournament(
+  program_id: &Pubkey,
+  participant: &Pubkey,
+  tournament_account: &Pubkey,
+  tournament: &Pubkey,
+  counter: &Pubkey,
+  init: Init) -> Instruction {
+
+  Instruction {
+    program_id: *program_id,
+    accounts: vec![
+      AccountMeta::new(*participant, true),
+      AccountMeta::new(*tournament_account, false),
+      AccountMeta::new_readonly(*tournament, false),
+      AccountMeta::new(*counter, false),
+      AccountMeta::new_readonly(system_program::id(), false),
+    ],
+    data: pack(GameInstruction::JoinTournament{ init }),
+  }
+}
+
+pub fn init_tournament_match(
+  program_id: &Pubkey,
+  initializer: &Pubkey,
+  initializer_tour_acc: &Pubkey,
+  game_state: &Pubkey,
+  tournament: &Pubkey,
+  init: Init) -> Instruction {
+
+  Instruction {
+    program_id: *program_id,
+    accounts: vec![
+      AccountMeta::new(*initializer, true),
+      AccountMeta::new(*initializer_tour_acc, false),
+      AccountMeta::new(*game_state, false),
+      AccountMeta::new_readonly(*tournament, false),
+      AccountMeta::new_readonly(system_program::id(), false),
+    ],
+    data: pack(GameInstruction::InitTournamentMatch{ init }),
+  }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn tournament_match_accept(
+  program_id: &Pubkey,
+  initializer: &Pubkey,
+  initializer_tour_acc: &Pubkey,
+  game_state: &Pubkey,
+  opponent_tour_acc: &Pubkey,
+  opponent: &Pubkey,
+  tournament: &Pubkey,
+  join: Join) -> Instruction {
+
+  Instruction {
+    program_id: *program_id,
+    accounts: vec![
+      AccountMeta::new_readonly(*initializer, false),
+      AccountMeta::new(*initializer_tour_acc, false),
+      AccountMeta::new(*game_state, false),
+      AccountMeta::new(*opponent_tour_acc, false),
+      AccountMeta::new_readonly(*opponent, true),
+      AccountMeta::new_readonly(*tournament, false),
+    ],
+    data: pack(GameInstruction::TournamentMatchAccept{ join }),
+  }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn tournament_initializer_play(
+  program_id: &Pubkey,
+  initializer: &Pubkey,
+  initializer_tour_acc: &Pubkey,
+  opponent: &Pubkey,
+  opponent_tour_acc: &Pubkey,
+  game_state: &Pubkey,
+  tournament: &Pubkey,
+  play: InitializerPlay) -> Instruction {
+
+  Instruction {
+    program_id: *program_id,
+    accounts: vec![
+      AccountMeta::new(*initializer, true),
+      AccountMeta::new(*initializer_tour_acc, false),
+      AccountMeta::new(*opponent, false),
+      AccountMeta::new(*opponent_tour_acc, false),
+      AccountMeta::new(*game_state, false),
+      AccountMeta::new_readonly(*tournament, false),
+    ],
+    data: pack(GameInstruction::TournamentInPlay{ play }),
+  }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn tournament_guest_play(
+  program_id: &Pubkey,
+  initializer: &Pubkey,
+  initializer_tour_acc: &Pubkey,
+  opponent: &Pubkey,
+  opponent_tour_acc: &Pubkey,
+  game_state: &Pubkey,
+  tournament: &Pubkey,
+  play: InitializerPlay) -> Instruction {
+
+  Instruction {
+    program_id: *program_id,
+    accounts: vec![
+      AccountMeta::new(*initializer, false),
+      AccountMeta::new(*initializer_tour_acc, false),
+      AccountMeta::new(*opponent, true),
+      AccountMeta::new(*opponent_tour_acc, false),
+      AccountMeta::new(*game_state, false),
+      AccountMeta::new_readonly(*tournament, false),
+    ],
+    data: pack(GameInstruction::TournamentGuPlay{ play }),
+  }
+}
+
+pub fn eliminate_for_initialize(
+  program_id: &Pubkey,
+  eliminate: &Pubkey,
+  eliminate_tour_acc: &Pubkey,
+  opponent: &Pubkey,
+  opponent_tour_acc: &Pubkey,
+  tournament: &Pubkey) -> Instruction {
+
+  Instruction {
+    program_id: *program_id,
+    accounts: vec![
+      AccountMeta::new(*eliminate, false),
+      AccountMeta::new(*eliminate_tour_acc, false),
+      AccountMeta::new_readonly(*opponent, false),
+      AccountMeta::new(*opponent_tour_acc, false),
+      AccountMeta::new_readonly(*tournament, false),
+    ],
+    data: pack(GameInstruction::EliminateForIn),
+  }
+}
+
+pub fn eliminate_for_move(
+  program_id: &Pubkey,
+  initializer: &Pubkey,
+  initializer_tour_acc: &Pubkey,
+  opponent: &Pubkey,
+  opponent_tour_acc: &Pubkey,
+  game_state: &Pubkey,
+  tournament: &Pubkey) -> Instruction {
+
+  Instruction {
+    program_id: *program_id,
+    accounts: vec![
+      AccountMeta::new(*initializer, false),
+      AccountMeta::new(*initializer_tour_acc, false),
+      AccountMeta::new(*opponent, false),
+      AccountMeta::new(*opponent_tour_acc, false),
+      AccountMeta::new(*game_state, false),
+      AccountMeta::new_readonly(*tournament, false),
+    ],
+    data: pack(GameInstruction::EliminateForMv),
+  }
+}
+
+pub fn init_counter(
+  program_id: &Pubkey,
+  authority: &Pubkey,
+  tournament_counter: &Pubkey,
+  config: &Pubkey,
+  t_counter: InitTournamentCounter) -> Instruction {
+
+  Instruction {
+    program_id: *program_id,
+    accounts: vec![
+      AccountMeta::new_readonly(*authority, true),
+      AccountMeta::new(*tournament_counter, false),
+      AccountMeta::new_readonly(*config, false),
+    ],
+    data: pack(GameInstruction::InitCounter{ t_counter }),
+  }
+}
+
+pub fn update_counter_finder(
+  program_id: &Pubkey,
+  authority: &Pubkey,
+  finder: &Pubkey,
+  config: &Pubkey,
+  c_finder: CounterFinder) -> Instruction {
+
+  Instruction {
+    program_id: *program_id,
+    accounts: vec![
+      AccountMeta::new_readonly(*authority, true),
+      AccountMeta::new(*finder, false),
+      AccountMeta::new_readonly(*config, false),
+    ],
+    data: pack(GameInstruction::UpdateCounterFinder{ c_finder }),
+  }
+}
+
+pub fn update_finder_finder(
+  program_id: &Pubkey,
+  authority: &Pubkey,
+  finder: &Pubkey,
+  config: &Pubkey,
+  f_finder: FinderFinder) -> Instruction {
+
+  Instruction {
+    program_id: *program_id,
+    accounts: vec![
+      AccountMeta::new_readonly(*authority, true),
+      AccountMeta::new(*finder, false),
+      AccountMeta::new_readonly(*config, false),
+    ],
+    data: pack(GameInstruction::UpdateFinderFinder{ f_finder }),
+  }
+}
+
+pub fn close_account(
+  program_id: &Pubkey,
+  authority: &Pubkey,
+  account: &Pubkey,
+  config: &Pubkey) -> Instruction {
+
+  Instruction {
+    program_id: *program_id,
+    accounts: vec![
+      AccountMeta::new(*authority, true),
+      AccountMeta::new(*account, false),
+      AccountMeta::new_readonly(*config, false),
+    ],
+    data: pack(GameInstruction::CloseAccount),
+  }
+}
+
+pub fn chat_global(
+  program_id: &Pubkey,
+  chat_account: &Pubkey,
+  rent_data: &Pubkey,
+  chat: Chat) -> Instruction {
+
+  Instruction {
+    program_id: *program_id,
+    accounts: vec![
+      AccountMeta::new(*chat_account, false),
+      AccountMeta::new_readonly(*rent_data, false),
+    ],
+    data: pack(GameInstruction::ChatGlobal{ chat }),
+  }
+}
+
+pub fn chat_local(
+  program_id: &Pubkey,
+  initializer: &Pubkey,
+  guest: &Pubkey,
+  speaker: &Pubkey,
+  game_state: &Pubkey,
+  rent_data: &Pubkey,
+  chat: Chat) -> Instruction {
+
+  Instruction {
+    program_id: *program_id,
+    accounts: vec![
+      AccountMeta::new_readonly(*initializer, false),
+      AccountMeta::new_readonly(*guest, false),
+      AccountMeta::new_readonly(*speaker, true),
+      AccountMeta::new(*game_state, false),
+      AccountMeta::new_readonly(*rent_data, false),
+    ],
+    data: pack(GameInstruction::ChatLocal{ chat }),
+  }
+}
+
+pub fn update_rent(
+  program_id: &Pubkey,
+  rent: &Pubkey,
+  authority: &Pubkey,
+  config: &Pubkey,
+  r: UpdateRent) -> Instruction {
+
+  Instruction {
+    program_id: *program_id,
+    accounts: vec![
+      AccountMeta::new(*rent, false),
+      AccountMeta::new(*authority, true),
+      AccountMeta::new_readonly(*config, false),
+      AccountMeta::new_readonly(system_program::id(), false),
+    ],
+    data: pack(GameInstruction::UpRent{ r }),
+  }
+}
+
+pub fn winner_claim(
+  program_id: &Pubkey,
+  initializer: &Pubkey,
+  initializer_tour_acc: &Pubkey,
+  tournament: &Pubkey) -> Instruction {
+
+  Instruction {
+    program_id: *program_id,
+    accounts: vec![
+      AccountMeta::new(*initializer, false),
+      AccountMeta::new(*initializer_tour_acc, false),
+      AccountMeta::new_readonly(*tournament, false),
+    ],
+    data: pack(GameInstruction::WinnerClaim),
+  }
+}
+
+pub fn init_config(
+  program_id: &Pubkey,
+  authority: &Pubkey,
+  config: &Pubkey,
+  c: InitConfig) -> Instruction {
+
+  Instruction {
+    program_id: *program_id,
+    accounts: vec![
+      AccountMeta::new(*authority, true),
+      AccountMeta::new(*config, false),
+      AccountMeta::new_readonly(find_program_data_address(program_id), false),
+      AccountMeta::new_readonly(system_program::id(), false),
+    ],
+    data: pack(GameInstruction::InitConfig{ c }),
+  }
+}
+
+pub fn propose_authority(
+  program_id: &Pubkey,
+  authority: &Pubkey,
+  config: &Pubkey,
+  p: ProposeAuthority) -> Instruction {
+
+  Instruction {
+    program_id: *program_id,
+    accounts: vec![
+      AccountMeta::new_readonly(*authority, true),
+      AccountMeta::new(*config, false),
+    ],
+    data: pack(GameInstruction::ProposeAuthority{ p }),
+  }
+}
+
+pub fn accept_authority(
+  program_id: &Pubkey,
+  new_authority: &Pubkey,
+  config: &Pubkey) -> Instruction {
+
+  Instruction {
+    program_id: *program_id,
+    accounts: vec![
+      AccountMeta::new_readonly(*new_authority, true),
+      AccountMeta::new(*config, false),
+    ],
+    data: pack(GameInstruction::AcceptAuthority),
+  }
+}
@@ -0,0 +1,18 @@
+use solana_program::{keccak, pubkey::Pubkey};
+
+// Bump the version suffix whenever the preimage layout changes.
+pub const COMMIT_DOMAIN: &[u8] = b"rps:move-commitment:v1";
+
+/// keccak(domain || game || round || player || move || salt) with the round
+/// index little endian. Binding the game account and round means a commitment
+/// can't be replayed elsewhere, and the 32 byte salt can't be brute forced.
+pub fn commitment(game: &Pubkey, round: u16, player: &Pubkey, mymove: u8, salt: &[u8; 32]) -> [u8; 32] {
+  keccak::hashv(&[
+    COMMIT_DOMAIN,
+    game.as_ref(),
+    &round.to_le_bytes(),
+    player.as_ref(),
+    &[mymove],
+    salt,
+  ]).0
+}
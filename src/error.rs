@@ -1,14 +1,159 @@
-use solana_program::program_error::ProgramError;
+use num_derive::FromPrimitive;
+use solana_program::{
+  decode_error::DecodeError,
+  msg,
+  program_error::{PrintProgramError, ProgramError},
+};
 use thiserror::Error;
 
-#[derive(Error, Debug, Copy, Clone)]
+#[derive(Error, Debug, Copy, Clone, FromPrimitive, PartialEq, Eq)]
 pub enum GameError {
   /// Invalid Instruction
   #[error("Invalid Instruction")]
   InvalidInstruction,
-  /// Account Is Not Writable
+  /// Reserved, not raised by any instruction. Kept so later codes keep their
+  /// numbers, never reuse it.
   #[error("Account Is Not Writable")]
   NotWritable,
+  /// Account Is Not Owned By The Program
+  #[error("Account Is Not Owned By The Program")]
+  WrongAccountOwner,
+  /// Game Account Does Not Match The Derived Address
+  #[error("Game Account Does Not Match The Derived Address")]
+  InvalidGameAccount,
+  /// Required Signature Is Missing
+  #[error("Required Signature Is Missing")]
+  NotSigner,
+  /// Signer Is Not The Program Authority
+  #[error("Signer Is Not The Program Authority")]
+  Unauthorized,
+  /// Host Does Not Match The Game
+  #[error("Host Does Not Match The Game")]
+  InvalidHost,
+  /// Initializer Does Not Match The Game
+  #[error("Initializer Does Not Match The Game")]
+  InvalidInitializer,
+  /// Guest Does Not Match The Game
+  #[error("Guest Does Not Match The Game")]
+  InvalidGuest,
+  /// Speaker Is Not A Player Of The Game
+  #[error("Speaker Is Not A Player Of The Game")]
+  NotAPlayer,
+  /// Account Is Already Initialized
+  #[error("Account Is Already Initialized")]
+  AlreadyInitialized,
+  /// Rent Account Is Not Initialized
+  #[error("Rent Account Is Not Initialized")]
+  RentNotInitialized,
+  /// Game Seed Must Be 10 Characters
+  #[error("Game Seed Must Be 10 Characters")]
+  InvalidSeedLength,
+  /// Match Format Is Invalid
+  #[error("Match Format Is Invalid")]
+  InvalidMatchFormat,
+  /// Not Enough Lamports
+  #[error("Not Enough Lamports")]
+  InsufficientFunds,
+  /// Reserved, no longer raised: see GameNotOpen, GameNotActive and
+  /// NoMovePending. Kept so later codes keep their numbers, never reuse it.
+  #[error("Game Is Not In The Required State")]
+  InvalidGameState,
+  /// Not Your Turn
+  #[error("Not Your Turn")]
+  WrongTurn,
+  /// Revealed Move Does Not Match The Commitment
+  #[error("Revealed Move Does Not Match The Commitment")]
+  HashMismatch,
+  /// Move Is Out Of Range
+  #[error("Move Is Out Of Range")]
+  InvalidMove,
+  /// Timeout Has Not Been Reached
+  #[error("Timeout Has Not Been Reached")]
+  TimeoutNotReached,
+  /// Tournament Is Not Initialized
+  #[error("Tournament Is Not Initialized")]
+  TournamentNotInitialized,
+  /// Account Belongs To Another Tournament
+  #[error("Account Belongs To Another Tournament")]
+  TournamentMismatch,
+  /// Account Must Be Read Only
+  #[error("Account Must Be Read Only")]
+  MustBeReadOnly,
+  /// Tournament Counter Is Full
+  #[error("Tournament Counter Is Full")]
+  CapacityFull,
+  /// Players Are Not Paired In The Bracket
+  #[error("Players Are Not Paired In The Bracket")]
+  OpponentMismatch,
+  /// Player Is Not In The Required Match State
+  #[error("Player Is Not In The Required Match State")]
+  InvalidPlayerState,
+  /// Player Has Not Reached The Final Level
+  #[error("Player Has Not Reached The Final Level")]
+  NotTournamentWinner,
+  /// Chat Message Is Too Long
+  #[error("Chat Message Is Too Long")]
+  ChatTooLong,
+  /// Config Account Is Invalid Or Not Initialized
+  #[error("Config Account Is Invalid Or Not Initialized")]
+  InvalidConfig,
+  /// Signer Is Not The Pending Authority
+  #[error("Signer Is Not The Pending Authority")]
+  NotPendingAuthority,
+  /// Ruleset Is Unknown Or Misconfigured
+  #[error("Ruleset Is Unknown Or Misconfigured")]
+  InvalidRuleset,
+  /// Profile Account Does Not Match The Player
+  #[error("Profile Account Does Not Match The Player")]
+  InvalidProfile,
+  /// Rating Band Is Invalid
+  #[error("Rating Band Is Invalid")]
+  InvalidRatingBand,
+  /// Player Rating Is Outside The Game's Band
+  #[error("Player Rating Is Outside The Game's Band")]
+  RatingOutOfBand,
+  /// Game Is Private And The Guest Is Not Invited
+  #[error("Game Is Private And The Guest Is Not Invited")]
+  NotInvited,
+  /// Token Account Does Not Match The Player Or The Mint
+  #[error("Token Account Does Not Match The Player Or The Mint")]
+  InvalidTokenAccount,
+  /// Escrow Account Does Not Match The Game Or Tournament
+  #[error("Escrow Account Does Not Match The Game Or Tournament")]
+  InvalidEscrow,
+  /// Lobby Page Does Not Match The Game
+  #[error("Lobby Page Does Not Match The Game")]
+  InvalidLobby,
+  /// Lobby Page Is Full
+  #[error("Lobby Page Is Full")]
+  LobbyFull,
+  /// Queue Account Does Not Match The Stake Bracket Or The Game
+  #[error("Queue Account Does Not Match The Stake Bracket Or The Game")]
+  InvalidQueue,
+  /// Player Is Already Waiting In The Queue
+  #[error("Player Is Already Waiting In The Queue")]
+  AlreadyQueued,
+  /// Game Is Finished And Its Account Closed
+  #[error("Game Is Finished And Its Account Closed")]
+  GameFinished,
+  /// Game Has Already Been Joined
+  #[error("Game Has Already Been Joined")]
+  GameNotOpen,
+  /// Game Has Not Been Joined Yet
+  #[error("Game Has Not Been Joined Yet")]
+  GameNotActive,
+  /// Neither Player Has Revealed This Round
+  #[error("Neither Player Has Revealed This Round")]
+  NoMovePending,
+  /// Account Is Not Of The Expected Type
+  #[error("Account Is Not Of The Expected Type")]
+  WrongAccountType,
+  /// Account Layout Version Is Not Supported
+  #[error("Account Layout Version Is Not Supported")]
+  UnsupportedVersion,
+  /// Tournament Prize Was Already Claimed
+  #[error("Tournament Prize Was Already Claimed")]
+  PrizeAlreadyClaimed,
 }
 
 impl From<GameError> for ProgramError {
@@ -16,3 +161,18 @@ impl From<GameError> for ProgramError {
     ProgramError::Custom(e as u32)
   }
 }
+
+impl<T> DecodeError<T> for GameError {
+  fn type_of() -> &'static str {
+    "GameError"
+  }
+}
+
+impl PrintProgramError for GameError {
+  fn print<E>(&self)
+  where
+    E: 'static + std::error::Error + DecodeError<E> + PrintProgramError + num_traits::FromPrimitive,
+  {
+    msg!("Error: {}", self);
+  }
+}
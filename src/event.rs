@@ -0,0 +1,74 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{entrypoint::ProgramResult, log::sol_log_data};
+
+// Prefix the runtime puts in front of sol_log_data payloads, which it base64 encodes.
+pub const LOG_PREFIX: &str = "Program data: ";
+
+// Logged by the processor with sol_log_data so indexers do not have to diff
+// account data. Keys are raw pubkey bytes like in state.rs.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub enum GameEvent {
+  GameCreated{ game:[u8;32], initializer:[u8;32], host:[u8;32], lamports:u64, game_ends:u8, ruleset:u8 },
+  GameJoined{ game:[u8;32], guest:[u8;32] },
+  MoveRevealed{ game:[u8;32], player:[u8;32], round:u16 },
+  RoundResolved{ game:[u8;32], round:u16, initializer_move:u8, guest_move:u8, winner:u8, score_i:u8, score_g:u8 },//winner as in RoundRecord
+  GameWon{ game:[u8;32], winner:u8, host_fee:u64 },//winner 0 when the round cap ends the match in a draw
+  TimeoutClaimed{ game:[u8;32], winner:[u8;32], host_fee:u64 },
+  Aborted{ game:[u8;32], refund:u64 },
+  TournamentCreated{ tournament:[u8;32], tournament_id:String },
+  TournamentJoined{ tournament:[u8;32], player:[u8;32], player_no:u32 },
+  MatchCreated{ game:[u8;32], tournament:[u8;32], initializer:[u8;32], opponent_no:u32 },
+  PlayerAdvanced{ tournament:[u8;32], player:[u8;32], player_no:u32, level:u8 },
+  PrizeClaimed{ tournament:[u8;32], player:[u8;32], prize:u64 },
+  ChatPosted{ channel:[u8;32], speaker:[u8;32], text:String },//speaker is zero for the global chat
+  ConfigUpdated{ authority:[u8;32], account:[u8;32] },//an admin instruction wrote or closed `account`
+}
+
+impl GameEvent {
+  pub fn emit(&self) -> ProgramResult {
+    sol_log_data(&[&self.try_to_vec()?]);
+    Ok(())
+  }
+
+  pub fn decode(data: &[u8]) -> Option<GameEvent> {
+    GameEvent::try_from_slice(data).ok()
+  }
+
+  /// Events found in a transaction's log messages, in order. Lines that are
+  /// not program data or do not decode as a GameEvent are skipped.
+  pub fn from_logs<S: AsRef<str>>(logs: &[S]) -> Vec<GameEvent> {
+    logs.iter()
+      .filter_map(|line| line.as_ref().strip_prefix(LOG_PREFIX))
+      .filter_map(base64_decode)
+      .filter_map(|data| GameEvent::decode(&data))
+      .collect()
+  }
+}
+
+// Standard alphabet with padding, which is what the runtime writes.
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+  let text: &str = text.trim_end_matches('=');
+  let mut out: Vec<u8> = Vec::with_capacity(text.len() * 3 / 4);
+  let mut buffer: u32 = 0;
+  let mut bits: u32 = 0;
+
+  for c in text.bytes() {
+    let value: u32 = match c {
+      b'A'..=b'Z' => c - b'A',
+      b'a'..=b'z' => c - b'a' + 26,
+      b'0'..=b'9' => c - b'0' + 52,
+      b'+' => 62,
+      b'/' => 63,
+      _ => return None,
+    } as u32;
+    buffer = (buffer << 6) | value;
+    bits += 6;
+    if bits >= 8 {
+      bits -= 8;
+      out.push((buffer >> bits) as u8);
+      buffer &= (1 << bits) - 1;
+    }
+  }
+
+  Some(out)
+}
@@ -1,14 +1,100 @@
-use crate::error::GameError::InvalidInstruction;
-use crate::state::{Init,Join,InitializerPlay,UpdateRent,Tournament,Chat,InitTournamentCounter,CounterFinder,FinderFinder};
-use borsh::BorshDeserialize;
+use crate::error::GameError::{InvalidInstruction, InvalidMove};
+use crate::rules;
+use crate::state::{Init,Join,Commit,InitializerPlay,UpdateRent,Tournament,Chat,InitTournamentCounter,CounterFinder,FinderFinder,InitConfig,ProposeAuthority,Enqueue};
+use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::program_error::ProgramError;
 
-#[derive(Debug, PartialEq)]
+/// Wire format: one tag byte followed by the Borsh encoding of the payload.
+///
+/// | tag | variant               | payload               |
+/// |-----|-----------------------|-----------------------|
+/// | 0   | InitGame              | Init                  |
+/// | 1   | JoinGame              | Commit                |
+/// | 2   | IPlay                 | InitializerPlay       |
+/// | 3   | GPlay                 | InitializerPlay       |
+/// | 4   | ClaimVictory          | -                     |
+/// | 5   | Abort                 | -                     |
+/// | 6   | InitTournament        | Tournament            |
+/// | 7   | JoinTournament        | Init                  |
+/// | 8   | InitTournamentMatch   | Init                  |
+/// | 9   | TournamentMatchAccept | Join                  |
+/// | 10  | reserved, never used  | -                     |
+/// | 11  | TournamentInPlay      | InitializerPlay       |
+/// | 12  | TournamentGuPlay      | InitializerPlay       |
+/// | 13  | EliminateForIn        | -                     |
+/// | 14  | EliminateForMv        | -                     |
+/// | 15  | InitCounter           | InitTournamentCounter |
+/// | 16  | UpdateCounterFinder   | CounterFinder         |
+/// | 17  | UpdateFinderFinder    | FinderFinder          |
+/// | 18  | CloseAccount          | -                     |
+/// | 19  | ChatGlobal            | Chat                  |
+/// | 20  | ChatLocal             | Chat                  |
+/// | 21  | UpRent                | UpdateRent            |
+/// | 22  | WinnerClaim           | -                     |
+/// | 23  | InitConfig            | InitConfig            |
+/// | 24  | ProposeAuthority      | ProposeAuthority      |
+/// | 25  | AcceptAuthority       | -                     |
+/// | 26  | Enqueue               | Enqueue               |
+///
+/// Tags are part of the deployed interface: never renumber a variant, and
+/// never reuse tag 10.
+///
+/// InitGame creates the game account itself, at the PDA of the initializer
+/// and gameseed (see `client::find_game_address`), funded by the initializer
+/// with the rent and their stake. Init's bump is that PDA's bump. The account
+/// is closed once the game is won, claimed on time or aborted, and anything
+/// sent to it afterwards fails with GameFinished.
+///
+/// Games and tournament matches carry a `status::GameStatus`. An instruction
+/// the status does not allow fails with GameNotOpen, GameNotActive, WrongTurn
+/// or NoMovePending, see `status::transition`.
+///
+/// Every account the program owns starts with the discriminator of its type
+/// and a layout version (see `account::AccountData`). An account of another
+/// type fails with WrongAccountType, one written by a newer layout with
+/// UnsupportedVersion. InitCounter stores an empty TournamentCounter.
+/// Accounts written before the header existed fail with WrongAccountType too,
+/// except the UpRent record, which is still read in its old layout. Upgrade to
+/// this layout with no game or tournament in flight: the config authority can
+/// only recover what is left in older accounts with CloseAccount.
+///
+/// InitConfig takes the program's ProgramData account (see
+/// `client::find_program_data_address`) and only its upgrade authority may
+/// send it, so nobody can claim the config of a fresh deployment first.
+///
+/// A game whose Init names a lobby page is listed there until it is joined
+/// or aborted, so InitGame, JoinGame and Abort of that game take the page
+/// after any token accounts (see `client::with_lobby`).
+///
+/// Enqueue pairs players whose stakes fall in the same bracket of
+/// `state::STAKE_BRACKETS` without agreeing on a gameseed: with nobody waiting
+/// in that bracket it opens the player's own game and parks it in the queue,
+/// otherwise it joins the waiting game. A game has one `lamports` both players
+/// put up, so the joining player stakes the waiting game's amount rather than
+/// their own. JoinGame and Abort of a queued game take the queue after any
+/// lobby page (see `client::with_queue`).
+///
+/// JoinGame, IPlay, GPlay, ClaimVictory, JoinTournament, TournamentInPlay,
+/// EliminateForIn, EliminateForMv and WinnerClaim also take the players'
+/// profile accounts after their own (see `client::with_profiles`). They are
+/// optional except in ranked games, which settle ratings from them.
+///
+/// A game whose Init names an invited guest or an allowlist root can only be
+/// joined by that guest, or by a signer whose JoinGame carries a proof from
+/// `allowlist::proof`.
+///
+/// Games and tournaments with a mint wager that token instead of SOL. Their
+/// InitGame, JoinGame, IPlay, GPlay, ClaimVictory, Abort, InitTournament,
+/// JoinTournament, TournamentInPlay, EliminateForIn, EliminateForMv and
+/// WinnerClaim take the token program, the escrow and the payees' token
+/// accounts before any profiles (see `client::with_tokens`). JoinGame then
+/// takes the guest's stake from their token account, not their wallet.
+#[derive(Debug, Clone, PartialEq)]
 pub enum GameInstruction {
   InitGame{ init : Init},
-  JoinGame{ join : Join},
+  JoinGame{ join : Commit},
   IPlay{ play : InitializerPlay},
-  GPlay{ play : Join},
+  GPlay{ play : InitializerPlay},
   ClaimVictory,
   Abort,
   InitTournament{t:Tournament},
@@ -26,8 +112,11 @@ pub enum GameInstruction {
   ChatGlobal{chat:Chat},
   ChatLocal{chat:Chat},
   UpRent{r:UpdateRent},
-  WinnerClaim
-
+  WinnerClaim,
+  InitConfig{c:InitConfig},
+  ProposeAuthority{p:ProposeAuthority},
+  AcceptAuthority,
+  Enqueue{q:Enqueue},
 }
 
 impl GameInstruction {
@@ -35,61 +124,176 @@ impl GameInstruction {
     let (tag, rest) = input.split_first().ok_or(InvalidInstruction)?;
     Ok(match tag {
       0 => Self::InitGame{
-        init: Init::try_from_slice(&rest)?,
+        init: Init::try_from_slice(rest)?,
       },
       1 => Self::JoinGame{
-        join: Join::try_from_slice(&rest)?,
+        join: Commit::try_from_slice(rest)?,
       },
       2 => Self::IPlay{
-        play: InitializerPlay::try_from_slice(&rest)?,
+        play: unpack_play(rest)?,
       },
       3 => Self::GPlay{
-        play: Join::try_from_slice(&rest)?,
+        play: unpack_play(rest)?,
       },
       4 => Self::ClaimVictory,
       5 => Self::Abort,
       6 => Self::InitTournament{
-        t: Tournament::try_from_slice(&rest)?,
+        t: Tournament::try_from_slice(rest)?,
       },
       7 => Self::JoinTournament{
-        init: Init::try_from_slice(&rest)?,
+        init: Init::try_from_slice(rest)?,
       },
       8 => Self::InitTournamentMatch{
-        init: Init::try_from_slice(&rest)?,
+        init: Init::try_from_slice(rest)?,
       },
       9 => Self::TournamentMatchAccept{
-        join: Join::try_from_slice(&rest)?,
+        join: unpack_join(rest)?,
       },
       11 => Self::TournamentInPlay{
-        play: InitializerPlay::try_from_slice(&rest)?,
+        play: unpack_play(rest)?,
       },
       12 => Self::TournamentGuPlay{
-        play: InitializerPlay::try_from_slice(&rest)?,
+        play: unpack_play(rest)?,
       },
       13 => Self::EliminateForIn,
       14 => Self::EliminateForMv,
       15 => Self::InitCounter{
-        t_counter: InitTournamentCounter::try_from_slice(&rest)?,
+        t_counter: InitTournamentCounter::try_from_slice(rest)?,
       },
       16 => Self::UpdateCounterFinder{
-        c_finder: CounterFinder::try_from_slice(&rest)?,
+        c_finder: CounterFinder::try_from_slice(rest)?,
       },
       17 => Self::UpdateFinderFinder{
-        f_finder: FinderFinder::try_from_slice(&rest)?,
+        f_finder: FinderFinder::try_from_slice(rest)?,
       },
       18 => Self::CloseAccount,
       19 => Self::ChatGlobal{
-        chat: Chat::try_from_slice(&rest)?,
+        chat: Chat::try_from_slice(rest)?,
       },
       20 => Self::ChatLocal{
-        chat: Chat::try_from_slice(&rest)?,
+        chat: Chat::try_from_slice(rest)?,
       },
       21 => Self::UpRent{
-        r: UpdateRent::try_from_slice(&rest)?,
+        r: UpdateRent::try_from_slice(rest)?,
       },
       22 => Self::WinnerClaim,
+      23 => Self::InitConfig{
+        c: InitConfig::try_from_slice(rest)?,
+      },
+      24 => Self::ProposeAuthority{
+        p: ProposeAuthority::try_from_slice(rest)?,
+      },
+      25 => Self::AcceptAuthority,
+      26 => Self::Enqueue{
+        q: Enqueue::try_from_slice(rest)?,
+      },
 
       _ => return Err(InvalidInstruction.into()),
     })
   }
+
+  /// Exact inverse of `unpack` for instructions that pass its move checks.
+  /// Fails only if a payload does not encode, e.g. a string longer than
+  /// u32::MAX bytes.
+  pub fn pack(&self) -> Result<Vec<u8>, ProgramError> {
+    let mut buf: Vec<u8> = Vec::new();
+    match self {
+      Self::InitGame{ init } => {
+        buf.push(0);
+        init.serialize(&mut buf)?;
+      }
+      Self::JoinGame{ join } => {
+        buf.push(1);
+        join.serialize(&mut buf)?;
+      }
+      Self::IPlay{ play } => {
+        buf.push(2);
+        play.serialize(&mut buf)?;
+      }
+      Self::GPlay{ play } => {
+        buf.push(3);
+        play.serialize(&mut buf)?;
+      }
+      Self::ClaimVictory => buf.push(4),
+      Self::Abort => buf.push(5),
+      Self::InitTournament{ t } => {
+        buf.push(6);
+        t.serialize(&mut buf)?;
+      }
+      Self::JoinTournament{ init } => {
+        buf.push(7);
+        init.serialize(&mut buf)?;
+      }
+      Self::InitTournamentMatch{ init } => {
+        buf.push(8);
+        init.serialize(&mut buf)?;
+      }
+      Self::TournamentMatchAccept{ join } => {
+        buf.push(9);
+        join.serialize(&mut buf)?;
+      }
+      Self::TournamentInPlay{ play } => {
+        buf.push(11);
+        play.serialize(&mut buf)?;
+      }
+      Self::TournamentGuPlay{ play } => {
+        buf.push(12);
+        play.serialize(&mut buf)?;
+      }
+      Self::EliminateForIn => buf.push(13),
+      Self::EliminateForMv => buf.push(14),
+      Self::InitCounter{ t_counter } => {
+        buf.push(15);
+        t_counter.serialize(&mut buf)?;
+      }
+      Self::UpdateCounterFinder{ c_finder } => {
+        buf.push(16);
+        c_finder.serialize(&mut buf)?;
+      }
+      Self::UpdateFinderFinder{ f_finder } => {
+        buf.push(17);
+        f_finder.serialize(&mut buf)?;
+      }
+      Self::CloseAccount => buf.push(18),
+      Self::ChatGlobal{ chat } => {
+        buf.push(19);
+        chat.serialize(&mut buf)?;
+      }
+      Self::ChatLocal{ chat } => {
+        buf.push(20);
+        chat.serialize(&mut buf)?;
+      }
+      Self::UpRent{ r } => {
+        buf.push(21);
+        r.serialize(&mut buf)?;
+      }
+      Self::WinnerClaim => buf.push(22),
+      Self::InitConfig{ c } => {
+        buf.push(23);
+        c.serialize(&mut buf)?;
+      }
+      Self::ProposeAuthority{ p } => {
+        buf.push(24);
+        p.serialize(&mut buf)?;
+      }
+      Self::AcceptAuthority => buf.push(25),
+      Self::Enqueue{ q } => {
+        buf.push(26);
+        q.serialize(&mut buf)?;
+      }
+    }
+    Ok(buf)
+  }
+}
+
+fn unpack_join(rest: &[u8]) -> Result<Join, ProgramError> {
+  let join: Join = Join::try_from_slice(rest)?;
+  if !rules::in_move_range(join.mymove) {return Err(InvalidMove.into())}
+  Ok(join)
+}
+
+fn unpack_play(rest: &[u8]) -> Result<InitializerPlay, ProgramError> {
+  let play: InitializerPlay = InitializerPlay::try_from_slice(rest)?;
+  if !rules::in_move_range(play.lastmove) {return Err(InvalidMove.into())}
+  Ok(play)
 }
@@ -1,5 +1,13 @@
+pub mod account;
+pub mod allowlist;
+pub mod client;
+pub mod commitment;
 pub mod entrypoint;
 pub mod error;
+pub mod event;
 pub mod instruction;
 pub mod processor;
+pub mod rating;
+pub mod rules;
 pub mod state;
+pub mod status;
@@ -1,20 +1,42 @@
+use crate::account::AccountData;
+use crate::allowlist;
+use crate::commitment;
+use crate::error::GameError;
+use crate::event::GameEvent;
 use crate::instruction::GameInstruction;
-use crate::state::{Chat, ChatGlobal, CounterFinder, FinderFinder, GameState, Init, InitTournamentCounter, InitializerPlay, Join, TGameState, Tournament, TournamentAccount, TournamentCounter, UpdateRent
+use crate::rating::{self, DEFAULT_RATING};
+use crate::rules::{self, Outcome};
+use crate::status::{self, Event, GameStatus};
+use crate::state::{Chat, ChatGlobal, Commit, Config, CounterFinder, FinderFinder, GameState, Init, InitTournamentCounter, InitializerPlay, Join, TGameState, Tournament, TournamentAccount, TournamentCounter, UpdateRent,
+  InitConfig, ProposeAuthority, CONFIG_SEED,
+  RENT_VERSION, LEGACY_RENT_LEN, DEFAULT_WIN_FEE_BPS, DEFAULT_TIMEOUT_FEE_BPS, DEFAULT_MOVE_TIMEOUT, CHAT_LINE_LEN,
+  RoundRecord, HISTORY_LEN,
+  PlayerProfile, PROFILE_SEED, PROFILE_MOVES, ESCROW_SEED, GAME_SEED, Lobby, LobbyEntry, LOBBY_SEED, LOBBY_PAGE_LEN,
+  MatchQueue, Enqueue, QUEUE_SEED, QUEUE_GAME_ENDS, stake_bracket,
 };
 
-use borsh::{BorshDeserialize, BorshSerialize};
-use std::str::FromStr;
+use borsh::BorshDeserialize;
 use solana_program::{
   account_info::{next_account_info, AccountInfo},
   entrypoint::ProgramResult,
+  program_error::ProgramError,
   pubkey::Pubkey,
-  sysvar::{clock::Clock, Sysvar,},
-  keccak,
-  program::invoke_signed,
-  system_instruction
+  sysvar::{clock::Clock, rent::Rent, Sysvar,},
+  program::{invoke, invoke_signed},
+  program_pack::Pack,
+  system_instruction, system_program,
+  bpf_loader_upgradeable::{self, UpgradeableLoaderState},
 };
 
 
+// Token program and escrow of a token game or tournament, see ESCROW_SEED.
+struct Escrow<'a, 'b> {
+  token_program: &'a AccountInfo<'b>,
+  account: &'a AccountInfo<'b>,
+  holder: Pubkey,
+  bump: u8,
+}
+
 pub struct Processor;
 impl Processor {
   pub fn process(
@@ -68,29 +90,41 @@ impl Processor {
         Self::eliminate_player_who_hasnt_made_his_move(accounts, program_id)
       }
       GameInstruction::InitCounter {t_counter} => {
-        Self::initialize_counter(accounts, t_counter)
+        Self::initialize_counter(accounts, t_counter, program_id)
       }
       GameInstruction::UpdateCounterFinder {c_finder} => {
-        Self::update_counter_finder(accounts, c_finder)
+        Self::update_counter_finder(accounts, c_finder, program_id)
       }
       GameInstruction::UpdateFinderFinder {f_finder} => {
-        Self::update_finder_finder(accounts, f_finder)
+        Self::update_finder_finder(accounts, f_finder, program_id)
       }
       GameInstruction::CloseAccount  => {
-        Self::close_account(accounts)
+        Self::close_account(accounts, program_id)
       }
       GameInstruction::ChatGlobal {chat} => {
-        Self::chat_global(accounts, chat)
+        Self::chat_global(accounts, chat, program_id)
       }
       GameInstruction::ChatLocal {chat} => {
-        Self::chat_local(accounts, chat)
+        Self::chat_local(accounts, chat, program_id)
       }
       GameInstruction::UpRent { r } => {
-        Self::update_rent(accounts,r)
+        Self::update_rent(accounts, r, program_id)
       }
       GameInstruction::WinnerClaim  => {
         Self::winner_claim_prize(accounts, program_id)
       }
+      GameInstruction::InitConfig { c } => {
+        Self::init_config(accounts, c, program_id)
+      }
+      GameInstruction::ProposeAuthority { p } => {
+        Self::propose_authority(accounts, p, program_id)
+      }
+      GameInstruction::AcceptAuthority => {
+        Self::accept_authority(accounts, program_id)
+      }
+      GameInstruction::Enqueue { q } => {
+        Self::enqueue(accounts, q, program_id)
+      }
     }
   }
 
@@ -107,42 +141,58 @@ impl Processor {
     let host: &AccountInfo<'_> = next_account_info(accounts_iter)?;
     let rent_data: &AccountInfo<'_> = next_account_info(accounts_iter)?;
 
-    let game_state_check: Pubkey = Pubkey::create_with_seed(initializer.key, &init.gameseed, program_id).unwrap();
+    if init.gameseed.len() != 10 {return Err(GameError::InvalidSeedLength.into())}
 
+    let game_state_check: Pubkey = Pubkey::create_program_address(&[GAME_SEED, initializer.key.as_ref(), init.gameseed.as_bytes(), &[init.bump]], program_id)?;
 
-    if game_state.key != &game_state_check{panic!()}
-    if game_state.owner != program_id{panic!()}
 
+    if game_state.key != &game_state_check {return Err(GameError::InvalidGameAccount.into())}
+    if !initializer.is_signer {return Err(GameError::NotSigner.into())}
 
-    let rents: UpdateRent = UpdateRent::try_from_slice(&rent_data.data.borrow())?;
 
-    if rents.is_init != 1 {panic!()}
-    if rent_data.owner != program_id {panic!()}
+    let rents: UpdateRent = Self::load_rent(rent_data, program_id)?;
 
-    if game_state.data.borrow()[0] != 0 {panic!()}
-    if init.gameseed.len() != 10 {panic!()}
-    if init.game_ends > 5 {panic!()}
-    if init.game_ends < 1 {panic!()}
-    if init.game_ends == 2 {panic!()}
-    if init.game_ends == 4 {panic!()}
+    if !game_state.data_is_empty() {return Err(GameError::AlreadyInitialized.into())}
+    if init.game_ends < 1 {return Err(GameError::InvalidMatchFormat.into())}
+    if init.max_rounds != 0 && init.max_rounds < init.game_ends {return Err(GameError::InvalidMatchFormat.into())}
+    if init.sudden_death > 1 {return Err(GameError::InvalidMatchFormat.into())}
+    if rules::move_count(init.ruleset, init.moves).is_none() {return Err(GameError::InvalidRuleset.into())}
+    if init.ranked > 1 {return Err(GameError::InvalidRatingBand.into())}
+    if init.ranked == 0 && (init.min_rating != 0 || init.max_rating != 0) {return Err(GameError::InvalidRatingBand.into())}
+    if init.max_rating != 0 && init.min_rating > init.max_rating {return Err(GameError::InvalidRatingBand.into())}
 
     let state: GameState = GameState{
     host:host.key.to_bytes(),
-    waiting:1,
-    initialized : 1,
-    gameseed : init.gameseed,
+    status:GameStatus::Open,
+    gameseed : init.gameseed.clone(),
     lamports:init.lamports,
     initializer:initializer.key.to_bytes(),
 
     gamehash:init.game_hash,
     guest: [0;32],
+    guest_hash: [0;32],
 
-    whoseturn:0,
+    initializer_move:0,
     guest_move:0,
     score_i:0,
     score_g:0,
     game_ends : init.game_ends,
+    max_rounds : init.max_rounds,
+    rounds_played : 0,
+    sudden_death : init.sudden_death,
+    ruleset : init.ruleset,
+    moves : init.moves,
+    ranked : init.ranked,
+    min_rating : init.min_rating,
+    max_rating : init.max_rating,
+    invited : init.invited,
+    allowlist_root : init.allowlist_root,
+    mint : init.mint,
+    bump : init.bump,
+    lobby_page : init.lobby_page,
+    queued : 0,
     lastplaytime : 0,
+    history : [RoundRecord::default(); HISTORY_LEN],
     chat_line_1: "XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX".to_string(),
     chat_line_2: "XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX".to_string(),
     chat_line_3: "XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX".to_string(),
@@ -152,48 +202,128 @@ impl Processor {
     };
 
 
-    if **game_state.lamports.borrow() < init.lamports + rents.rent {panic!()}
+    // token games hold the stake in the escrow, the game account only its rent
+    let (escrow, payees) = Self::load_escrow(accounts_iter, game_state.key, &init.mint, [initializer], program_id)?;
+    let staked: u64 = if escrow.is_some() {0} else {init.lamports};
+
+    // the initializer funds the game account with the rent and their stake
+    Self::create_pda(initializer, game_state, rents.rent + staked, state.space()?, &[GAME_SEED, initializer.key.as_ref(), init.gameseed.as_bytes(), &[init.bump]], program_id)?;
+
+    if let Some(escrow) = &escrow {
+      let mint: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+      if mint.key.to_bytes() != init.mint {return Err(GameError::InvalidTokenAccount.into())}
 
-    state.serialize(&mut &mut game_state.data.borrow_mut()[..])?;
+      Self::create_escrow(initializer, escrow, mint)?;
+      Self::deposit(payees[0], initializer, escrow, init.lamports)?;
+    }
+
+    if init.lobby_page != 0 {
+      let lobby: &AccountInfo<'_> = Self::next_lobby(accounts_iter)?;
+      let entry: LobbyEntry = LobbyEntry{
+        game: game_state.key.to_bytes(),
+        lamports: init.lamports,
+        game_ends: init.game_ends,
+        created: Clock::get()?.unix_timestamp as u64,
+      };
+      Self::list_game(initializer, lobby, init.lobby_page, entry, program_id)?;
+    }
+
+    state.store(&mut game_state.data.borrow_mut())?;
+
+    GameEvent::GameCreated{
+      game: game_state.key.to_bytes(),
+      initializer: state.initializer,
+      host: state.host,
+      lamports: state.lamports,
+      game_ends: state.game_ends,
+      ruleset: state.ruleset,
+    }.emit()?;
 
     Ok(())
   }
   fn join_the_game(
     accounts: &[AccountInfo], 
     program_id: &Pubkey,
-    join: Join ) -> ProgramResult {
+    join: Commit ) -> ProgramResult {
+
+    Self::seat_guest(accounts, program_id, join)
+  }
+
+  // JoinGame after its checks, shared with the pairing side of Enqueue
+  fn seat_guest(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+    join: Commit) -> ProgramResult {
 
     let accounts_iter: &mut std::slice::Iter<'_, AccountInfo<'_>> = &mut accounts.iter();
 
     let guest: &AccountInfo<'_> = next_account_info(accounts_iter)?;
     let game_state: &AccountInfo<'_> = next_account_info(accounts_iter)?;
-    let temp_account: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+    let system_program_info: &AccountInfo<'_> = next_account_info(accounts_iter)?;
     let host: &AccountInfo<'_> = next_account_info(accounts_iter)?;
 
-    if game_state.owner != program_id{panic!()}
-
-    let mut state: GameState = GameState::try_from_slice(&game_state.data.borrow())?;
+    let mut state: GameState = Self::load_game(game_state, program_id)?;
 
+    let (escrow, payees) = Self::load_escrow(accounts_iter, game_state.key, &state.mint, [guest], program_id)?;
+    let lobby: Option<&AccountInfo<'_>> = if state.lobby_page != 0 {Some(Self::next_lobby(accounts_iter)?)} else {None};
+    let queue: Option<&AccountInfo<'_>> = if state.queued == 1 {Some(Self::next_queue(accounts_iter)?)} else {None};
+    let profiles: [Option<&AccountInfo<'_>>; 2] = Self::trailing_profiles(accounts_iter);
 
     let hoast_address: Pubkey = Pubkey::new_from_array(state.host);
 
-    if &hoast_address != host.key{panic!()}
+    if &hoast_address != host.key {return Err(GameError::InvalidHost.into())}
+    state.status = status::transition(state.status, Event::Join{turn:0})?;
+
+    // the guest always signs, so nobody can join in their name. Private games
+    // take the invited guest or anyone proving they are on the allowlist
+    if !guest.is_signer {return Err(GameError::NotSigner.into())}
+    let open: bool = state.invited == [0;32] && state.allowlist_root == [0;32];
+    let invited: bool = state.invited != [0;32] && state.invited == guest.key.to_bytes();
+    let listed: bool = state.allowlist_root != [0;32] && allowlist::verify(&state.allowlist_root, guest.key, &join.proof);
+
+    if !open && !invited && !listed {return Err(GameError::NotInvited.into())}
+
+    // ranked games need the guest's rating, a first time player starts at the default
+    if state.ranked == 1 {
+      Self::create_profile(guest, profiles[0], guest.key, program_id)?;
+      let profile: PlayerProfile = Self::load_profile(profiles[0], guest.key, program_id)?.ok_or(GameError::InvalidProfile)?;
+      if !rating::in_band(profile.rating, state.min_rating, state.max_rating) {return Err(GameError::RatingOutOfBand.into())}
+    }
 
     let clock: Clock= Clock::get()?;
     let current_time: u64 = clock.unix_timestamp as u64;
 
 
     state.guest = guest.key.to_bytes();
-    state.waiting = 2;
     state.lastplaytime = current_time;
-    state.whoseturn = 1;
-    state.initialized = 2;
-    state.guest_move = join.mymove;
+    state.guest_hash = join.game_hash;
+
+    // a joined game leaves the lobby
+    if let Some(lobby) = lobby {
+      Self::unlist_game(lobby, game_state.key, state.lobby_page, program_id)?;
+      state.lobby_page = 0;
+    }
+    if let Some(queue) = queue {
+      Self::dequeue(queue, game_state.key, state.lamports, program_id)?;
+      state.queued = 0;
+    }
+
+    match &escrow {
+      Some(escrow) => Self::deposit(payees[0], guest, escrow, state.lamports)?,
+      // the SOL stake comes straight from the signing guest
+      None => invoke(
+        &system_instruction::transfer(guest.key, game_state.key, state.lamports),
+        &[
+          guest.clone(),
+          game_state.clone(),
+          system_program_info.clone(),
+        ],
+      )?,
+    }
 
-    **temp_account.lamports.borrow_mut()-= state.lamports;
-    **game_state.lamports.borrow_mut()+= state.lamports;
+    state.store(&mut game_state.data.borrow_mut())?;
 
-    state.serialize(&mut &mut game_state.data.borrow_mut()[..])?;
+    GameEvent::GameJoined{ game: game_state.key.to_bytes(), guest: state.guest }.emit()?;
 
     Ok(())
   }
@@ -209,32 +339,37 @@ impl Processor {
     let guest: &AccountInfo<'_> = next_account_info(accounts_iter)?;
     let host: &AccountInfo<'_> = next_account_info(accounts_iter)?;
     let game_state: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+    let rent_data: &AccountInfo<'_> = next_account_info(accounts_iter)?;
 
-    if game_state.owner != program_id{panic!()}
+    let mut state: GameState = Self::load_game(game_state, program_id)?;
+    let rents: UpdateRent = Self::load_rent(rent_data, program_id)?;
 
+    let (escrow, payees) = Self::load_escrow(accounts_iter, game_state.key, &state.mint, [initializer, guest, host], program_id)?;
+    let profiles: [Option<&AccountInfo<'_>>; 2] = Self::trailing_profiles(accounts_iter);
 
-    let mut state: GameState = GameState::try_from_slice(&game_state.data.borrow())?;
 
+    let host_address: Pubkey = Pubkey::new_from_array(state.host);
+    let initializer_address: Pubkey = Pubkey::new_from_array(state.initializer);
+    let guest_address: Pubkey = Pubkey::new_from_array(state.guest);
 
-    let host_address: Pubkey = Pubkey::new_from_array(host.key.to_bytes());
-    let initializer_address: Pubkey = Pubkey::new_from_array(initializer.key.to_bytes());
-    let guest_address: Pubkey = Pubkey::new_from_array(guest.key.to_bytes());
 
+    let game_state_check: Pubkey = Self::game_address(initializer.key, &state, program_id)?;
+    let last_game_hash: [u8;32] = commitment::commitment(game_state.key, state.rounds_played, initializer.key, play.lastmove, &play.salt);
 
-    let game_state_check: Pubkey = Pubkey::create_with_seed(initializer.key, &state.gameseed, program_id).unwrap();
-    let last_game_hash: keccak::Hash = keccak::hashv(&[&play.last_round_seed.to_string().as_bytes(),play.lastmove.to_string().as_ref(),&play.last_round_seed.to_string().as_bytes()]);
 
 
+    if initializer.key != &initializer_address {return Err(GameError::InvalidInitializer.into())}
+    if game_state.key != &game_state_check {return Err(GameError::InvalidGameAccount.into())}
+    if guest.key != &guest_address {return Err(GameError::InvalidGuest.into())}
+    if host.key != &host_address {return Err(GameError::InvalidHost.into())}
+    state.status = status::transition(state.status, Event::Reveal{player:1})?;
+    if state.gamehash != last_game_hash {return Err(GameError::HashMismatch.into())}
+    if !rules::is_valid_move(state.ruleset, state.moves, play.lastmove) {return Err(GameError::InvalidMove.into())}
 
-    if initializer.key != &initializer_address{panic!()}
-    if game_state.key != &game_state_check{panic!()}
-    if guest.key != &guest_address{panic!()}
-    if host.key != &host_address{panic!()}
-    if state.initialized != 2 {panic!()}
-    if state.whoseturn != 1 {panic!()}
-    if state.gamehash != last_game_hash.0{panic!()}
+    if !initializer.is_signer {return Err(GameError::NotSigner.into())}
 
-    if !initializer.is_signer {panic!()}
+    Self::create_profile(initializer, profiles[0], &initializer_address, program_id)?;
+    Self::create_profile(initializer, profiles[1], &guest_address, program_id)?;
 
 
     let clock: Clock= Clock::get()?;
@@ -242,76 +377,23 @@ impl Processor {
 
 
     state.lastplaytime = current_time;
-    state.whoseturn = 2;
+    state.initializer_move = play.lastmove;
     state.gamehash = play.new_game_hash;
 
-    
-    let mut iwins:bool=false;
-    let mut gwins:bool=false;
-
-    if state.guest_move == 1 {
-
-      if play.lastmove == 2 {state.score_g += 1}
-      if play.lastmove == 3 {state.score_i += 1}
-    }
-    if state.guest_move == 2 {
-
-      if play.lastmove == 3 {state.score_g += 1}
-      if play.lastmove == 1 {state.score_i += 1}
-    }
-    if state.guest_move == 3 {
-
-      if play.lastmove == 1 {state.score_g += 1}
-      if play.lastmove == 2 {state.score_i += 1}
-    }
-
-    state.serialize(&mut &mut game_state.data.borrow_mut()[..])?;
-
-
-    if state.game_ends == state.score_g{
-      gwins = true;
-    }
-    if state.game_ends == state.score_i{
-      iwins = true;
-    }
-
-    if iwins == true{
-
-      let host_fee: u64 = state.lamports/50;
-
-      **game_state.lamports.borrow_mut()-= host_fee;
-      **host.lamports.borrow_mut()+= host_fee;
-
-      let value: u64 = **game_state.lamports.borrow();
-
-      **game_state.lamports.borrow_mut()-= value;
-      **initializer.lamports.borrow_mut()+= value;
-
-    }
-    if gwins == true{
-      let host_fee: u64 = state.lamports/50;
-
-      **game_state.lamports.borrow_mut()-= host_fee;
-      **host.lamports.borrow_mut()+= host_fee;
-
-      let rew: u64 = (&state.lamports*2)-&host_fee;
-
-      **game_state.lamports.borrow_mut()-= rew;
-      **guest.lamports.borrow_mut()+= rew;
-
-      let value: u64 = **game_state.lamports.borrow();
+    GameEvent::MoveRevealed{ game: game_state.key.to_bytes(), player: state.initializer, round: state.rounds_played }.emit()?;
 
-      **game_state.lamports.borrow_mut()-= value;
-      **initializer.lamports.borrow_mut()+= value;
+    if state.status == (GameStatus::Active{turn:0}) {
+      return Self::finish_round(state, &rents, game_state, initializer, escrow.as_ref(), payees, profiles)
     }
 
+    state.store(&mut game_state.data.borrow_mut())?;
 
     Ok(())
   }
   fn guest_play(
     accounts: &[AccountInfo], 
     program_id: &Pubkey,
-    play: Join ) -> ProgramResult {
+    play: InitializerPlay ) -> ProgramResult {
       
 
     let accounts_iter: &mut std::slice::Iter<'_, AccountInfo<'_>> = &mut accounts.iter();
@@ -320,39 +402,154 @@ impl Processor {
     let guest: &AccountInfo<'_> = next_account_info(accounts_iter)?;
     let host: &AccountInfo<'_> = next_account_info(accounts_iter)?;
     let game_state: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+    let rent_data: &AccountInfo<'_> = next_account_info(accounts_iter)?;
 
-    if game_state.owner != program_id{panic!()}
+    let mut state: GameState = Self::load_game(game_state, program_id)?;
+    let rents: UpdateRent = Self::load_rent(rent_data, program_id)?;
 
-    let mut state: GameState = GameState::try_from_slice(&game_state.data.borrow())?;
+    let (escrow, payees) = Self::load_escrow(accounts_iter, game_state.key, &state.mint, [initializer, guest, host], program_id)?;
+    let profiles: [Option<&AccountInfo<'_>>; 2] = Self::trailing_profiles(accounts_iter);
 
 
-    let host_address: Pubkey = Pubkey::new_from_array(host.key.to_bytes());
-    let initializer_address: Pubkey = Pubkey::new_from_array(initializer.key.to_bytes());
-    let guest_address: Pubkey = Pubkey::new_from_array(guest.key.to_bytes());
+    let host_address: Pubkey = Pubkey::new_from_array(state.host);
+    let initializer_address: Pubkey = Pubkey::new_from_array(state.initializer);
+    let guest_address: Pubkey = Pubkey::new_from_array(state.guest);
+
 
+    let game_state_check: Pubkey = Self::game_address(initializer.key, &state, program_id)?;
+    let last_guest_hash: [u8;32] = commitment::commitment(game_state.key, state.rounds_played, guest.key, play.lastmove, &play.salt);
 
-    let game_state_check: Pubkey = Pubkey::create_with_seed(initializer.key, &state.gameseed, program_id).unwrap();
 
+    if initializer.key != &initializer_address {return Err(GameError::InvalidInitializer.into())}
+    if guest.key != &guest_address {return Err(GameError::InvalidGuest.into())}
+    if host.key != &host_address {return Err(GameError::InvalidHost.into())}
+    if game_state.key != &game_state_check {return Err(GameError::InvalidGameAccount.into())}
+    state.status = status::transition(state.status, Event::Reveal{player:2})?;
+    if state.guest_hash != last_guest_hash {return Err(GameError::HashMismatch.into())}
+    if !rules::is_valid_move(state.ruleset, state.moves, play.lastmove) {return Err(GameError::InvalidMove.into())}
 
-    if initializer.key != &initializer_address{panic!()}
-    if guest.key != &guest_address{panic!()}
-    if host.key != &host_address{panic!()}
-    if game_state.key != &game_state_check{panic!()}
-    if state.initialized != 2 {panic!()}
-    if state.whoseturn != 2 {panic!()}
+    if !guest.is_signer {return Err(GameError::NotSigner.into())}
 
-    if !guest.is_signer {panic!()}
+    Self::create_profile(guest, profiles[0], &initializer_address, program_id)?;
+    Self::create_profile(guest, profiles[1], &guest_address, program_id)?;
 
 
     let clock: Clock= Clock::get()?;
     let current_time: u64 = clock.unix_timestamp as u64;
 
     state.lastplaytime = current_time;
-    state.whoseturn = 1;
-    state.guest_move = play.mymove;
-    
+    state.guest_move = play.lastmove;
+    state.guest_hash = play.new_game_hash;
+
+    GameEvent::MoveRevealed{ game: game_state.key.to_bytes(), player: state.guest, round: state.rounds_played }.emit()?;
+
+    if state.status == (GameStatus::Active{turn:0}) {
+      return Self::finish_round(state, &rents, game_state, initializer, escrow.as_ref(), payees, profiles)
+    }
+
+    state.store(&mut game_state.data.borrow_mut())?;
+
+    Ok(())
+  }
+  // Both moves of the round are revealed: score it, open the next round and
+  // pay out if the match is over, to the initializer, guest and host payees.
+  // The players' profiles count the round and the result.
+  fn finish_round<'a>(
+    mut state: GameState,
+    rents: &UpdateRent,
+    game_state: &AccountInfo<'a>,
+    initializer: &AccountInfo<'a>,
+    escrow: Option<&Escrow<'_, 'a>>,
+    payees: [&AccountInfo<'a>; 3],
+    profiles: [Option<&AccountInfo>; 2]) -> ProgramResult {
+
+    // copied, closing the game hands its account to the system program
+    let program_id: Pubkey = *game_state.owner;
+    let moves: [u8; 2] = [state.initializer_move, state.guest_move];
+    let mut iwins:bool=false;
+    let mut gwins:bool=false;
+    let mut draw:bool=false;
+
+    let winner: u8 = match rules::resolve(state.ruleset, state.moves, state.initializer_move, state.guest_move)? {
+      Outcome::InitializerWins => {state.score_i += 1; 1}
+      Outcome::GuestWins => {state.score_g += 1; 2}
+      Outcome::Draw => 0
+    };
+    state.history[state.rounds_played as usize % HISTORY_LEN] = RoundRecord{
+      round: state.rounds_played,
+      initializer_move: state.initializer_move,
+      guest_move: state.guest_move,
+      winner,
+      time: state.lastplaytime,
+    };
+    GameEvent::RoundResolved{
+      game: game_state.key.to_bytes(),
+      round: state.rounds_played,
+      initializer_move: state.initializer_move,
+      guest_move: state.guest_move,
+      winner,
+      score_i: state.score_i,
+      score_g: state.score_g,
+    }.emit()?;
+
+    state.rounds_played = state.rounds_played.saturating_add(1);
+    state.initializer_move = 0;
+    state.guest_move = 0;
+
+    if state.game_ends == state.score_g{
+      gwins = true;
+    }
+    if state.game_ends == state.score_i{
+      iwins = true;
+    }
+    if state.max_rounds != 0 && state.rounds_played >= state.max_rounds as u16 && !iwins && !gwins {
+      if state.score_i > state.score_g {iwins = true}
+      if state.score_g > state.score_i {gwins = true}
+      if state.score_i == state.score_g && state.sudden_death == 0 {draw = true}
+    }
+
+    if iwins || gwins || draw {
+      state.status = status::transition(state.status, Event::End{winner: if iwins {1} else if gwins {2} else {0}})?;
+    }
+    state.store(&mut game_state.data.borrow_mut())?;
+
+    if iwins || gwins || draw {
+      let host_fee: u64 = Self::fee(state.lamports, rents.win_fee_bps);
+
+      // the winner takes both stakes less the fee, a draw gives both back
+      // with each side paying half of it
+      let rew: u64 = if gwins {(state.lamports*2)-host_fee} else if draw {state.lamports-(host_fee/2)} else {0};
+
+      Self::pay(game_state, payees[2], host_fee, escrow)?;
+      Self::pay(game_state, payees[1], rew, escrow)?;
+      Self::release(game_state, initializer, payees[0], escrow)?;
+
+      GameEvent::GameWon{
+        game: game_state.key.to_bytes(),
+        winner: if iwins {1} else if gwins {2} else {0},
+        host_fee: Self::fee(state.lamports, rents.win_fee_bps),
+      }.emit()?;
+    }
+
+    let over: bool = iwins || gwins || draw;
+    let wagered: u64 = Self::lamports_of(&state.mint, state.lamports);
+    let host_fee: u64 = Self::lamports_of(&state.mint, Self::fee(state.lamports, rents.win_fee_bps));
+    let ratings: Option<[u16; 2]> = if over {Self::settle_ratings(&state, profiles, &program_id, iwins, gwins)?} else {None};
+    let sides: [([u8;32], bool, bool); 2] = [(state.initializer, iwins, gwins), (state.guest, gwins, iwins)];
+    for (side, (player, won, lost)) in sides.into_iter().enumerate() {
+      Self::update_profile(profiles[side], &Pubkey::new_from_array(player), &program_id, |p| {
+        if let Some(ratings) = ratings {p.rating = ratings[side]; p.ranked_games += 1}
+        p.rounds_played += 1;
+        Self::count_move(p, moves[side]);
+        if !over {return}
+        p.games_played += 1;
+        p.lamports_wagered += wagered;
+        if won {p.wins += 1; p.lamports_won += wagered*2 - host_fee}
+        if lost {p.losses += 1}
+        if draw {p.draws += 1; p.lamports_won += wagered - host_fee/2}
+      })?;
+    }
 
-    state.serialize(&mut &mut game_state.data.borrow_mut()[..])?;
 
     Ok(())
   }
@@ -367,68 +564,69 @@ impl Processor {
     let guest: &AccountInfo<'_> = next_account_info(accounts_iter)?;
     let host: &AccountInfo<'_> = next_account_info(accounts_iter)?;
     let game_state: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+    let rent_data: &AccountInfo<'_> = next_account_info(accounts_iter)?;
 
-    let state: GameState = GameState::try_from_slice(&game_state.data.borrow())?;
+    let mut state: GameState = Self::load_game(game_state, program_id)?;
+    let rents: UpdateRent = Self::load_rent(rent_data, program_id)?;
 
-    let host_address: Pubkey = Pubkey::new_from_array(host.key.to_bytes());
-    let initializer_address: Pubkey = Pubkey::new_from_array(initializer.key.to_bytes());
-    let guest_address: Pubkey = Pubkey::new_from_array(guest.key.to_bytes());
+    let (escrow, payees) = Self::load_escrow(accounts_iter, game_state.key, &state.mint, [initializer, guest, host], program_id)?;
+    let profiles: [Option<&AccountInfo<'_>>; 2] = Self::trailing_profiles(accounts_iter);
 
-    if host.key != &host_address{panic!()}
-    if guest.key != &guest_address{panic!()}
-    if initializer.key != &initializer_address{panic!()}
+    let host_address: Pubkey = Pubkey::new_from_array(state.host);
+    let initializer_address: Pubkey = Pubkey::new_from_array(state.initializer);
+    let guest_address: Pubkey = Pubkey::new_from_array(state.guest);
 
-    let game_state_check: Pubkey = Pubkey::create_with_seed(initializer.key, &state.gameseed, program_id).unwrap();
+    if host.key != &host_address {return Err(GameError::InvalidHost.into())}
+    if guest.key != &guest_address {return Err(GameError::InvalidGuest.into())}
+    if initializer.key != &initializer_address {return Err(GameError::InvalidInitializer.into())}
 
-    if game_state.key != &game_state_check{panic!()}
-    if state.initialized != 2 {panic!()}
+    let game_state_check: Pubkey = Self::game_address(initializer.key, &state, program_id)?;
+
+    if game_state.key != &game_state_check {return Err(GameError::InvalidGameAccount.into())}
+    let waiting_on: GameStatus = state.status;
+    state.status = status::transition(state.status, Event::Timeout)?;
 
     let clock: Clock= Clock::get()?;
     let current_time: u64 = clock.unix_timestamp as u64;
 
-    let time_passed: u64 = &current_time - &state.lastplaytime;
+    let time_passed: u64 = current_time - state.lastplaytime;
 
-    if time_passed<120{panic!()}
+    if time_passed<rents.move_timeout {return Err(GameError::TimeoutNotReached.into())}
 
     let mut gwins:bool=false;
     let mut iwins:bool=false;
 
-    if state.whoseturn == 1{
+    if waiting_on == (GameStatus::Active{turn:1}){
       gwins = true;
     }
-    if state.whoseturn == 2{
+    if waiting_on == (GameStatus::Active{turn:2}){
       iwins = true;
     }
 
-    if iwins == true{
-
-      let host_fee: u64 = state.lamports/100;
-
-      **game_state.lamports.borrow_mut()-= host_fee;
-      **host.lamports.borrow_mut()+= host_fee;
-
-      let value: u64 = **game_state.lamports.borrow();
-
-      **game_state.lamports.borrow_mut()-= value;
-      **initializer.lamports.borrow_mut()+= value;
-
-    }
-
-    if gwins == true{
-      let host_fee: u64 = state.lamports/100;
-
-      **game_state.lamports.borrow_mut()-= host_fee;
-      **host.lamports.borrow_mut()+= host_fee;
-
-      let rew: u64 = (&state.lamports*2)-&host_fee;
-
-      **game_state.lamports.borrow_mut()-= rew;
-      **guest.lamports.borrow_mut()+= rew;
-
-      let value: u64 = **game_state.lamports.borrow();
-
-      **game_state.lamports.borrow_mut()-= value;
-      **initializer.lamports.borrow_mut()+= value;
+    let host_fee: u64 = Self::fee(state.lamports, rents.timeout_fee_bps);
+    let rew: u64 = if gwins {(state.lamports*2)-host_fee} else {0};
+
+    Self::pay(game_state, payees[2], host_fee, escrow.as_ref())?;
+    Self::pay(game_state, payees[1], rew, escrow.as_ref())?;
+    Self::release(game_state, initializer, payees[0], escrow.as_ref())?;
+
+    GameEvent::TimeoutClaimed{
+      game: game_state.key.to_bytes(),
+      winner: if iwins {state.initializer} else {state.guest},
+      host_fee: Self::fee(state.lamports, rents.timeout_fee_bps),
+    }.emit()?;
+
+    let wagered: u64 = Self::lamports_of(&state.mint, state.lamports);
+    let host_fee: u64 = Self::lamports_of(&state.mint, host_fee);
+    let ratings: Option<[u16; 2]> = Self::settle_ratings(&state, profiles, program_id, iwins, gwins)?;
+    let sides: [(&Pubkey, bool); 2] = [(&initializer_address, iwins), (&guest_address, gwins)];
+    for (side, (player, won)) in sides.into_iter().enumerate() {
+      Self::update_profile(profiles[side], player, program_id, |p| {
+        if let Some(ratings) = ratings {p.rating = ratings[side]; p.ranked_games += 1}
+        p.games_played += 1;
+        p.lamports_wagered += wagered;
+        if won {p.wins += 1; p.lamports_won += wagered*2 - host_fee} else {p.losses += 1}
+      })?;
     }
 
     Ok(())
@@ -443,53 +641,149 @@ impl Processor {
     let initializer: &AccountInfo<'_> = next_account_info(accounts_iter)?;
     let game_state: &AccountInfo<'_> = next_account_info(accounts_iter)?;
 
-    let state: GameState = GameState::try_from_slice(&game_state.data.borrow())?;
+    let mut state: GameState = Self::load_game(game_state, program_id)?;
 
+    let (escrow, payees) = Self::load_escrow(accounts_iter, game_state.key, &state.mint, [initializer], program_id)?;
 
   
     let initializer_address: Pubkey = Pubkey::new_from_array(initializer.key.to_bytes());
 
 
-    if initializer.key != &initializer_address{panic!()}
+    if initializer.key != &initializer_address {return Err(GameError::InvalidInitializer.into())}
+
 
+    let game_state_check: Pubkey = Self::game_address(initializer.key, &state, program_id)?;
 
-    let game_state_check: Pubkey = Pubkey::create_with_seed(initializer.key, &state.gameseed, program_id).unwrap();
 
+    if game_state.key != &game_state_check {return Err(GameError::InvalidGameAccount.into())}
+    state.status = status::transition(state.status, Event::Abort)?;
 
-    if game_state.key != &game_state_check{panic!()}
-    if state.initialized != 1 {panic!()}
+    if !initializer.is_signer {return Err(GameError::NotSigner.into())}
 
-    if !initializer.is_signer {panic!()}
+    if state.lobby_page != 0 {
+      let lobby: &AccountInfo<'_> = Self::next_lobby(accounts_iter)?;
+      Self::unlist_game(lobby, game_state.key, state.lobby_page, program_id)?;
+    }
+    if state.queued == 1 {
+      let queue: &AccountInfo<'_> = Self::next_queue(accounts_iter)?;
+      Self::dequeue(queue, game_state.key, state.lamports, program_id)?;
+    }
 
     let value: u64 = **game_state.lamports.borrow();
 
-    **game_state.lamports.borrow_mut()-= value;
-    **initializer.lamports.borrow_mut()+= value;
+    Self::release(game_state, initializer, payees[0], escrow.as_ref())?;
+
+    GameEvent::Aborted{ game: game_state.key.to_bytes(), refund: value }.emit()?;
+
+    Ok(())
+  }
+  fn enqueue(
+    accounts: &[AccountInfo],
+    q: Enqueue,
+    program_id: &Pubkey) -> ProgramResult {
+
+    let accounts_iter: &mut std::slice::Iter<'_, AccountInfo<'_>> = &mut accounts.iter();
 
+    let player: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+    let queue: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+    let game_state: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+    let host: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+    let rent_data: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+    let system_program_info: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+
+    if !player.is_signer {return Err(GameError::NotSigner.into())}
+
+    let bracket: u8 = stake_bracket(q.lamports);
+    Self::create_queue(player, queue, bracket, program_id)?;
+    let mut queue_account: MatchQueue = Self::load_queue(queue, bracket, program_id)?;
+
+    if queue_account.game == [0;32] {
+      // nobody is waiting: the player opens their own game and waits in it
+      let init: Init = Init{
+        bump:q.bump,
+        game_ends:QUEUE_GAME_ENDS,
+        gameseed:q.gameseed,
+        lamports:q.lamports,
+        game_hash:q.game_hash,
+        max_rounds:0,
+        sudden_death:0,
+        ruleset:rules::CLASSIC,
+        moves:0,
+        ranked:0,
+        min_rating:0,
+        max_rating:0,
+        invited:[0;32],
+        allowlist_root:[0;32],
+        mint:[0;32],
+        lobby_page:0,
+      };
+      Self::init_game(&[player.clone(), game_state.clone(), host.clone(), rent_data.clone(), system_program_info.clone()], init, program_id)?;
+
+      let mut state: GameState = GameState::load(&game_state.data.borrow())?;
+      state.queued = 1;
+      state.store(&mut game_state.data.borrow_mut())?;
+
+      queue_account.game = game_state.key.to_bytes();
+      queue_account.player = player.key.to_bytes();
+      queue_account.store(&mut queue.data.borrow_mut())?;
+    } else {
+      if game_state.key.to_bytes() != queue_account.game {return Err(GameError::InvalidQueue.into())}
+      if player.key.to_bytes() == queue_account.player {return Err(GameError::AlreadyQueued.into())}
+
+      // the player stakes the waiting game's lamports, which JoinGame takes
+      // from their wallet before it empties the queue
+      let join: Commit = Commit{ game_hash: q.game_hash, proof: vec![] };
+      Self::seat_guest(&[player.clone(), game_state.clone(), system_program_info.clone(), host.clone(), queue.clone()], program_id, join)?;
+    }
 
     Ok(())
   }
   fn update_rent(        
     accounts: &[AccountInfo], 
-    r: UpdateRent
+    r: UpdateRent,
+    program_id: &Pubkey
     ) -> ProgramResult {
 
       let accounts_iter: &mut std::slice::Iter<'_, AccountInfo<'_>> = &mut accounts.iter();
 
       let rent: &AccountInfo<'_> = next_account_info(accounts_iter)?;
       let authority: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+      let config: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+
+      Self::check_authority(authority, config, program_id)?;
+
+      if rent.owner != program_id {return Err(GameError::WrongAccountOwner.into())}
+      if r.win_fee_bps > 10_000 {return Err(GameError::InvalidConfig.into())}
+      if r.timeout_fee_bps > 10_000 {return Err(GameError::InvalidConfig.into())}
+      if r.move_timeout == 0 {return Err(GameError::InvalidConfig.into())}
+      if r.max_chat_len > CHAT_LINE_LEN {return Err(GameError::InvalidConfig.into())}
+
+      let rent_account: UpdateRent = UpdateRent{
+        is_init:1,
+        rent:r.rent,
+        version:RENT_VERSION,
+        win_fee_bps:r.win_fee_bps,
+        timeout_fee_bps:r.timeout_fee_bps,
+        move_timeout:r.move_timeout,
+        max_chat_len:r.max_chat_len,
+      };
+      let space: usize = rent_account.space()?;
+
+      // version 0 records are too small for the current layout, the authority pays for the growth
+      if rent.data_len() < space {
+        rent.realloc(space, false)?;
+        let top_up: u64 = Rent::get()?.minimum_balance(space).saturating_sub(**rent.lamports.borrow());
+        if top_up > 0 {
+          invoke(
+            &system_instruction::transfer(authority.key, rent.key, top_up),
+            &[authority.clone(), rent.clone()],
+          )?;
+        }
+      }
 
-      if !authority.is_signer {panic!()}
-
-      let authority_key: Pubkey = Pubkey::from_str("4YbLBRXwseG1NuyJbteSD5u81Q2QjFqJBp6JmxwYBKYm").unwrap();
-
-      if authority.key != &authority_key {panic!()}
-
-      let rent_account: UpdateRent = UpdateRent{is_init:1,rent:r.rent};
-  
-
-      rent_account.serialize(&mut &mut rent.data.borrow_mut()[..])?;
+      rent_account.store(&mut rent.data.borrow_mut())?;
 
+      GameEvent::ConfigUpdated{ authority: authority.key.to_bytes(), account: rent.key.to_bytes() }.emit()?;
 
     Ok(())
   }
@@ -504,19 +798,21 @@ impl Processor {
   
       let initializer: &AccountInfo<'_> = next_account_info(accounts_iter)?;
       let tournament: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+      let config: &AccountInfo<'_> = next_account_info(accounts_iter)?;
   
-      let authority = Pubkey::from_str("4YbLBRXwseG1NuyJbteSD5u81Q2QjFqJBp6JmxwYBKYm").unwrap();
-  
-      if initializer.key != &authority {panic!()}
-      if !initializer.is_signer{panic!()}
+      Self::check_authority(initializer, config, program_id)?;
+
+      if rules::move_count(t.ruleset, t.moves).is_none() {return Err(GameError::InvalidRuleset.into())}
+
+      let (escrow, _) = Self::load_escrow(accounts_iter, tournament.key, &t.mint, [], program_id)?;
 
       invoke_signed(
         &system_instruction::create_account( 
-            &initializer.key, 
-            &tournament.key,
+            initializer.key, 
+            tournament.key,
             t.rent,
-            87, //312???m
-            &program_id
+            t.space()? as u64,
+            program_id
         ),
         &[
           initializer.clone(), 
@@ -525,8 +821,16 @@ impl Processor {
         &[&[t.tournament_id.as_ref(), &[t.bump]]],
       )?;
   
-      t.serialize(&mut &mut tournament.data.borrow_mut()[..])?;
+      if let Some(escrow) = &escrow {
+        let mint: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+        if mint.key.to_bytes() != t.mint {return Err(GameError::InvalidTokenAccount.into())}
 
+        Self::create_escrow(initializer, escrow, mint)?;
+      }
+
+      t.store(&mut tournament.data.borrow_mut())?;
+
+      GameEvent::TournamentCreated{ tournament: tournament.key.to_bytes(), tournament_id: t.tournament_id }.emit()?;
 
     Ok(())
   }
@@ -543,17 +847,16 @@ impl Processor {
     let tournament = next_account_info(accounts_iter)?;
     let counter = next_account_info(accounts_iter)?;
 
-    let t = Tournament::try_from_slice(&tournament.data.borrow())?;
-    let mut t_counter = TournamentCounter::try_from_slice(&counter.data.borrow())?;
+    let t = Tournament::load(&tournament.data.borrow())?;
+    let mut t_counter = TournamentCounter::load(&counter.data.borrow())?;
 
-    let value = **tournament_account.lamports.borrow();
+    let (escrow, payees) = Self::load_escrow(accounts_iter, tournament.key, &t.mint, [participant], program_id)?;
+    let profiles: [Option<&AccountInfo<'_>>; 2] = Self::trailing_profiles(accounts_iter);
 
-    if value < t.entrance_fee{panic!()}
-    if tournament.is_writable{panic!()}
-    if tournament.owner != program_id{panic!()}
-    if t.is_init != 1 {panic!()}
-    if tournament_account.owner != program_id{panic!()}
-    if t_counter.player_participating >= t_counter.capacity{panic!()}
+    if tournament.is_writable {return Err(GameError::MustBeReadOnly.into())}
+    if tournament.owner != program_id {return Err(GameError::WrongAccountOwner.into())}
+    if t.is_init != 1 {return Err(GameError::TournamentNotInitialized.into())}
+    if t_counter.player_participating >= t_counter.capacity {return Err(GameError::CapacityFull.into())}
 
 
     t_counter.player_participating += 1;
@@ -579,19 +882,26 @@ impl Processor {
     empty_tournament_id += &str_tournament_id;
 
 
-    let mut opp = player_no + 1;
-
-    if player_no%2 == 0{
-      opp = player_no - 1;
-    }
+    let t_account = TournamentAccount{
+      player_find:str_no.clone(),
+      tournamentid:str_tournament_id,
+      player:participant.key.to_bytes(),
+      opponent:Self::bracket_opponent(player_no, 0),
+      level:0,
+      playerno_int:player_no,
+      opponent_played_on:t.starts_at,
+      is_playing:0,
+      waiting_opponent_to_join:0,
+      prize_claimed:0,
+    };
 
     invoke_signed(
       &system_instruction::create_account( 
-          &participant.key, 
-          &tournament_account.key,
+          participant.key, 
+          tournament_account.key,
           t.rent,
-          96, //312???m
-          &program_id
+          t_account.space()? as u64,
+          program_id
       ),
       &[
         participant.clone(), 
@@ -600,24 +910,32 @@ impl Processor {
       &[&[str_no.as_ref(), &[init.bump]]],
     )?;
 
-    let t_account = TournamentAccount{
-      player_find:str_no,
-      tournamentid:str_tournament_id,
-      player:participant.key.to_bytes(),
-      opponent:opp,
-      level:0,
-      playerno_int:player_no,
-      opponent_played_on:t.starts_at,
-      is_playing:0,
-      waiting_opponent_to_join:0
-    };
+    // token tournaments take the entrance fee into the escrow, the player
+    // account then only holds its rent
+    match &escrow {
+      Some(escrow) => Self::deposit(payees[0], participant, escrow, t.entrance_fee)?,
+      None => {
+        let value = **tournament_account.lamports.borrow();
+        if value < t.entrance_fee {return Err(GameError::InsufficientFunds.into())}
+      }
+    }
+
+    if tournament_account.owner != program_id {return Err(GameError::WrongAccountOwner.into())}
 
     if t_counter.player_participating == t_counter.capacity{
       t_counter.empty_tournament_id = empty_tournament_id;
     }
 
-    t_account.serialize(&mut &mut tournament_account.data.borrow_mut()[..])?;
-    t_counter.serialize(&mut &mut counter.data.borrow_mut()[..])?;
+    t_account.store(&mut tournament_account.data.borrow_mut())?;
+    t_counter.store(&mut counter.data.borrow_mut())?;
+
+    GameEvent::TournamentJoined{ tournament: tournament.key.to_bytes(), player: participant.key.to_bytes(), player_no }.emit()?;
+
+    Self::create_profile(participant, profiles[0], participant.key, program_id)?;
+    Self::update_profile(profiles[0], participant.key, program_id, |p| {
+      p.tournaments_joined += 1;
+      p.lamports_wagered += Self::lamports_of(&t.mint, t.entrance_fee);
+    })?;
 
     Ok(())
   }
@@ -634,28 +952,14 @@ impl Processor {
     let game_state: &AccountInfo<'_> = next_account_info(accounts_iter)?;
     let tournament: &AccountInfo<'_> = next_account_info(accounts_iter)?;
 
-    let t: Tournament = Tournament::try_from_slice(&tournament.data.borrow())?;
+    let t: Tournament = Tournament::load(&tournament.data.borrow())?;
 
-    let mut t_account: TournamentAccount = TournamentAccount::try_from_slice(&initializer_tour_acc.data.borrow())?;
+    let mut t_account: TournamentAccount = TournamentAccount::load(&initializer_tour_acc.data.borrow())?;
  
     
-    let mut substract:bool = false;
     let initializer_no: u32 = t_account.playerno_int;
-    let pwr: u32 = t_account.level as u32;
-    let mut pwrplus: u32 = t_account.level as u32;
-    pwrplus += 1;
-    let pwoftwo:u32 = 2;
-    let divisibleby: &u32 = &pwoftwo.pow(pwrplus);
-    if initializer_no%divisibleby == 0 {
-      substract = true;
-    }
-    let mut opponent_no: u32 = 0;
-    if substract == false {
-      opponent_no = &initializer_no + &pwoftwo.pow(pwr);
-    }
-    if substract == true {
-      opponent_no = &initializer_no - &pwoftwo.pow(pwr);
-    }
+    let opponent_no: u32 = Self::bracket_opponent(initializer_no, t_account.level);
+
     let mut game_seed: String = String::new();
     let opponent_no_str: &String = &opponent_no.to_string();
     let initializer_no_str: &String = &initializer_no.to_string();
@@ -677,41 +981,9 @@ impl Processor {
     game_id += &game_seed;
 
 
-    invoke_signed(
-      &system_instruction::create_account( 
-          &initializer.key, 
-          &game_state.key,
-          t.rent,
-          557, //312???m
-          &program_id
-      ),
-      &[
-        initializer.clone(), 
-        game_state.clone(),
-      ],
-      &[&[game_id.as_ref(), &[init.bump]]],
-    )?;
-
-    let initializer_address = Pubkey::new_from_array(initializer.key.to_bytes());
-
-
-
-    if initializer.key != &initializer_address{panic!()}
-
-
-    if !initializer.is_signer{panic!()}
-    if t.is_init != 1{panic!()}
-    if t.tournament_id != t_account.tournamentid{panic!()}
-    if tournament.owner != program_id{panic!()}
-    if game_state.owner != program_id{panic!()}
-    if initializer_tour_acc.owner != program_id{panic!()}
-    if tournament.is_writable {panic!()}
-
-
-
     let gamestate: TGameState = TGameState{
-      game_id:game_id,
-      initialized :3,
+      game_id:game_id.clone(),
+      status:GameStatus::Open,
       gameseed:"XXXXXXXXXX".to_string(),
       lamports:0,
       initializer: initializer.key.to_bytes(),
@@ -719,11 +991,11 @@ impl Processor {
       gamehash:init.game_hash,
       guest:[0;32],
 
-      whoseturn:0,
       guest_move:0,
       score_i:0,
       score_g:0,
       game_ends : t.game_ends,
+      rounds_played:0,
       lastplaytime:0,
       chat_line_1:"XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX".to_string(),
       chat_line_2:"XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX".to_string(),
@@ -734,11 +1006,49 @@ impl Processor {
 
     };
 
+    invoke_signed(
+      &system_instruction::create_account( 
+          initializer.key, 
+          game_state.key,
+          t.rent,
+          gamestate.space()? as u64,
+          program_id
+      ),
+      &[
+        initializer.clone(), 
+        game_state.clone(),
+      ],
+      &[&[game_id.as_ref(), &[init.bump]]],
+    )?;
+
+    let initializer_address = Pubkey::new_from_array(initializer.key.to_bytes());
+
+
+
+    if initializer.key != &initializer_address {return Err(GameError::InvalidInitializer.into())}
+
+
+    if !initializer.is_signer {return Err(GameError::NotSigner.into())}
+    if t.is_init != 1 {return Err(GameError::TournamentNotInitialized.into())}
+    if t.tournament_id != t_account.tournamentid {return Err(GameError::TournamentMismatch.into())}
+    if tournament.owner != program_id {return Err(GameError::WrongAccountOwner.into())}
+    if game_state.owner != program_id {return Err(GameError::WrongAccountOwner.into())}
+    if initializer_tour_acc.owner != program_id {return Err(GameError::WrongAccountOwner.into())}
+    if tournament.is_writable {return Err(GameError::MustBeReadOnly.into())}
+
+
     t_account.opponent = opponent_no;
     t_account.waiting_opponent_to_join = 1;
 
-    gamestate.serialize(&mut &mut game_state.data.borrow_mut()[..])?;
-    t_account.serialize(&mut &mut initializer_tour_acc.data.borrow_mut()[..])?;
+    gamestate.store(&mut game_state.data.borrow_mut())?;
+    t_account.store(&mut initializer_tour_acc.data.borrow_mut())?;
+
+    GameEvent::MatchCreated{
+      game: game_state.key.to_bytes(),
+      tournament: tournament.key.to_bytes(),
+      initializer: initializer.key.to_bytes(),
+      opponent_no,
+    }.emit()?;
 
     Ok(())
   }
@@ -758,32 +1068,33 @@ impl Processor {
     let opponent: &AccountInfo<'_> = next_account_info(accounts_iter)?;
     let tournament: &AccountInfo<'_> = next_account_info(accounts_iter)?;
 
-    let t: Tournament = Tournament::try_from_slice(&tournament.data.borrow())?;
+    let t: Tournament = Tournament::load(&tournament.data.borrow())?;
 
-    let o_t_account: TournamentAccount = TournamentAccount::try_from_slice(&opponent_tour_acc.data.borrow())?;
+    let o_t_account: TournamentAccount = TournamentAccount::load(&opponent_tour_acc.data.borrow())?;
     let opponent_address: Pubkey = Pubkey::new_from_array(o_t_account.player);
 
-    let t_account: TournamentAccount = TournamentAccount::try_from_slice(&initializer_tour_acc.data.borrow())?;
+    let t_account: TournamentAccount = TournamentAccount::load(&initializer_tour_acc.data.borrow())?;
     let initializer_address: Pubkey = Pubkey::new_from_array(t_account.player);
 
-    let state: TGameState = TGameState::try_from_slice(&game_state.data.borrow())?;
+    let state: TGameState = TGameState::load(&game_state.data.borrow())?;
 
 
-    if !opponent.is_signer{panic!()}
-    if t.is_init != 1{panic!()}
-    if t.tournament_id != t_account.tournamentid{panic!()}
-    if t.tournament_id != o_t_account.tournamentid{panic!()}
-    if o_t_account.level == t_account.level {panic!()}
-    if o_t_account.playerno_int == t_account.opponent {panic!()}
-    if opponent.key != &opponent_address{panic!()}
-    if initializer.key != &initializer_address{panic!()}
-    if state.initializer != t_account.player {panic!()}
-    if tournament.owner != program_id{panic!()}
-    if opponent_tour_acc.owner != program_id{panic!()}
-    if initializer_tour_acc.owner != program_id{panic!()}
-    if game_state.owner != program_id{panic!()}
-    if state.initialized != 3 {panic!()}
-    if tournament.is_writable {panic!()}
+    if !opponent.is_signer {return Err(GameError::NotSigner.into())}
+    if t.is_init != 1 {return Err(GameError::TournamentNotInitialized.into())}
+    if t.tournament_id != t_account.tournamentid {return Err(GameError::TournamentMismatch.into())}
+    if t.tournament_id != o_t_account.tournamentid {return Err(GameError::TournamentMismatch.into())}
+    if o_t_account.level != t_account.level {return Err(GameError::OpponentMismatch.into())}
+    if o_t_account.playerno_int != t_account.opponent {return Err(GameError::OpponentMismatch.into())}
+    if opponent.key != &opponent_address {return Err(GameError::InvalidGuest.into())}
+    if initializer.key != &initializer_address {return Err(GameError::InvalidInitializer.into())}
+    if state.initializer != t_account.player {return Err(GameError::InvalidInitializer.into())}
+    if tournament.owner != program_id {return Err(GameError::WrongAccountOwner.into())}
+    if opponent_tour_acc.owner != program_id {return Err(GameError::WrongAccountOwner.into())}
+    if initializer_tour_acc.owner != program_id {return Err(GameError::WrongAccountOwner.into())}
+    if game_state.owner != program_id {return Err(GameError::WrongAccountOwner.into())}
+    let status: GameStatus = status::transition(state.status, Event::Join{turn:1})?;
+    if tournament.is_writable {return Err(GameError::MustBeReadOnly.into())}
+    if !rules::is_valid_move(t.ruleset, t.moves, join.mymove) {return Err(GameError::InvalidMove.into())}
 
 
     let clock: Clock= Clock::get()?;
@@ -791,18 +1102,18 @@ impl Processor {
 
     let gamestate: TGameState = TGameState{
       game_id:state.game_id,
-      initialized:4,
+      status,
       gameseed:state.gameseed,
       lamports:state.lamports,
       initializer: state.initializer,
       gamehash: state.gamehash,
       guest: opponent.key.to_bytes(),
-      whoseturn:1,
       guest_move:join.mymove,
       score_i:state.score_i,
       score_g:state.score_g,
       game_ends:state.game_ends,
-      lastplaytime:state.lastplaytime,
+      rounds_played:state.rounds_played,
+      lastplaytime:current_time,
       chat_line_1: state.chat_line_1,
       chat_line_2: state.chat_line_2,
       chat_line_3: state.chat_line_3,
@@ -816,12 +1127,13 @@ impl Processor {
       player_find:o_t_account.player_find,
       tournamentid:o_t_account.tournamentid,
       player:o_t_account.player,
-      opponent:o_t_account.opponent,
+      opponent:t_account.playerno_int,
       level:o_t_account.level,
       playerno_int:o_t_account.playerno_int,
       opponent_played_on:o_t_account.opponent_played_on,
       is_playing:1,
       waiting_opponent_to_join:o_t_account.waiting_opponent_to_join,
+      prize_claimed:o_t_account.prize_claimed,
     };
 
     let initializer_tounament_account: TournamentAccount = TournamentAccount{
@@ -834,11 +1146,14 @@ impl Processor {
       opponent_played_on:current_time,
       is_playing:1,
       waiting_opponent_to_join:0,
+      prize_claimed:t_account.prize_claimed,
     };
 
-    opponent_tounament_account.serialize(&mut &mut opponent_tour_acc.data.borrow_mut()[..])?;
-    initializer_tounament_account.serialize(&mut &mut initializer_tour_acc.data.borrow_mut()[..])?;
-    gamestate.serialize(&mut &mut game_state.data.borrow_mut()[..])?;
+    opponent_tounament_account.store(&mut opponent_tour_acc.data.borrow_mut())?;
+    initializer_tounament_account.store(&mut initializer_tour_acc.data.borrow_mut())?;
+    gamestate.store(&mut game_state.data.borrow_mut())?;
+
+    GameEvent::GameJoined{ game: game_state.key.to_bytes(), guest: opponent.key.to_bytes() }.emit()?;
 
     Ok(())
   }
@@ -858,77 +1173,80 @@ impl Processor {
     let game_state: &AccountInfo<'_> = next_account_info(accounts_iter)?;
     let tournament: &AccountInfo<'_> = next_account_info(accounts_iter)?;
 
-    let mut state: TGameState = TGameState::try_from_slice(&game_state.data.borrow())?;
+    let mut state: TGameState = TGameState::load(&game_state.data.borrow())?;
+
+    let t: Tournament = Tournament::load(&tournament.data.borrow())?;
 
-    let t: Tournament = Tournament::try_from_slice(&tournament.data.borrow())?;
+    let (escrow, payees) = Self::load_escrow(accounts_iter, tournament.key, &t.mint, [initializer, opponent], program_id)?;
+    let profiles: [Option<&AccountInfo<'_>>; 2] = Self::trailing_profiles(accounts_iter);
 
-    let mut opponent_tournament_account: TournamentAccount = TournamentAccount::try_from_slice(&opponent_tour_acc.data.borrow())?;
-    let mut initializer_tournament_account: TournamentAccount = TournamentAccount::try_from_slice(&initializer_tour_acc.data.borrow())?;
+    let mut opponent_tournament_account: TournamentAccount = TournamentAccount::load(&opponent_tour_acc.data.borrow())?;
+    let mut initializer_tournament_account: TournamentAccount = TournamentAccount::load(&initializer_tour_acc.data.borrow())?;
 
     let opponent_address: Pubkey = Pubkey::new_from_array(opponent_tournament_account.player);
     let initializer_address: Pubkey = Pubkey::new_from_array(initializer_tournament_account.player);
 
 
-    if t.is_init != 1{panic!()}
-    if t.tournament_id != initializer_tournament_account.tournamentid{panic!()}
-    if t.tournament_id != opponent_tournament_account.tournamentid{panic!()}
-    if state.initializer != initializer_tournament_account.player{panic!()}
-    if state.guest != opponent_tournament_account.player{panic!()}
-    if opponent_tournament_account.level == initializer_tournament_account.level {panic!()}
-    if opponent_tournament_account.playerno_int == initializer_tournament_account.opponent {panic!()}
-    if opponent_tournament_account.opponent == initializer_tournament_account.playerno_int {panic!()}
-    if opponent.key != &opponent_address{panic!()}
-    if initializer.key != &initializer_address{panic!()}
-    if tournament.owner != program_id{panic!()}
-    if opponent_tour_acc.owner != program_id{panic!()}
-    if initializer_tour_acc.owner != program_id{panic!()}
-    if game_state.owner != program_id{panic!()}
-    if tournament.is_writable {panic!()}
-    if opponent_tournament_account.is_playing != 1{panic!()}
-    if initializer_tournament_account.is_playing != 1{panic!()}
+    if t.is_init != 1 {return Err(GameError::TournamentNotInitialized.into())}
+    if t.tournament_id != initializer_tournament_account.tournamentid {return Err(GameError::TournamentMismatch.into())}
+    if t.tournament_id != opponent_tournament_account.tournamentid {return Err(GameError::TournamentMismatch.into())}
+    if state.initializer != initializer_tournament_account.player {return Err(GameError::InvalidInitializer.into())}
+    if state.guest != opponent_tournament_account.player {return Err(GameError::InvalidGuest.into())}
+    if opponent_tournament_account.level != initializer_tournament_account.level {return Err(GameError::OpponentMismatch.into())}
+    if opponent_tournament_account.playerno_int != initializer_tournament_account.opponent {return Err(GameError::OpponentMismatch.into())}
+    if opponent_tournament_account.opponent != initializer_tournament_account.playerno_int {return Err(GameError::OpponentMismatch.into())}
+    if opponent.key != &opponent_address {return Err(GameError::InvalidGuest.into())}
+    if initializer.key != &initializer_address {return Err(GameError::InvalidInitializer.into())}
+    if !initializer.is_signer {return Err(GameError::NotSigner.into())}
+    if tournament.owner != program_id {return Err(GameError::WrongAccountOwner.into())}
+    if opponent_tour_acc.owner != program_id {return Err(GameError::WrongAccountOwner.into())}
+    if initializer_tour_acc.owner != program_id {return Err(GameError::WrongAccountOwner.into())}
+    if game_state.owner != program_id {return Err(GameError::WrongAccountOwner.into())}
+    if tournament.is_writable {return Err(GameError::MustBeReadOnly.into())}
+    if opponent_tournament_account.is_playing != 1 {return Err(GameError::InvalidPlayerState.into())}
+    if initializer_tournament_account.is_playing != 1 {return Err(GameError::InvalidPlayerState.into())}
 
 
-    if state.initialized != 4 {panic!()}
-    if play.lastmove > 3 {panic!()}
-    if play.lastmove < 1 {panic!()}
-    if state.whoseturn != 1 {panic!()}
+    state.status = status::transition(state.status, Event::Move{player:1})?;
+    if !rules::is_valid_move(t.ruleset, t.moves, play.lastmove) {return Err(GameError::InvalidMove.into())}
 
 
     let clock: Clock= Clock::get()?;
     let current_time: u64 = clock.unix_timestamp as u64;
 
-    let last_game_hash: keccak::Hash = keccak::hashv(&[&play.last_round_seed.to_string().as_bytes(),play.lastmove.to_string().as_ref(),&play.last_round_seed.to_string().as_bytes()]);
+    let last_game_hash: [u8;32] = commitment::commitment(game_state.key, state.rounds_played, initializer.key, play.lastmove, &play.salt);
 
 
-    if last_game_hash.0 != state.gamehash{panic!()}
+    if last_game_hash != state.gamehash {return Err(GameError::HashMismatch.into())}
 
+    Self::create_profile(initializer, profiles[0], &initializer_address, program_id)?;
+    Self::create_profile(initializer, profiles[1], &opponent_address, program_id)?;
 
 
     state.lastplaytime = current_time;
-    state.whoseturn = 2;
     state.gamehash = play.new_game_hash;
     
+    let level: u8 = initializer_tournament_account.level;
     let mut iwins:bool=false;
     let mut gwins:bool=false;
 
-    if state.guest_move == 1 {
-
-      if play.lastmove == 2 {state.score_g += 1}
-      if play.lastmove == 3 {state.score_i += 1}
-    }
-    if state.guest_move == 2 {
-
-      if play.lastmove == 3 {state.score_g += 1}
-      if play.lastmove == 1 {state.score_i += 1}
-    }
-    if state.guest_move == 3 {
-
-      if play.lastmove == 1 {state.score_g += 1}
-      if play.lastmove == 2 {state.score_i += 1}
-    }
+    let winner: u8 = match rules::resolve(t.ruleset, t.moves, play.lastmove, state.guest_move)? {
+      Outcome::InitializerWins => {state.score_i += 1; 1}
+      Outcome::GuestWins => {state.score_g += 1; 2}
+      Outcome::Draw => 0
+    };
 
-    state.serialize(&mut &mut game_state.data.borrow_mut()[..])?;
+    GameEvent::RoundResolved{
+      game: game_state.key.to_bytes(),
+      round: state.rounds_played,
+      initializer_move: play.lastmove,
+      guest_move: state.guest_move,
+      winner,
+      score_i: state.score_i,
+      score_g: state.score_g,
+    }.emit()?;
 
+    state.rounds_played = state.rounds_played.saturating_add(1);
 
     if state.game_ends == state.score_g{
       gwins = true;
@@ -936,6 +1254,11 @@ impl Processor {
     if state.game_ends == state.score_i{
       iwins = true;
     }
+    if iwins || gwins {
+      state.status = status::transition(state.status, Event::End{winner: if iwins {1} else {2}})?;
+    }
+
+    state.store(&mut game_state.data.borrow_mut())?;
 
     let mut the_no = 0;
     if initializer_tournament_account.playerno_int>opponent_tournament_account.playerno_int{
@@ -946,14 +1269,13 @@ impl Processor {
     }
 
 
-    if iwins == true{
+    if iwins{
 
       let us: usize = opponent_tournament_account.level as usize;
       let multiply: u64 = t.lvl_get[us] as u64; 
       let reward:u64 = multiply*t.entrance_fee;
 
-      **opponent_tour_acc.lamports.borrow_mut()-= reward;
-      **opponent.lamports.borrow_mut()+= reward;
+      Self::pay(opponent_tour_acc, payees[1], reward, escrow.as_ref())?;
 
       let value: u64 = **opponent_tour_acc.lamports.borrow();
 
@@ -979,19 +1301,26 @@ impl Processor {
       initializer_tournament_account.waiting_opponent_to_join = 0;
       initializer_tournament_account.level += 1;
       initializer_tournament_account.playerno_int = the_no;
+      initializer_tournament_account.opponent = Self::bracket_opponent(the_no, initializer_tournament_account.level);
       initializer_tournament_account.opponent_played_on = current_time;
 
-      initializer_tournament_account.serialize(&mut &mut initializer_tour_acc.data.borrow_mut()[..])?;
+      initializer_tournament_account.store(&mut initializer_tour_acc.data.borrow_mut())?;
+
+      GameEvent::PlayerAdvanced{
+        tournament: tournament.key.to_bytes(),
+        player: initializer_tournament_account.player,
+        player_no: the_no,
+        level: initializer_tournament_account.level,
+      }.emit()?;
 
     }
-    if gwins == true{
+    if gwins{
       let us: usize = initializer_tournament_account.level as usize;
       let multiply: u64 = t.lvl_get[us] as u64; 
       let reward:u64 = multiply*t.entrance_fee;
 
 
-      **initializer_tour_acc.lamports.borrow_mut()-= reward;
-      **initializer.lamports.borrow_mut()+= reward;
+      Self::pay(initializer_tour_acc, payees[0], reward, escrow.as_ref())?;
 
       let value: u64 = **initializer_tour_acc.lamports.borrow();
 
@@ -1001,7 +1330,7 @@ impl Processor {
       let game: u64 = **game_state.lamports.borrow();
 
       **game_state.lamports.borrow_mut()-= game;
-      **initializer_tour_acc.lamports.borrow_mut()+= game;
+      **opponent_tour_acc.lamports.borrow_mut()+= game;
 
       let str_player_no: String = the_no.to_string();
       let mut somestr: String = String::from("pppppppppp");
@@ -1017,10 +1346,36 @@ impl Processor {
       opponent_tournament_account.waiting_opponent_to_join = 0;
       opponent_tournament_account.level += 1;
       opponent_tournament_account.playerno_int = the_no;
+      opponent_tournament_account.opponent = Self::bracket_opponent(the_no, opponent_tournament_account.level);
       opponent_tournament_account.opponent_played_on = current_time;
 
-      opponent_tournament_account.serialize(&mut &mut opponent_tour_acc.data.borrow_mut()[..])?;
+      opponent_tournament_account.store(&mut opponent_tour_acc.data.borrow_mut())?;
+
+      GameEvent::PlayerAdvanced{
+        tournament: tournament.key.to_bytes(),
+        player: opponent_tournament_account.player,
+        player_no: the_no,
+        level: opponent_tournament_account.level,
+      }.emit()?;
+
+    }
 
+    // the loser is paid back the share of the entrance fee set for this level
+    let loser_get: u64 = Self::lamports_of(&t.mint, t.lvl_get[level as usize] as u64 * t.entrance_fee);
+    let over: bool = iwins || gwins;
+    let sides: [(&Pubkey, u8, bool, bool, u8); 2] = [
+      (&initializer_address, play.lastmove, iwins, gwins, initializer_tournament_account.level),
+      (&opponent_address, state.guest_move, gwins, iwins, opponent_tournament_account.level),
+    ];
+    for (side, (player, mymove, won, lost, reached)) in sides.into_iter().enumerate() {
+      Self::update_profile(profiles[side], player, program_id, |p| {
+        p.rounds_played += 1;
+        Self::count_move(p, mymove);
+        if !over {return}
+        p.games_played += 1;
+        if won {p.wins += 1; p.best_level = p.best_level.max(reached)}
+        if lost {p.losses += 1; p.lamports_won += loser_get}
+      })?;
     }
 
 
@@ -1041,40 +1396,39 @@ impl Processor {
     let game_state: &AccountInfo<'_> = next_account_info(accounts_iter)?;
     let tournament: &AccountInfo<'_> = next_account_info(accounts_iter)?;
 
-    let mut state: GameState = GameState::try_from_slice(&game_state.data.borrow())?;
+    let mut state: TGameState = TGameState::load(&game_state.data.borrow())?;
 
-    let t: Tournament = Tournament::try_from_slice(&tournament.data.borrow())?;
+    let t: Tournament = Tournament::load(&tournament.data.borrow())?;
 
-    let mut opponent_tournament_account: TournamentAccount = TournamentAccount::try_from_slice(&opponent_tour_acc.data.borrow())?;
+    let opponent_tournament_account: TournamentAccount = TournamentAccount::load(&opponent_tour_acc.data.borrow())?;
 
-    let mut initializer_tournament_account: TournamentAccount = TournamentAccount::try_from_slice(&initializer_tour_acc.data.borrow())?;
+    let initializer_tournament_account: TournamentAccount = TournamentAccount::load(&initializer_tour_acc.data.borrow())?;
 
     let opponent_address: Pubkey = Pubkey::new_from_array(opponent_tournament_account.player);
     let initializer_address: Pubkey = Pubkey::new_from_array(initializer_tournament_account.player);
 
-    if t.is_init != 1{panic!()}
-    if t.tournament_id != initializer_tournament_account.tournamentid{panic!()}
-    if t.tournament_id != opponent_tournament_account.tournamentid{panic!()}
-    if state.initializer != initializer_tournament_account.player{panic!()}
-    if state.guest != opponent_tournament_account.player{panic!()}
-    if opponent_tournament_account.level == initializer_tournament_account.level {panic!()}
-    if opponent_tournament_account.playerno_int == initializer_tournament_account.opponent {panic!()}
-    if opponent_tournament_account.opponent == initializer_tournament_account.playerno_int {panic!()}
-    if opponent.key != &opponent_address{panic!()}
-    if initializer.key != &initializer_address{panic!()}
-    if tournament.owner != program_id{panic!()}
-    if opponent_tour_acc.owner != program_id{panic!()}
-    if initializer_tour_acc.owner != program_id{panic!()}
-    if game_state.owner != program_id{panic!()}
-    if tournament.is_writable {panic!()}
-    if opponent_tournament_account.is_playing != 1{panic!()}
-    if initializer_tournament_account.is_playing != 1{panic!()}
-
-
-    if state.initialized != 4 {panic!()}
-    if play.lastmove > 3 {panic!()}
-    if play.lastmove < 1 {panic!()}
-    if state.whoseturn != 2 {panic!()}
+    if t.is_init != 1 {return Err(GameError::TournamentNotInitialized.into())}
+    if t.tournament_id != initializer_tournament_account.tournamentid {return Err(GameError::TournamentMismatch.into())}
+    if t.tournament_id != opponent_tournament_account.tournamentid {return Err(GameError::TournamentMismatch.into())}
+    if state.initializer != initializer_tournament_account.player {return Err(GameError::InvalidInitializer.into())}
+    if state.guest != opponent_tournament_account.player {return Err(GameError::InvalidGuest.into())}
+    if opponent_tournament_account.level != initializer_tournament_account.level {return Err(GameError::OpponentMismatch.into())}
+    if opponent_tournament_account.playerno_int != initializer_tournament_account.opponent {return Err(GameError::OpponentMismatch.into())}
+    if opponent_tournament_account.opponent != initializer_tournament_account.playerno_int {return Err(GameError::OpponentMismatch.into())}
+    if opponent.key != &opponent_address {return Err(GameError::InvalidGuest.into())}
+    if initializer.key != &initializer_address {return Err(GameError::InvalidInitializer.into())}
+    if !opponent.is_signer {return Err(GameError::NotSigner.into())}
+    if tournament.owner != program_id {return Err(GameError::WrongAccountOwner.into())}
+    if opponent_tour_acc.owner != program_id {return Err(GameError::WrongAccountOwner.into())}
+    if initializer_tour_acc.owner != program_id {return Err(GameError::WrongAccountOwner.into())}
+    if game_state.owner != program_id {return Err(GameError::WrongAccountOwner.into())}
+    if tournament.is_writable {return Err(GameError::MustBeReadOnly.into())}
+    if opponent_tournament_account.is_playing != 1 {return Err(GameError::InvalidPlayerState.into())}
+    if initializer_tournament_account.is_playing != 1 {return Err(GameError::InvalidPlayerState.into())}
+
+
+    state.status = status::transition(state.status, Event::Move{player:2})?;
+    if !rules::is_valid_move(t.ruleset, t.moves, play.lastmove) {return Err(GameError::InvalidMove.into())}
 
 
     let clock: Clock= Clock::get()?;
@@ -1082,175 +1436,63 @@ impl Processor {
 
 
     state.lastplaytime = current_time;
-    state.whoseturn = 1;
+    state.guest_move = play.lastmove;
 
+    state.store(&mut game_state.data.borrow_mut())?;
 
-    let mut iwins:bool=false;
-    let mut gwins:bool=false;
+    GameEvent::MoveRevealed{ game: game_state.key.to_bytes(), player: state.guest, round: state.rounds_played }.emit()?;
 
-    if state.guest_move == 1 {
+    Ok(())
+  }
+  fn eliminate_player_who_hasnt_initialized_his_game(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,) -> ProgramResult {
 
-      if play.lastmove == 2 {state.score_g += 1}
-      if play.lastmove == 3 {state.score_i += 1}
-    }
-    if state.guest_move == 2 {
 
-      if play.lastmove == 3 {state.score_g += 1}
-      if play.lastmove == 1 {state.score_i += 1}
-    }
-    if state.guest_move == 3 {
+    let accounts_iter: &mut std::slice::Iter<'_, AccountInfo<'_>> = &mut accounts.iter();
 
-      if play.lastmove == 1 {state.score_g += 1}
-      if play.lastmove == 2 {state.score_i += 1}
-    }
+    let eliminate: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+    let eliminate_tour_acc: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+    let opponent: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+    let opponent_tour_acc: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+    let tournament: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+    //let host = next_account_info(accounts_iter)?;
 
-    state.serialize(&mut &mut game_state.data.borrow_mut()[..])?;
+    let t: Tournament = Tournament::load(&tournament.data.borrow())?;
 
+    let (escrow, payees) = Self::load_escrow(accounts_iter, tournament.key, &t.mint, [eliminate], program_id)?;
+    let profiles: [Option<&AccountInfo<'_>>; 2] = Self::trailing_profiles(accounts_iter);
 
-    if state.game_ends == state.score_g{
-      gwins = true;
-    }
-    if state.game_ends == state.score_i{
-      iwins = true;
-    }
-
-    let mut the_no: u32 = 0;
-    if initializer_tournament_account.playerno_int>opponent_tournament_account.playerno_int{
-      the_no = initializer_tournament_account.playerno_int;
-    }
-    if initializer_tournament_account.playerno_int<opponent_tournament_account.playerno_int{
-      the_no = opponent_tournament_account.playerno_int;
-    }
-
-
-    if iwins == true{
-
-      let us: usize = opponent_tournament_account.level as usize;
-      let multiply: u64 = t.lvl_get[us] as u64; 
-      let reward:u64 = multiply*t.entrance_fee;
-
-      **opponent_tour_acc.lamports.borrow_mut()-= reward;
-      **opponent.lamports.borrow_mut()+= reward;
-
-      let value: u64 = **opponent_tour_acc.lamports.borrow();
-
-      **opponent_tour_acc.lamports.borrow_mut()-= value;
-      **initializer_tour_acc.lamports.borrow_mut()+= value;
-
-      let game: u64 = **game_state.lamports.borrow();
-
-      **game_state.lamports.borrow_mut()-= game;
-      **initializer_tour_acc.lamports.borrow_mut()+= game;
-
-      let str_player_no: String = the_no.to_string();
-      let mut somestr: String = String::from("pppppppppp");
-      let len: usize = str_player_no.len();
-      somestr.replace_range(somestr.len() - len.., &str_player_no);
-
-      let offset2: usize = somestr.len();
-      let mut find_me: String = initializer_tournament_account.player_find;
-      find_me.replace_range(..offset2,&somestr);
-
-      initializer_tournament_account.player_find = find_me;
-      initializer_tournament_account.is_playing = 0;
-      initializer_tournament_account.waiting_opponent_to_join = 0;
-      initializer_tournament_account.level += 1;
-      initializer_tournament_account.playerno_int = the_no;
-      initializer_tournament_account.opponent_played_on = current_time;
-
-      initializer_tournament_account.serialize(&mut &mut initializer_tour_acc.data.borrow_mut()[..])?;
-
-    }
-    if gwins == true{
-      let us: usize = initializer_tournament_account.level as usize;
-      let multiply: u64 = t.lvl_get[us] as u64; 
-      let reward:u64 = multiply*t.entrance_fee;
-
-
-      **initializer_tour_acc.lamports.borrow_mut()-= reward;
-      **initializer.lamports.borrow_mut()+= reward;
-
-      let value: u64 = **initializer_tour_acc.lamports.borrow();
-
-      **initializer_tour_acc.lamports.borrow_mut()-= value;
-      **opponent_tour_acc.lamports.borrow_mut()+= value;
-
-      let game: u64 = **game_state.lamports.borrow();
-
-      **game_state.lamports.borrow_mut()-= game;
-      **initializer_tour_acc.lamports.borrow_mut()+= game;
-
-      let str_player_no: String = the_no.to_string();
-      let mut somestr: String = String::from("pppppppppp");
-      let len: usize = str_player_no.len();
-      somestr.replace_range(somestr.len() - len.., &str_player_no);
-
-      let offset2: usize = somestr.len();
-      let mut find_me: String = opponent_tournament_account.player_find;
-      find_me.replace_range(..offset2,&somestr);
-
-      opponent_tournament_account.player_find = find_me;
-      opponent_tournament_account.is_playing = 0;
-      opponent_tournament_account.waiting_opponent_to_join = 0;
-      opponent_tournament_account.level += 1;
-      opponent_tournament_account.playerno_int = the_no;
-      opponent_tournament_account.opponent_played_on = current_time;
-
-      opponent_tournament_account.serialize(&mut &mut opponent_tour_acc.data.borrow_mut()[..])?;
-
-    }
-
-
-    Ok(())
-  }
-  fn eliminate_player_who_hasnt_initialized_his_game(
-    accounts: &[AccountInfo],
-    program_id: &Pubkey,) -> ProgramResult {
-
-
-    let accounts_iter: &mut std::slice::Iter<'_, AccountInfo<'_>> = &mut accounts.iter();
-
-    let eliminate: &AccountInfo<'_> = next_account_info(accounts_iter)?;
-    let eliminate_tour_acc: &AccountInfo<'_> = next_account_info(accounts_iter)?;
-    let opponent: &AccountInfo<'_> = next_account_info(accounts_iter)?;
-    let opponent_tour_acc: &AccountInfo<'_> = next_account_info(accounts_iter)?;
-    let tournament: &AccountInfo<'_> = next_account_info(accounts_iter)?;
-    //let host = next_account_info(accounts_iter)?;
-
-    let t: Tournament = Tournament::try_from_slice(&tournament.data.borrow())?;
-
-    let el_t_account: TournamentAccount = TournamentAccount::try_from_slice(&eliminate_tour_acc.data.borrow())?;
+    let el_t_account: TournamentAccount = TournamentAccount::load(&eliminate_tour_acc.data.borrow())?;
 
     let el_check: Pubkey = Pubkey::new_from_array(el_t_account.player);
 
-    let mut opponent_tournament_account: TournamentAccount = TournamentAccount::try_from_slice(&opponent_tour_acc.data.borrow())?;
+    let mut opponent_tournament_account: TournamentAccount = TournamentAccount::load(&opponent_tour_acc.data.borrow())?;
 
     let opponent_check: Pubkey = Pubkey::new_from_array(opponent_tournament_account.player);
 
 
-    if t.is_init != 1{panic!()}
-    if t.tournament_id != el_t_account.tournamentid{panic!()}
-    if t.tournament_id != opponent_tournament_account.tournamentid{panic!()}
-    if eliminate.key != &el_check{panic!()}
-    if opponent.key != &opponent_check{panic!()}
-    if tournament.owner != program_id{panic!()}
-    if el_t_account.waiting_opponent_to_join != 0 {panic!()}
-    if eliminate_tour_acc.owner != program_id{panic!()}
-    if tournament.is_writable {panic!()}
-    if el_t_account.is_playing != 0  {panic!()}
-    if el_t_account.opponent != opponent_tournament_account.playerno_int{panic!()}
-    if el_t_account.playerno_int != opponent_tournament_account.opponent{panic!()}
-    if el_t_account.level != opponent_tournament_account.level{panic!()}
-
-    if el_t_account.level == 0{
-      if el_t_account.playerno_int % 2 == 0 {panic!()}
-    }
+    if t.is_init != 1 {return Err(GameError::TournamentNotInitialized.into())}
+    if t.tournament_id != el_t_account.tournamentid {return Err(GameError::TournamentMismatch.into())}
+    if t.tournament_id != opponent_tournament_account.tournamentid {return Err(GameError::TournamentMismatch.into())}
+    if eliminate.key != &el_check {return Err(GameError::InvalidGuest.into())}
+    if opponent.key != &opponent_check {return Err(GameError::InvalidGuest.into())}
+    if tournament.owner != program_id {return Err(GameError::WrongAccountOwner.into())}
+    if el_t_account.waiting_opponent_to_join != 0 {return Err(GameError::InvalidPlayerState.into())}
+    if eliminate_tour_acc.owner != program_id {return Err(GameError::WrongAccountOwner.into())}
+    if tournament.is_writable {return Err(GameError::MustBeReadOnly.into())}
+    if el_t_account.is_playing != 0 {return Err(GameError::InvalidPlayerState.into())}
+    if el_t_account.opponent != opponent_tournament_account.playerno_int {return Err(GameError::OpponentMismatch.into())}
+    if el_t_account.playerno_int != opponent_tournament_account.opponent {return Err(GameError::OpponentMismatch.into())}
+    if el_t_account.level != opponent_tournament_account.level {return Err(GameError::OpponentMismatch.into())}
+
+    if el_t_account.level == 0 && el_t_account.playerno_int.is_multiple_of(2) {return Err(GameError::OpponentMismatch.into())}
 
     let clock: Clock= Clock::get()?;
     let current_time: u64 = clock.unix_timestamp as u64;
     let time_passed: u64 = current_time - el_t_account.opponent_played_on;
 
-    if time_passed < t.time_is_up {panic!()}
+    if time_passed < t.time_is_up {return Err(GameError::TimeoutNotReached.into())}
 
     let mut the_no: u32 = 0;
     if el_t_account.playerno_int>opponent_tournament_account.playerno_int{
@@ -1264,8 +1506,7 @@ impl Processor {
     let multiply: u64 = t.lvl_get[us] as u64;
     let reward:u64 = multiply*t.entrance_fee;
 
-    **eliminate_tour_acc.lamports.borrow_mut()-= reward;
-    **eliminate.lamports.borrow_mut()+= reward;
+    Self::pay(eliminate_tour_acc, payees[0], reward, escrow.as_ref())?;
 
     let value: u64 = **eliminate_tour_acc.lamports.borrow();
 
@@ -1286,9 +1527,22 @@ impl Processor {
     opponent_tournament_account.waiting_opponent_to_join = 0;
     opponent_tournament_account.level += 1;
     opponent_tournament_account.playerno_int = the_no;
+    opponent_tournament_account.opponent = Self::bracket_opponent(the_no, opponent_tournament_account.level);
+    opponent_tournament_account.opponent_played_on = current_time;
+
+    opponent_tournament_account.store(&mut opponent_tour_acc.data.borrow_mut())?;
 
-    opponent_tournament_account.serialize(&mut &mut opponent_tour_acc.data.borrow_mut()[..])?;
+    GameEvent::PlayerAdvanced{
+      tournament: tournament.key.to_bytes(),
+      player: opponent_tournament_account.player,
+      player_no: the_no,
+      level: opponent_tournament_account.level,
+    }.emit()?;
 
+    Self::update_profile(profiles[0], &el_check, program_id, |p| p.lamports_won += Self::lamports_of(&t.mint, reward))?;
+    Self::update_profile(profiles[1], &opponent_check, program_id, |p| {
+      p.best_level = p.best_level.max(opponent_tournament_account.level);
+    })?;
 
     Ok(())
   }
@@ -1307,37 +1561,41 @@ impl Processor {
     let tournament: &AccountInfo<'_> = next_account_info(accounts_iter)?;
     //let host = next_account_info(accounts_iter)?;
 
-    let state: GameState = GameState::try_from_slice(&game_state.data.borrow())?;
+    let mut state: TGameState = TGameState::load(&game_state.data.borrow())?;
 
-    let t: Tournament = Tournament::try_from_slice(&tournament.data.borrow())?;
+    let t: Tournament = Tournament::load(&tournament.data.borrow())?;
 
-    let mut opponent_tournament_account: TournamentAccount = TournamentAccount::try_from_slice(&opponent_tour_acc.data.borrow())?;
+    let (escrow, payees) = Self::load_escrow(accounts_iter, tournament.key, &t.mint, [initializer, opponent], program_id)?;
+    let profiles: [Option<&AccountInfo<'_>>; 2] = Self::trailing_profiles(accounts_iter);
 
-    let mut initializer_tournament_account: TournamentAccount = TournamentAccount::try_from_slice(&initializer_tour_acc.data.borrow())?;
+    let mut opponent_tournament_account: TournamentAccount = TournamentAccount::load(&opponent_tour_acc.data.borrow())?;
+
+    let mut initializer_tournament_account: TournamentAccount = TournamentAccount::load(&initializer_tour_acc.data.borrow())?;
 
     let initializer_account_check: Pubkey = Pubkey::new_from_array(initializer_tournament_account.player);
     let opponent_check: Pubkey = Pubkey::new_from_array(opponent_tournament_account.player);
 
 
 
-    if t.is_init != 1{panic!()}
-    if t.tournament_id != initializer_tournament_account.tournamentid{panic!()}
-    if t.tournament_id != opponent_tournament_account.tournamentid{panic!()}
-    if state.initializer != initializer_tournament_account.player{panic!()}
-    if state.guest != opponent_tournament_account.player{panic!()}
-    if opponent_tournament_account.level == initializer_tournament_account.level {panic!()}
-    if opponent_tournament_account.opponent == initializer_tournament_account.playerno_int {panic!()}
-    if opponent_tournament_account.playerno_int == initializer_tournament_account.opponent {panic!()}
-    if opponent.key != &opponent_check{panic!()}
-    if initializer.key != &initializer_account_check{panic!()}
-    if tournament.owner != program_id{panic!()}
-    if opponent_tour_acc.owner != program_id{panic!()}
-    if initializer_tour_acc.owner != program_id{panic!()}
-    if game_state.owner != program_id{panic!()}
-    if tournament.is_writable {panic!()}
-    if opponent_tournament_account.is_playing != 1{panic!()}
-    if initializer_tournament_account.is_playing != 1{panic!()}
-    if state.initialized != 4 {panic!()}
+    if t.is_init != 1 {return Err(GameError::TournamentNotInitialized.into())}
+    if t.tournament_id != initializer_tournament_account.tournamentid {return Err(GameError::TournamentMismatch.into())}
+    if t.tournament_id != opponent_tournament_account.tournamentid {return Err(GameError::TournamentMismatch.into())}
+    if state.initializer != initializer_tournament_account.player {return Err(GameError::InvalidInitializer.into())}
+    if state.guest != opponent_tournament_account.player {return Err(GameError::InvalidGuest.into())}
+    if opponent_tournament_account.level != initializer_tournament_account.level {return Err(GameError::OpponentMismatch.into())}
+    if opponent_tournament_account.opponent != initializer_tournament_account.playerno_int {return Err(GameError::OpponentMismatch.into())}
+    if opponent_tournament_account.playerno_int != initializer_tournament_account.opponent {return Err(GameError::OpponentMismatch.into())}
+    if opponent.key != &opponent_check {return Err(GameError::InvalidGuest.into())}
+    if initializer.key != &initializer_account_check {return Err(GameError::InvalidInitializer.into())}
+    if tournament.owner != program_id {return Err(GameError::WrongAccountOwner.into())}
+    if opponent_tour_acc.owner != program_id {return Err(GameError::WrongAccountOwner.into())}
+    if initializer_tour_acc.owner != program_id {return Err(GameError::WrongAccountOwner.into())}
+    if game_state.owner != program_id {return Err(GameError::WrongAccountOwner.into())}
+    if tournament.is_writable {return Err(GameError::MustBeReadOnly.into())}
+    if opponent_tournament_account.is_playing != 1 {return Err(GameError::InvalidPlayerState.into())}
+    if initializer_tournament_account.is_playing != 1 {return Err(GameError::InvalidPlayerState.into())}
+    let waiting_on: GameStatus = state.status;
+    state.status = status::transition(state.status, Event::Timeout)?;
 
 
     let mut the_no: u32 = 0;
@@ -1355,28 +1613,30 @@ impl Processor {
     let current_time: u64 = clock.unix_timestamp as u64;
 
 
-    if state.whoseturn == 1{
+    if waiting_on == (GameStatus::Active{turn:1}){
       let time_passed: u64 = current_time - state.lastplaytime;
       if time_passed > t.time_is_up {gwins=true;}
-      if time_passed < t.time_is_up {panic!()}
+      if time_passed < t.time_is_up {return Err(GameError::TimeoutNotReached.into())}
     }
-    if state.whoseturn == 2{
+    if waiting_on == (GameStatus::Active{turn:2}){
       let time_passed: u64 = current_time - state.lastplaytime;
       if time_passed > t.time_is_up {iwins=true;}
-      if time_passed < t.time_is_up {panic!()}
+      if time_passed < t.time_is_up {return Err(GameError::TimeoutNotReached.into())}
     }
 
-    if !iwins && !gwins{panic!()}
-    if !iwins && gwins{panic!()}
+    if !iwins && !gwins {return Err(GameError::TimeoutNotReached.into())}
+
+    state.store(&mut game_state.data.borrow_mut())?;
+
+    let loser_get: u64 = Self::lamports_of(&t.mint, t.lvl_get[initializer_tournament_account.level as usize] as u64 * t.entrance_fee);
 
-    if iwins == true{
+    if iwins{
 
       let us: usize = opponent_tournament_account.level as usize;
       let multiply: u64 = t.lvl_get[us] as u64; 
       let reward:u64 = multiply*t.entrance_fee;
 
-      **opponent_tour_acc.lamports.borrow_mut()-= reward;
-      **opponent.lamports.borrow_mut()+= reward;
+      Self::pay(opponent_tour_acc, payees[1], reward, escrow.as_ref())?;
 
       let value: u64 = **opponent_tour_acc.lamports.borrow();
 
@@ -1402,19 +1662,26 @@ impl Processor {
       initializer_tournament_account.waiting_opponent_to_join = 0;
       initializer_tournament_account.level += 1;
       initializer_tournament_account.playerno_int = the_no;
+      initializer_tournament_account.opponent = Self::bracket_opponent(the_no, initializer_tournament_account.level);
       initializer_tournament_account.opponent_played_on = current_time;
 
-      initializer_tournament_account.serialize(&mut &mut initializer_tour_acc.data.borrow_mut()[..])?;
+      initializer_tournament_account.store(&mut initializer_tour_acc.data.borrow_mut())?;
+
+      GameEvent::PlayerAdvanced{
+        tournament: tournament.key.to_bytes(),
+        player: initializer_tournament_account.player,
+        player_no: the_no,
+        level: initializer_tournament_account.level,
+      }.emit()?;
 
     }
-    if gwins == true{
+    if gwins{
 
       let us: usize = initializer_tournament_account.level as usize;
       let multiply: u64 = t.lvl_get[us] as u64; 
       let reward:u64 = multiply*t.entrance_fee;
 
-      **initializer_tour_acc.lamports.borrow_mut()-= reward;
-      **initializer.lamports.borrow_mut()+= reward;
+      Self::pay(initializer_tour_acc, payees[0], reward, escrow.as_ref())?;
 
       let value: u64 = **initializer_tour_acc.lamports.borrow();
 
@@ -1424,7 +1691,7 @@ impl Processor {
       let game: u64 = **game_state.lamports.borrow();
 
       **game_state.lamports.borrow_mut()-= game;
-      **initializer_tour_acc.lamports.borrow_mut()+= game;
+      **opponent_tour_acc.lamports.borrow_mut()+= game;
 
       let str_player_no: String = the_no.to_string();
       let mut somestr: String = String::from("pppppppppp");
@@ -1440,44 +1707,71 @@ impl Processor {
       opponent_tournament_account.waiting_opponent_to_join = 0;
       opponent_tournament_account.level += 1;
       opponent_tournament_account.playerno_int = the_no;
+      opponent_tournament_account.opponent = Self::bracket_opponent(the_no, opponent_tournament_account.level);
       opponent_tournament_account.opponent_played_on = current_time;
 
-      opponent_tournament_account.serialize(&mut &mut opponent_tour_acc.data.borrow_mut()[..])?;
+      opponent_tournament_account.store(&mut opponent_tour_acc.data.borrow_mut())?;
+
+      GameEvent::PlayerAdvanced{
+        tournament: tournament.key.to_bytes(),
+        player: opponent_tournament_account.player,
+        player_no: the_no,
+        level: opponent_tournament_account.level,
+      }.emit()?;
 
     }
 
+    let sides: [(&Pubkey, bool, u8); 2] = [
+      (&initializer_account_check, iwins, initializer_tournament_account.level),
+      (&opponent_check, gwins, opponent_tournament_account.level),
+    ];
+    for (side, (player, won, reached)) in sides.into_iter().enumerate() {
+      Self::update_profile(profiles[side], player, program_id, |p| {
+        p.games_played += 1;
+        if won {p.wins += 1; p.best_level = p.best_level.max(reached)} else {p.losses += 1; p.lamports_won += loser_get}
+      })?;
+    }
+
     Ok(())
   }
   fn initialize_counter(        
     accounts: &[AccountInfo],
-    t_counter: InitTournamentCounter) -> ProgramResult {
+    t_counter: InitTournamentCounter,
+    program_id: &Pubkey) -> ProgramResult {
 
       let accounts_iter: &mut std::slice::Iter<'_, AccountInfo<'_>> = &mut accounts.iter();
 
       let authority: &AccountInfo<'_> = next_account_info(accounts_iter)?;
       let tournament_counter: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+      let config: &AccountInfo<'_> = next_account_info(accounts_iter)?;
 
-      let a_k: Pubkey = Pubkey::from_str("4YbLBRXwseG1NuyJbteSD5u81Q2QjFqJBp6JmxwYBKYm").unwrap();
-      if authority.key != &a_k {panic!()}
-      if !authority.is_signer {panic!()}
+      Self::check_authority(authority, config, program_id)?;
 
-      t_counter.serialize(&mut &mut tournament_counter.data.borrow_mut()[..])?;
+      let counter_account: TournamentCounter = TournamentCounter{
+        empty_tournament_id:format!("E{}", t_counter.tournament_id),
+        counter_no:t_counter.counter_no,
+        player_participating:0,
+        capacity:t_counter.capacity,
+      };
+
+      counter_account.store(&mut tournament_counter.data.borrow_mut())?;
 
+      GameEvent::ConfigUpdated{ authority: authority.key.to_bytes(), account: tournament_counter.key.to_bytes() }.emit()?;
 
       Ok(())
   }
   fn update_counter_finder(        
     accounts: &[AccountInfo],
-    c_finder: CounterFinder) -> ProgramResult {
+    c_finder: CounterFinder,
+    program_id: &Pubkey) -> ProgramResult {
   
         let accounts_iter: &mut std::slice::Iter<'_, AccountInfo<'_>> = &mut accounts.iter();
   
         let authority: &AccountInfo<'_> = next_account_info(accounts_iter)?;
         let finder: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+        let config: &AccountInfo<'_> = next_account_info(accounts_iter)?;
   
-        let a_k: Pubkey = Pubkey::from_str("4YbLBRXwseG1NuyJbteSD5u81Q2QjFqJBp6JmxwYBKYm").unwrap();
-        if authority.key != &a_k {panic!()}
-        if !authority.is_signer {panic!()}
+        Self::check_authority(authority, config, program_id)?;
   
       let f: CounterFinder = CounterFinder{
         finder_no:c_finder.finder_no,
@@ -1485,61 +1779,74 @@ impl Processor {
         tournament_id:c_finder.tournament_id,
       };
 
-      f.serialize(&mut &mut finder.data.borrow_mut()[..])?;
+      f.store(&mut finder.data.borrow_mut())?;
+
+      GameEvent::ConfigUpdated{ authority: authority.key.to_bytes(), account: finder.key.to_bytes() }.emit()?;
 
         Ok(())
   }
   fn update_finder_finder(        
         accounts: &[AccountInfo],
-    f_finder: FinderFinder) -> ProgramResult {
+    f_finder: FinderFinder,
+    program_id: &Pubkey) -> ProgramResult {
     
           let accounts_iter: &mut std::slice::Iter<'_, AccountInfo<'_>> = &mut accounts.iter();
   
           let authority: &AccountInfo<'_> = next_account_info(accounts_iter)?;
           let finder: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+          let config: &AccountInfo<'_> = next_account_info(accounts_iter)?;
     
-          let a_k: Pubkey = Pubkey::from_str("4YbLBRXwseG1NuyJbteSD5u81Q2QjFqJBp6JmxwYBKYm").unwrap();
-          if authority.key != &a_k {panic!()}
-          if !authority.is_signer {panic!()}
+          Self::check_authority(authority, config, program_id)?;
     
         let f: FinderFinder = FinderFinder{
           counters:f_finder.counters,
           tournament_id:f_finder.tournament_id,
         };
   
-        f.serialize(&mut &mut finder.data.borrow_mut()[..])?;
+        f.store(&mut finder.data.borrow_mut())?;
+
+        GameEvent::ConfigUpdated{ authority: authority.key.to_bytes(), account: finder.key.to_bytes() }.emit()?;
     
           Ok(())
   }
   fn close_account(        
-    accounts: &[AccountInfo]) -> ProgramResult {
+    accounts: &[AccountInfo],
+    program_id: &Pubkey) -> ProgramResult {
 
       let accounts_iter = &mut accounts.iter();
 
       let authority = next_account_info(accounts_iter)?;
       let account = next_account_info(accounts_iter)?;
+      let config = next_account_info(accounts_iter)?;
+
+      Self::check_authority(authority, config, program_id)?;
 
-      let a_k = Pubkey::from_str("4YbLBRXwseG1NuyJbteSD5u81Q2QjFqJBp6JmxwYBKYm").unwrap();
-      if authority.key != &a_k {panic!()}
-      if !authority.is_signer {panic!()}
+      if account.key == config.key {return Err(GameError::InvalidConfig.into())}
 
       let account_value = **account.lamports.borrow();
 
       **account.lamports.borrow_mut()-= account_value;
       **authority.lamports.borrow_mut()+= account_value;
 
+      GameEvent::ConfigUpdated{ authority: authority.key.to_bytes(), account: account.key.to_bytes() }.emit()?;
+
       Ok(())
 
   }
   fn chat_global(        
     accounts: &[AccountInfo],
-    chat:Chat) -> ProgramResult {
+    chat:Chat,
+    program_id: &Pubkey) -> ProgramResult {
 
       let accounts_iter: &mut std::slice::Iter<'_, AccountInfo<'_>> = &mut accounts.iter();
 
       let chat_account: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+      let rent_data: &AccountInfo<'_> = next_account_info(accounts_iter)?;
 
-      let g_chat: ChatGlobal = ChatGlobal::try_from_slice(&chat_account.data.borrow())?;
+      let g_chat: ChatGlobal = ChatGlobal::load(&chat_account.data.borrow())?;
+      let rents: UpdateRent = Self::load_rent(rent_data, program_id)?;
+
+      if chat.chat.len() > rents.max_chat_len as usize {return Err(GameError::ChatTooLong.into())}
 
 
       let new_chat: ChatGlobal = ChatGlobal{
@@ -1565,14 +1872,17 @@ impl Processor {
         chat_line_20:chat.chat,
       };
 
-      new_chat.serialize(&mut &mut chat_account.data.borrow_mut()[..])?;
+      new_chat.store(&mut chat_account.data.borrow_mut())?;
+
+      GameEvent::ChatPosted{ channel: chat_account.key.to_bytes(), speaker: [0;32], text: new_chat.chat_line_20 }.emit()?;
 
 
       Ok(())
   }
   fn chat_local(
     accounts: &[AccountInfo],
-    chat: Chat) -> ProgramResult {
+    chat: Chat,
+    program_id: &Pubkey) -> ProgramResult {
 
       let accounts_iter: &mut std::slice::Iter<'_, AccountInfo<'_>> = &mut accounts.iter();
 
@@ -1580,37 +1890,56 @@ impl Processor {
       let guest: &AccountInfo<'_> = next_account_info(accounts_iter)?;
       let speaker: &AccountInfo<'_> = next_account_info(accounts_iter)?;
       let game_state: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+      let rent_data: &AccountInfo<'_> = next_account_info(accounts_iter)?;
 
 
-      if speaker.key != guest.key && speaker.key != initializer.key {panic!()}
+      if speaker.key != guest.key && speaker.key != initializer.key {return Err(GameError::NotAPlayer.into())}
+      if !speaker.is_signer {return Err(GameError::NotSigner.into())}
 
-      let state: GameState = GameState::try_from_slice(&game_state.data.borrow())?;
+      let state: GameState = Self::load_game(game_state, program_id)?;
 
 
 
       let guest_check: Pubkey = Pubkey::new_from_array(state.guest);
       let initializer_check:Pubkey = Pubkey::new_from_array(state.initializer);
 
-      if initializer.key != &initializer_check{panic!()}
-      if guest.key != &guest_check{panic!()}
+      if initializer.key != &initializer_check {return Err(GameError::InvalidInitializer.into())}
+      if guest.key != &guest_check {return Err(GameError::InvalidGuest.into())}
 
-      if chat.chat.len() > 50{panic!()}
+      let rents: UpdateRent = Self::load_rent(rent_data, program_id)?;
+
+      if chat.chat.len() > rents.max_chat_len as usize {return Err(GameError::ChatTooLong.into())}
 
       let new_state: GameState = GameState{
         host:state.host,
-        waiting:state.waiting,
-        initialized:state.initialized,
+        status:state.status,
         gameseed:state.gameseed,
         lamports:state.lamports,
         initializer: state.initializer,
         gamehash:state.gamehash,
         guest:state.guest,
-        whoseturn:state.whoseturn,
+        guest_hash:state.guest_hash,
+        initializer_move:state.initializer_move,
         guest_move:state.guest_move,
         score_i:state.score_i,
         score_g:state.score_g,
         game_ends:state.game_ends,
+        max_rounds:state.max_rounds,
+        rounds_played:state.rounds_played,
+        sudden_death:state.sudden_death,
+        ruleset:state.ruleset,
+        moves:state.moves,
+        ranked:state.ranked,
+        min_rating:state.min_rating,
+        max_rating:state.max_rating,
+        invited:state.invited,
+        allowlist_root:state.allowlist_root,
+        mint:state.mint,
+        bump:state.bump,
+        lobby_page:state.lobby_page,
+        queued:state.queued,
         lastplaytime:state.lastplaytime,
+        history:state.history,
         chat_line_1:state.chat_line_2,
         chat_line_2:state.chat_line_3,
         chat_line_3:state.chat_line_4,
@@ -1619,7 +1948,9 @@ impl Processor {
         chat_line_6:chat.chat,
       };
 
-      new_state.serialize(&mut &mut game_state.data.borrow_mut()[..])?;
+      new_state.store(&mut game_state.data.borrow_mut())?;
+
+      GameEvent::ChatPosted{ channel: game_state.key.to_bytes(), speaker: speaker.key.to_bytes(), text: new_state.chat_line_6 }.emit()?;
 
 
       Ok(())
@@ -1634,40 +1965,686 @@ impl Processor {
       let initializer_tour_acc: &AccountInfo<'_> = next_account_info(accounts_iter)?;
       let tournament: &AccountInfo<'_> = next_account_info(accounts_iter)?;
   
-      let t: Tournament = Tournament::try_from_slice(&tournament.data.borrow())?;
+      let t: Tournament = Tournament::load(&tournament.data.borrow())?;
+
+      let (escrow, payees) = Self::load_escrow(accounts_iter, tournament.key, &t.mint, [initializer], program_id)?;
+      let profiles: [Option<&AccountInfo<'_>>; 2] = Self::trailing_profiles(accounts_iter);
 
-      let initializer_tournament_account: TournamentAccount = TournamentAccount::try_from_slice(&initializer_tour_acc.data.borrow())?;
+      let mut initializer_tournament_account: TournamentAccount = TournamentAccount::load(&initializer_tour_acc.data.borrow())?;
 
   
       let initializer_account_check: Pubkey = Pubkey::new_from_array(initializer_tournament_account.player);
 
   
-      if t.is_init != 1{panic!()}
-      if t.tournament_id != initializer_tournament_account.tournamentid{panic!()}
+      if t.is_init != 1 {return Err(GameError::TournamentNotInitialized.into())}
+      if t.tournament_id != initializer_tournament_account.tournamentid {return Err(GameError::TournamentMismatch.into())}
 
 
-      if initializer.key != &initializer_account_check{panic!()}
+      if initializer.key != &initializer_account_check {return Err(GameError::InvalidInitializer.into())}
 
-      if tournament.owner != program_id{panic!()}
-      if initializer_tour_acc.owner != program_id{panic!()}
+      if tournament.owner != program_id {return Err(GameError::WrongAccountOwner.into())}
+      if initializer_tour_acc.owner != program_id {return Err(GameError::WrongAccountOwner.into())}
 
-      if tournament.is_writable {panic!()}
-      if initializer_tournament_account.level != t.tournament_size{panic!()}
+      if tournament.is_writable {return Err(GameError::MustBeReadOnly.into())}
+      if initializer_tournament_account.level != t.tournament_size {return Err(GameError::NotTournamentWinner.into())}
+      if initializer_tournament_account.prize_claimed != 0 {return Err(GameError::PrizeAlreadyClaimed.into())}
 
-      **initializer_tour_acc.lamports.borrow_mut()-= t.winner_get;
-      **initializer.lamports.borrow_mut()+= t.winner_get;
+      Self::pay(initializer_tour_acc, payees[0], t.winner_get, escrow.as_ref())?;
+
+      initializer_tournament_account.prize_claimed = 1;
+      initializer_tournament_account.store(&mut initializer_tour_acc.data.borrow_mut())?;
+
+      GameEvent::PrizeClaimed{ tournament: tournament.key.to_bytes(), player: initializer.key.to_bytes(), prize: t.winner_get }.emit()?;
+
+      Self::update_profile(profiles[0], initializer.key, program_id, |p| p.lamports_won += Self::lamports_of(&t.mint, t.winner_get))?;
 
 
       Ok(())
   }
 
-}
+  fn init_config(
+    accounts: &[AccountInfo],
+    c: InitConfig,
+    program_id: &Pubkey) -> ProgramResult {
+
+      let accounts_iter: &mut std::slice::Iter<'_, AccountInfo<'_>> = &mut accounts.iter();
+
+      let authority: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+      let config: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+      let program_data: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+
+      let config_check: Pubkey = Pubkey::create_program_address(&[CONFIG_SEED, &[c.bump]], program_id)?;
+
+      if config.key != &config_check {return Err(GameError::InvalidConfig.into())}
+      if !authority.is_signer {return Err(GameError::NotSigner.into())}
+      Self::check_upgrade_authority(authority, program_data, program_id)?;
+
+      let config_account: Config = Config{
+        is_init:1,
+        authority:authority.key.to_bytes(),
+        pending_authority:[0;32],
+        bump:c.bump,
+      };
+      let space: usize = config_account.space()?;
+
+      Self::create_pda(authority, config, Rent::get()?.minimum_balance(space), space, &[CONFIG_SEED, &[c.bump]], program_id)?;
+
+      config_account.store(&mut config.data.borrow_mut())?;
+
+      GameEvent::ConfigUpdated{ authority: authority.key.to_bytes(), account: config.key.to_bytes() }.emit()?;
 
+      Ok(())
+  }
+  fn propose_authority(
+    accounts: &[AccountInfo],
+    p: ProposeAuthority,
+    program_id: &Pubkey) -> ProgramResult {
+
+      let accounts_iter: &mut std::slice::Iter<'_, AccountInfo<'_>> = &mut accounts.iter();
+
+      let authority: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+      let config: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+
+      Self::check_authority(authority, config, program_id)?;
+
+      let mut config_account: Config = Config::load(&config.data.borrow())?;
+      config_account.pending_authority = p.new_authority;
+
+      config_account.store(&mut config.data.borrow_mut())?;
+
+      GameEvent::ConfigUpdated{ authority: authority.key.to_bytes(), account: config.key.to_bytes() }.emit()?;
+
+      Ok(())
+  }
+  fn accept_authority(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey) -> ProgramResult {
+
+      let accounts_iter: &mut std::slice::Iter<'_, AccountInfo<'_>> = &mut accounts.iter();
+
+      let new_authority: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+      let config: &AccountInfo<'_> = next_account_info(accounts_iter)?;
+
+      let mut config_account: Config = Self::load_config(config, program_id)?;
+
+      if config_account.pending_authority == [0;32] {return Err(GameError::NotPendingAuthority.into())}
+      if new_authority.key.to_bytes() != config_account.pending_authority {return Err(GameError::NotPendingAuthority.into())}
+      if !new_authority.is_signer {return Err(GameError::NotSigner.into())}
+
+      config_account.authority = config_account.pending_authority;
+      config_account.pending_authority = [0;32];
 
+      config_account.store(&mut config.data.borrow_mut())?;
 
+      GameEvent::ConfigUpdated{ authority: new_authority.key.to_bytes(), account: config.key.to_bytes() }.emit()?;
 
-// 1 tas
-// 2 makas
-// 3 kagit
+      Ok(())
+  }
+
+  fn load_config(
+    config: &AccountInfo,
+    program_id: &Pubkey) -> Result<Config, ProgramError> {
+
+    if config.owner != program_id {return Err(GameError::InvalidConfig.into())}
+
+    let config_account: Config = Config::load(&config.data.borrow())?;
+    let config_check: Pubkey = Pubkey::create_program_address(&[CONFIG_SEED, &[config_account.bump]], program_id)?;
+
+    if config.key != &config_check {return Err(GameError::InvalidConfig.into())}
+    if config_account.is_init != 1 {return Err(GameError::InvalidConfig.into())}
 
+    Ok(config_account)
+  }
+
+  fn check_authority(
+    authority: &AccountInfo,
+    config: &AccountInfo,
+    program_id: &Pubkey) -> ProgramResult {
 
+    let config_account: Config = Self::load_config(config, program_id)?;
+
+    if authority.key.to_bytes() != config_account.authority {return Err(GameError::Unauthorized.into())}
+    if !authority.is_signer {return Err(GameError::NotSigner.into())}
+
+    Ok(())
+  }
+
+  // Only whoever can upgrade the program may create its config. ProgramData
+  // holds UpgradeableLoaderState::ProgramData: a u32 variant, the deploy slot
+  // and the upgrade authority as an Option<Pubkey>.
+  fn check_upgrade_authority(
+    authority: &AccountInfo,
+    program_data: &AccountInfo,
+    program_id: &Pubkey) -> ProgramResult {
+
+    let (program_data_check, _) = Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+
+    if program_data.key != &program_data_check {return Err(GameError::InvalidConfig.into())}
+    if program_data.owner != &bpf_loader_upgradeable::id() {return Err(GameError::WrongAccountOwner.into())}
+
+    let data = program_data.data.borrow();
+    if data.len() < UpgradeableLoaderState::size_of_programdata_metadata() {return Err(GameError::InvalidConfig.into())}
+    if data[..4] != 3u32.to_le_bytes() {return Err(GameError::InvalidConfig.into())}
+    if data[12] != 1 || data[13..45] != authority.key.to_bytes() {return Err(GameError::Unauthorized.into())}
+
+    Ok(())
+  }
+
+  fn load_rent(
+    rent_data: &AccountInfo,
+    program_id: &Pubkey) -> Result<UpdateRent, ProgramError> {
+
+    if rent_data.owner != program_id {return Err(GameError::WrongAccountOwner.into())}
+
+    let data = rent_data.data.borrow();
+    let rents: UpdateRent = if data.len() == LEGACY_RENT_LEN {
+      UpdateRent{
+        is_init:data[0],
+        rent:u64::try_from_slice(&data[1..])?,
+        version:0,
+        win_fee_bps:DEFAULT_WIN_FEE_BPS,
+        timeout_fee_bps:DEFAULT_TIMEOUT_FEE_BPS,
+        move_timeout:DEFAULT_MOVE_TIMEOUT,
+        max_chat_len:CHAT_LINE_LEN,
+      }
+    } else {
+      UpdateRent::load(&data)?
+    };
+
+    if rents.is_init != 1 {return Err(GameError::RentNotInitialized.into())}
+
+    Ok(rents)
+  }
+
+  fn fee(lamports: u64, bps: u16) -> u64 {
+    (lamports as u128 * bps as u128 / 10_000) as u64
+  }
+
+  // Profiles are optional accounts after the ones a handler needs, in the
+  // order of its players. The system program may be among them for the CPI
+  // that creates a profile.
+  fn trailing_profiles<'a, 'b>(
+    accounts_iter: &mut std::slice::Iter<'a, AccountInfo<'b>>) -> [Option<&'a AccountInfo<'b>>; 2] {
+
+    let mut profiles = accounts_iter.filter(|account| account.key != &system_program::id());
+    [profiles.next(), profiles.next()]
+  }
+
+  // Creates the profile of `player` on their first play, paid by the signer
+  // of the instruction. Nothing happens if it exists or was not passed.
+  fn create_profile<'a>(
+    payer: &AccountInfo<'a>,
+    profile: Option<&AccountInfo<'a>>,
+    player: &Pubkey,
+    program_id: &Pubkey) -> ProgramResult {
+
+    let profile: &AccountInfo<'a> = match profile {
+      Some(profile) if profile.data_is_empty() && payer.is_signer => profile,
+      _ => return Ok(()),
+    };
+
+    let (profile_check, bump) = Pubkey::find_program_address(&[PROFILE_SEED, player.as_ref()], program_id);
+    if profile.key != &profile_check {return Err(GameError::InvalidProfile.into())}
+
+    let profile_account: PlayerProfile = PlayerProfile{
+      is_init:1,
+      player:player.to_bytes(),
+      bump,
+      rating:DEFAULT_RATING,
+      ..PlayerProfile::default()
+    };
+    let space: usize = profile_account.space()?;
+
+    Self::create_pda(payer, profile, Rent::get()?.minimum_balance(space), space, &[PROFILE_SEED, player.as_ref(), &[bump]], program_id)?;
+
+    profile_account.store(&mut profile.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  // The profile of `player`, None if it was not passed or does not exist yet.
+  fn load_profile(
+    profile: Option<&AccountInfo>,
+    player: &Pubkey,
+    program_id: &Pubkey) -> Result<Option<PlayerProfile>, ProgramError> {
+
+    let profile: &AccountInfo = match profile {
+      Some(profile) if !profile.data_is_empty() => profile,
+      _ => return Ok(None),
+    };
+    if profile.owner != program_id {return Err(GameError::InvalidProfile.into())}
+
+    let profile_account: PlayerProfile = PlayerProfile::load(&profile.data.borrow())?;
+    let profile_check: Pubkey = Pubkey::create_program_address(&[PROFILE_SEED, player.as_ref(), &[profile_account.bump]], program_id)?;
+
+    if profile.key != &profile_check {return Err(GameError::InvalidProfile.into())}
+    if profile_account.player != player.to_bytes() {return Err(GameError::InvalidProfile.into())}
+
+    Ok(Some(profile_account))
+  }
+
+  // Applies `update` to the profile of `player` if it was passed and exists.
+  fn update_profile<F: FnOnce(&mut PlayerProfile)>(
+    profile: Option<&AccountInfo>,
+    player: &Pubkey,
+    program_id: &Pubkey,
+    update: F) -> ProgramResult {
+
+    let mut profile_account: PlayerProfile = match Self::load_profile(profile, player, program_id)? {
+      Some(profile_account) => profile_account,
+      None => return Ok(()),
+    };
+
+    update(&mut profile_account);
+    if let Some(profile) = profile {
+      profile_account.store(&mut profile.data.borrow_mut())?;
+    }
+
+    Ok(())
+  }
+
+  // New ratings of the initializer and the guest when a ranked game is
+  // settled, None for unranked games. Both profiles are required.
+  fn settle_ratings(
+    state: &GameState,
+    profiles: [Option<&AccountInfo>; 2],
+    program_id: &Pubkey,
+    iwins: bool,
+    gwins: bool) -> Result<Option<[u16; 2]>, ProgramError> {
+
+    if state.ranked != 1 {return Ok(None)}
+
+    let initializer: PlayerProfile = Self::load_profile(profiles[0], &Pubkey::new_from_array(state.initializer), program_id)?.ok_or(GameError::InvalidProfile)?;
+    let guest: PlayerProfile = Self::load_profile(profiles[1], &Pubkey::new_from_array(state.guest), program_id)?.ok_or(GameError::InvalidProfile)?;
+
+    let outcome: Outcome = if iwins {Outcome::InitializerWins} else if gwins {Outcome::GuestWins} else {Outcome::Draw};
+    let (initializer_rating, guest_rating) = rating::settle(initializer.rating, guest.rating, outcome);
+
+    Ok(Some([initializer_rating, guest_rating]))
+  }
+
+  fn count_move(profile: &mut PlayerProfile, mymove: u8) {
+    match (mymove as usize).checked_sub(1) {
+      Some(index) if index < PROFILE_MOVES => profile.move_counts[index] += 1,
+      _ => profile.other_moves += 1,
+    }
+  }
+
+  // Token games and tournaments take the token program, their escrow and one
+  // token account per wallet in `wallets` right after the accounts a handler
+  // needs, skipping the system program like the profiles do. Payouts go to
+  // those token accounts; SOL games pay the wallets.
+  fn load_escrow<'a, 'b, const N: usize>(
+    accounts_iter: &mut std::slice::Iter<'a, AccountInfo<'b>>,
+    holder: &Pubkey,
+    mint: &[u8;32],
+    wallets: [&'a AccountInfo<'b>; N],
+    program_id: &Pubkey) -> Result<(Option<Escrow<'a, 'b>>, [&'a AccountInfo<'b>; N]), ProgramError> {
+
+    if mint == &[0;32] {return Ok((None, wallets))}
+
+    let mut accounts = accounts_iter.filter(|account| account.key != &system_program::id());
+    let token_program: &AccountInfo<'b> = accounts.next().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let account: &AccountInfo<'b> = accounts.next().ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    if token_program.key != &spl_token::id() {return Err(ProgramError::IncorrectProgramId)}
+
+    let (escrow_check, bump) = Pubkey::find_program_address(&[ESCROW_SEED, holder.as_ref()], program_id);
+    if account.key != &escrow_check {return Err(GameError::InvalidEscrow.into())}
+
+    let mut payees: [&'a AccountInfo<'b>; N] = wallets;
+    for (payee, wallet) in payees.iter_mut().zip(wallets) {
+      *payee = accounts.next().ok_or(ProgramError::NotEnoughAccountKeys)?;
+      Self::check_token_account(payee, wallet.key, mint)?;
+    }
+
+    Ok((Some(Escrow{ token_program, account, holder: *holder, bump }), payees))
+  }
+
+  fn check_token_account(
+    token_account: &AccountInfo,
+    wallet: &Pubkey,
+    mint: &[u8;32]) -> ProgramResult {
+
+    if token_account.owner != &spl_token::id() {return Err(GameError::InvalidTokenAccount.into())}
+
+    let account: spl_token::state::Account = spl_token::state::Account::unpack(&token_account.data.borrow())?;
+
+    if &account.owner != wallet {return Err(GameError::InvalidTokenAccount.into())}
+    if account.mint.to_bytes() != *mint {return Err(GameError::InvalidTokenAccount.into())}
+
+    Ok(())
+  }
+
+  // Creates the escrow of a new token game or tournament, paid by `payer`.
+  fn create_escrow<'a>(
+    payer: &AccountInfo<'a>,
+    escrow: &Escrow<'_, 'a>,
+    mint: &AccountInfo<'a>) -> ProgramResult {
+
+    let space: usize = spl_token::state::Account::LEN;
+
+    Self::create_pda(payer, escrow.account, Rent::get()?.minimum_balance(space), space, &[ESCROW_SEED, escrow.holder.as_ref(), &[escrow.bump]], &spl_token::id())?;
+
+    invoke(
+      &spl_token::instruction::initialize_account3(&spl_token::id(), escrow.account.key, mint.key, escrow.account.key)?,
+      &[
+        escrow.account.clone(),
+        mint.clone(),
+        escrow.token_program.clone(),
+      ],
+    )?;
+
+    Ok(())
+  }
+
+  // Moves a stake or entrance fee from the player's token account into the
+  // escrow, signed by the player.
+  fn deposit<'a>(
+    from: &AccountInfo<'a>,
+    player: &AccountInfo<'a>,
+    escrow: &Escrow<'_, 'a>,
+    amount: u64) -> ProgramResult {
+
+    invoke(
+      &spl_token::instruction::transfer(&spl_token::id(), from.key, escrow.account.key, player.key, &[], amount)?,
+      &[
+        from.clone(),
+        escrow.account.clone(),
+        player.clone(),
+        escrow.token_program.clone(),
+      ],
+    )
+  }
+
+  // Pays `amount` out of a game or tournament account: lamports in SOL
+  // games, a transfer signed by the escrow in token games.
+  fn pay<'a>(
+    from: &AccountInfo<'a>,
+    to: &AccountInfo<'a>,
+    amount: u64,
+    escrow: Option<&Escrow<'_, 'a>>) -> ProgramResult {
+
+    match escrow {
+      None => {
+        **from.lamports.borrow_mut()-= amount;
+        **to.lamports.borrow_mut()+= amount;
+      }
+      Some(escrow) if amount > 0 => {
+        invoke_signed(
+          &spl_token::instruction::transfer(&spl_token::id(), escrow.account.key, to.key, escrow.account.key, &[], amount)?,
+          &[
+            escrow.account.clone(),
+            to.clone(),
+            escrow.token_program.clone(),
+          ],
+          &[&[ESCROW_SEED, escrow.holder.as_ref(), &[escrow.bump]]],
+        )?;
+      }
+      Some(_) => {}
+    }
+
+    Ok(())
+  }
+
+  // Settles what is left of a finished game: the rest of the stakes go to
+  // `to`, and the rent of the game account and the escrow back to `wallet`
+  // as both are closed.
+  fn release<'a>(
+    game_state: &AccountInfo<'a>,
+    wallet: &AccountInfo<'a>,
+    to: &AccountInfo<'a>,
+    escrow: Option<&Escrow<'_, 'a>>) -> ProgramResult {
+
+    if let Some(escrow) = escrow {
+      let rest: u64 = spl_token::state::Account::unpack(&escrow.account.data.borrow())?.amount;
+      Self::pay(game_state, to, rest, Some(escrow))?;
+
+      invoke_signed(
+        &spl_token::instruction::close_account(&spl_token::id(), escrow.account.key, wallet.key, escrow.account.key, &[])?,
+        &[
+          escrow.account.clone(),
+          wallet.clone(),
+          escrow.token_program.clone(),
+        ],
+        &[&[ESCROW_SEED, escrow.holder.as_ref(), &[escrow.bump]]],
+      )?;
+    }
+
+    Self::close_game(game_state, wallet)
+  }
+
+  // Closes a finished game: its lamports go to `wallet`, its data is zeroed
+  // and the emptied account goes back to the system program.
+  fn close_game<'a>(
+    game_state: &AccountInfo<'a>,
+    wallet: &AccountInfo<'a>) -> ProgramResult {
+
+    let value: u64 = **game_state.lamports.borrow();
+    Self::pay(game_state, wallet, value, None)?;
+
+    game_state.data.borrow_mut().fill(0);
+    game_state.realloc(0, false)?;
+    game_state.assign(&system_program::id());
+
+    Ok(())
+  }
+
+  // The state of a live game. Finished games are closed, so an emptied
+  // account is reported as finished rather than as unreadable.
+  fn load_game(
+    game_state: &AccountInfo,
+    program_id: &Pubkey) -> Result<GameState, ProgramError> {
+
+    if game_state.data_is_empty() {return Err(GameError::GameFinished.into())}
+    if game_state.owner != program_id {return Err(GameError::WrongAccountOwner.into())}
+
+    GameState::load(&game_state.data.borrow())
+  }
+
+  // Games live at the PDA of [GAME_SEED, initializer, gameseed].
+  fn game_address(
+    initializer: &Pubkey,
+    state: &GameState,
+    program_id: &Pubkey) -> Result<Pubkey, ProgramError> {
+
+    Ok(Pubkey::create_program_address(&[GAME_SEED, initializer.as_ref(), state.gameseed.as_bytes(), &[state.bump]], program_id)?)
+  }
+
+  // Listed games take their lobby page after any token accounts, skipping the
+  // system program like the profiles do.
+  fn next_lobby<'a, 'b>(
+    accounts_iter: &mut std::slice::Iter<'a, AccountInfo<'b>>) -> Result<&'a AccountInfo<'b>, ProgramError> {
+
+    accounts_iter.find(|account| account.key != &system_program::id()).ok_or_else(|| GameError::InvalidLobby.into())
+  }
+
+  // Puts a new game in the first free slot of lobby page `page`. The page is
+  // created on first use, paid by the game's initializer.
+  fn list_game<'a>(
+    payer: &AccountInfo<'a>,
+    lobby: &AccountInfo<'a>,
+    page: u16,
+    entry: LobbyEntry,
+    program_id: &Pubkey) -> ProgramResult {
+
+    if lobby.data_is_empty() {
+      let (lobby_check, bump) = Pubkey::find_program_address(&[LOBBY_SEED, &page.to_le_bytes()], program_id);
+      if lobby.key != &lobby_check {return Err(GameError::InvalidLobby.into())}
+
+      let lobby_account: Lobby = Lobby{
+        is_init:1,
+        page,
+        bump,
+        games:[LobbyEntry::default(); LOBBY_PAGE_LEN],
+      };
+      let space: usize = lobby_account.space()?;
+
+      Self::create_pda(payer, lobby, Rent::get()?.minimum_balance(space), space, &[LOBBY_SEED, &page.to_le_bytes(), &[bump]], program_id)?;
+
+      lobby_account.store(&mut lobby.data.borrow_mut())?;
+    }
+
+    let mut lobby_account: Lobby = Self::load_lobby(lobby, page, program_id)?;
+    let slot: &mut LobbyEntry = lobby_account.games.iter_mut().find(|slot| slot.game == [0;32]).ok_or(GameError::LobbyFull)?;
+    *slot = entry;
+    lobby_account.store(&mut lobby.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  // Creates an account of `space` bytes owned by `owner` at the PDA of
+  // `seeds`, holding at least `lamports`. Unlike create_account this works
+  // when someone has already sent lamports to the address: the payer only
+  // tops it up, then the account is allocated and assigned under its seeds.
+  fn create_pda<'a>(
+    payer: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+    lamports: u64,
+    space: usize,
+    seeds: &[&[u8]],
+    owner: &Pubkey) -> ProgramResult {
+
+    let missing: u64 = lamports.saturating_sub(account.lamports());
+    if missing > 0 {
+      invoke(
+        &system_instruction::transfer(payer.key, account.key, missing),
+        &[
+          payer.clone(),
+          account.clone(),
+        ],
+      )?;
+    }
+
+    invoke_signed(
+      &system_instruction::allocate(account.key, space as u64),
+      std::slice::from_ref(account),
+      &[seeds],
+    )?;
+    invoke_signed(
+      &system_instruction::assign(account.key, owner),
+      std::slice::from_ref(account),
+      &[seeds],
+    )?;
+
+    Ok(())
+  }
+
+  // Frees the slot of `game` on lobby page `page`.
+  fn unlist_game(
+    lobby: &AccountInfo,
+    game: &Pubkey,
+    page: u16,
+    program_id: &Pubkey) -> ProgramResult {
+
+    let mut lobby_account: Lobby = Self::load_lobby(lobby, page, program_id)?;
+    let slot: &mut LobbyEntry = lobby_account.games.iter_mut().find(|slot| slot.game == game.to_bytes()).ok_or(GameError::InvalidLobby)?;
+    *slot = LobbyEntry::default();
+    lobby_account.store(&mut lobby.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  fn load_lobby(
+    lobby: &AccountInfo,
+    page: u16,
+    program_id: &Pubkey) -> Result<Lobby, ProgramError> {
+
+    if lobby.owner != program_id {return Err(GameError::InvalidLobby.into())}
+
+    let lobby_account: Lobby = Lobby::load(&lobby.data.borrow())?;
+    if lobby_account.is_init != 1 || lobby_account.page != page {return Err(GameError::InvalidLobby.into())}
+
+    let lobby_check: Pubkey = Pubkey::create_program_address(&[LOBBY_SEED, &page.to_le_bytes(), &[lobby_account.bump]], program_id)?;
+    if lobby.key != &lobby_check {return Err(GameError::InvalidLobby.into())}
+
+    Ok(lobby_account)
+  }
+
+  // Queued games take their queue after any lobby page, skipping the system
+  // program like the profiles do.
+  fn next_queue<'a, 'b>(
+    accounts_iter: &mut std::slice::Iter<'a, AccountInfo<'b>>) -> Result<&'a AccountInfo<'b>, ProgramError> {
+
+    accounts_iter.find(|account| account.key != &system_program::id()).ok_or_else(|| GameError::InvalidQueue.into())
+  }
+
+  // Creates the queue of a stake bracket on first use, paid by the first
+  // player to enqueue. Nothing happens if it exists.
+  fn create_queue<'a>(
+    payer: &AccountInfo<'a>,
+    queue: &AccountInfo<'a>,
+    bracket: u8,
+    program_id: &Pubkey) -> ProgramResult {
+
+    if !queue.data_is_empty() {return Ok(())}
+
+    let (queue_check, bump) = Pubkey::find_program_address(&[QUEUE_SEED, &[bracket]], program_id);
+    if queue.key != &queue_check {return Err(GameError::InvalidQueue.into())}
+
+    let queue_account: MatchQueue = MatchQueue{
+      is_init:1,
+      bracket,
+      bump,
+      game:[0;32],
+      player:[0;32],
+    };
+    let space: usize = queue_account.space()?;
+
+    Self::create_pda(payer, queue, Rent::get()?.minimum_balance(space), space, &[QUEUE_SEED, &[bracket], &[bump]], program_id)?;
+
+    queue_account.store(&mut queue.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  fn load_queue(
+    queue: &AccountInfo,
+    bracket: u8,
+    program_id: &Pubkey) -> Result<MatchQueue, ProgramError> {
+
+    if queue.owner != program_id {return Err(GameError::InvalidQueue.into())}
+
+    let queue_account: MatchQueue = MatchQueue::load(&queue.data.borrow())?;
+    if queue_account.is_init != 1 || queue_account.bracket != bracket {return Err(GameError::InvalidQueue.into())}
+
+    let queue_check: Pubkey = Pubkey::create_program_address(&[QUEUE_SEED, &[bracket], &[queue_account.bump]], program_id)?;
+    if queue.key != &queue_check {return Err(GameError::InvalidQueue.into())}
+
+    Ok(queue_account)
+  }
+
+  // Empties the queue `game` waits in, once it is joined or aborted.
+  fn dequeue(
+    queue: &AccountInfo,
+    game: &Pubkey,
+    lamports: u64,
+    program_id: &Pubkey) -> ProgramResult {
+
+    let mut queue_account: MatchQueue = Self::load_queue(queue, stake_bracket(lamports), program_id)?;
+    if queue_account.game != game.to_bytes() {return Err(GameError::InvalidQueue.into())}
+
+    queue_account.game = [0;32];
+    queue_account.player = [0;32];
+    queue_account.store(&mut queue.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  // Profiles total SOL only, amounts of token games are left out.
+  fn lamports_of(mint: &[u8;32], amount: u64) -> u64 {
+    if mint == &[0;32] {amount} else {0}
+  }
+
+  // Opponent of player_no at the given bracket level: players are paired in
+  // blocks of 2^(level+1) and the winner carries the higher number upwards.
+  fn bracket_opponent(player_no: u32, level: u8) -> u32 {
+    let step: u32 = 2u32.pow(level as u32);
+    if player_no.is_multiple_of(step * 2) {
+      player_no - step
+    } else {
+      player_no + step
+    }
+  }
+
+}
@@ -0,0 +1,45 @@
+use crate::rules::Outcome;
+
+// Elo ratings of ranked games. Integer arithmetic only, so every validator
+// settles a game to the same ratings.
+
+pub const DEFAULT_RATING: u16 = 1200;
+pub const K_FACTOR: i32 = 32;
+pub const RATING_FLOOR: u16 = 100;
+
+// Expected score of the higher rated player in thousandths, for rating
+// differences of 0, 25, .., 400. Larger differences count as 400.
+const EXPECTED: [i32; 17] = [500, 536, 571, 606, 640, 673, 703, 733, 760, 785, 808, 830, 849, 867, 882, 896, 909];
+const STEP: i32 = 25;
+
+/// Expected score of `rating` against `opponent`, in thousandths.
+pub fn expected(rating: u16, opponent: u16) -> i32 {
+  let diff: i32 = (rating as i32 - opponent as i32).clamp(-400, 400);
+  let (index, rest) = ((diff.abs() / STEP) as usize, diff.abs() % STEP);
+  let upper: i32 = EXPECTED[(index + 1).min(EXPECTED.len() - 1)];
+  let favourite: i32 = EXPECTED[index] + (upper - EXPECTED[index]) * rest / STEP;
+  if diff >= 0 {favourite} else {1000 - favourite}
+}
+
+/// New rating after scoring `score` thousandths (1000 win, 500 draw, 0 loss).
+pub fn update(rating: u16, opponent: u16, score: i32) -> u16 {
+  let change: i32 = K_FACTOR * (score - expected(rating, opponent));
+  // round half away from zero so a draw between equals changes nothing
+  let change: i32 = (change + change.signum() * 500) / 1000;
+  (rating as i32 + change).clamp(RATING_FLOOR as i32, u16::MAX as i32) as u16
+}
+
+/// Ratings of the initializer and the guest after a settled game.
+pub fn settle(initializer: u16, guest: u16, outcome: Outcome) -> (u16, u16) {
+  let score: i32 = match outcome {
+    Outcome::InitializerWins => 1000,
+    Outcome::GuestWins => 0,
+    Outcome::Draw => 500,
+  };
+  (update(initializer, guest, score), update(guest, initializer, 1000 - score))
+}
+
+/// Whether `rating` is inside a game's band. A bound of 0 is no bound.
+pub fn in_band(rating: u16, min_rating: u16, max_rating: u16) -> bool {
+  (min_rating == 0 || rating >= min_rating) && (max_rating == 0 || rating <= max_rating)
+}
@@ -0,0 +1,69 @@
+use crate::error::GameError;
+
+// Ruleset ids chosen at InitGame / InitTournament.
+pub const CLASSIC: u8 = 0;
+pub const RPSLS: u8 = 1;
+pub const CYCLIC: u8 = 2;
+
+// Largest move number any ruleset accepts, checked when instructions are decoded.
+pub const MAX_MOVES: u8 = 101;
+
+// 1 rock, 2 scissors, 3 paper, 4 lizard, 5 spock
+const RPSLS_BEATS: [[u8; 2]; 5] = [[2, 4], [3, 4], [1, 5], [3, 5], [1, 2]];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+  Draw,
+  InitializerWins,
+  GuestWins,
+}
+
+/// Number of moves in the ruleset, None if the ruleset is unknown. `moves` is
+/// only read for CYCLIC and must be odd so that every pair of moves is decided.
+pub fn move_count(ruleset: u8, moves: u8) -> Option<u8> {
+  match ruleset {
+    CLASSIC => Some(3),
+    RPSLS => Some(5),
+    CYCLIC if (3..=MAX_MOVES).contains(&moves) && moves % 2 == 1 => Some(moves),
+    _ => None,
+  }
+}
+
+pub fn in_move_range(mymove: u8) -> bool {
+  (1..=MAX_MOVES).contains(&mymove)
+}
+
+pub fn is_valid_move(ruleset: u8, moves: u8, mymove: u8) -> bool {
+  match move_count(ruleset, moves) {
+    Some(count) => mymove >= 1 && mymove <= count,
+    None => false,
+  }
+}
+
+pub fn resolve(ruleset: u8, moves: u8, initializer_move: u8, guest_move: u8) -> Result<Outcome, GameError> {
+  let count: u8 = move_count(ruleset, moves).ok_or(GameError::InvalidRuleset)?;
+
+  if !is_valid_move(ruleset, moves, initializer_move) {return Err(GameError::InvalidMove)}
+  if !is_valid_move(ruleset, moves, guest_move) {return Err(GameError::InvalidMove)}
+
+  if initializer_move == guest_move {return Ok(Outcome::Draw)}
+
+  let initializer_wins: bool = if ruleset == RPSLS {
+    RPSLS_BEATS[initializer_move as usize - 1].contains(&guest_move)
+  } else {
+    cyclic_beats(count, initializer_move, guest_move)
+  };
+
+  if initializer_wins {
+    Ok(Outcome::InitializerWins)
+  } else {
+    Ok(Outcome::GuestWins)
+  }
+}
+
+// Each move beats the (count-1)/2 moves that follow it, wrapping around.
+// With three moves this is rock > scissors > paper > rock.
+fn cyclic_beats(count: u8, a: u8, b: u8) -> bool {
+  let distance: u16 = (b as u16 + count as u16 - a as u16) % count as u16;
+  distance >= 1 && distance <= count as u16 / 2
+}
@@ -1,21 +1,37 @@
 use borsh::{BorshDeserialize, BorshSerialize};
+use crate::status::GameStatus;
 
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
 pub struct GameState {
     pub host:[u8;32],//
-    pub waiting:u8,
-    pub initialized:u8,
+    pub status:GameStatus,//see status.rs
     pub gameseed:String,
     pub lamports:u64,
     pub initializer: [u8;32],
-    pub gamehash: [u8;32],
+    pub gamehash: [u8;32],//initializer commitment for the current round
     pub guest: [u8;32],
-    pub whoseturn:u8,
+    pub guest_hash: [u8;32],//guest commitment for the current round
+    pub initializer_move:u8,
     pub guest_move:u8,
     pub score_i:u8,
     pub score_g:u8,
-    pub game_ends:u8,
+    pub game_ends:u8,//first to game_ends round wins
+    pub max_rounds:u8,//0 for no cap, otherwise the leader wins when the cap is reached
+    pub rounds_played:u16,//index of the round being played, bound into commitments
+    pub sudden_death:u8,//1 keeps playing past the cap while tied, 0 ends it as a draw
+    pub ruleset:u8,//see rules.rs
+    pub moves:u8,//move count for the cyclic ruleset
+    pub ranked:u8,//1 settles the players' ratings, see rating.rs
+    pub min_rating:u16,//0 for no bound, otherwise the guest's rating must be inside the band
+    pub max_rating:u16,
+    pub invited:[u8;32],//zero for an open game, otherwise the only guest allowed to join
+    pub allowlist_root:[u8;32],//zero for no allowlist, see allowlist.rs
+    pub mint:[u8;32],//zero for SOL stakes, otherwise lamports counts base units of this token
+    pub bump:u8,//of the game PDA, see GAME_SEED
+    pub lobby_page:u16,//0 when not listed, otherwise the lobby page listing the game until it is joined or aborted
+    pub queued:u8,//1 while the game waits in its stake bracket's matchmaking queue, see MatchQueue
     pub lastplaytime:u64,
+    pub history:[RoundRecord;HISTORY_LEN],//round n is kept at n % HISTORY_LEN
     pub chat_line_1:String,
     pub chat_line_2:String,
     pub chat_line_3:String,
@@ -24,20 +40,31 @@ pub struct GameState {
     pub chat_line_6:String,
 }
 
+pub const HISTORY_LEN: usize = 16;//rounds kept in a game's history, older ones are overwritten
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub struct RoundRecord{
+    pub round:u16,
+    pub initializer_move:u8,
+    pub guest_move:u8,
+    pub winner:u8,//0 draw, 1 initializer, 2 guest
+    pub time:u64,//time of the reveal that closed the round
+}
+
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
 pub struct TGameState {
     pub game_id:String,//
-    pub initialized:u8,
+    pub status:GameStatus,//see status.rs
     pub gameseed:String,
     pub lamports:u64,
     pub initializer: [u8;32],
     pub gamehash: [u8;32],
     pub guest: [u8;32],
-    pub whoseturn:u8,
     pub guest_move:u8,
     pub score_i:u8,
     pub score_g:u8,
     pub game_ends:u8,
+    pub rounds_played:u16,
     pub lastplaytime:u64,
     pub chat_line_1:String,
     pub chat_line_2:String,
@@ -54,6 +81,17 @@ pub struct Init{
     pub gameseed:String,
     pub lamports:u64,
     pub game_hash:[u8;32],
+    pub max_rounds:u8,
+    pub sudden_death:u8,
+    pub ruleset:u8,
+    pub moves:u8,
+    pub ranked:u8,
+    pub min_rating:u16,
+    pub max_rating:u16,
+    pub invited:[u8;32],
+    pub allowlist_root:[u8;32],
+    pub mint:[u8;32],
+    pub lobby_page:u16,//0 keeps the game out of the lobby, otherwise the page to list it on
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
@@ -61,17 +99,37 @@ pub struct Join{
     pub mymove:u8,
 }
 
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct Commit{
+    pub game_hash:[u8;32],
+    pub proof:Vec<[u8;32]>,//allowlist proof of the guest, empty for other games
+}
+
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub struct InitializerPlay{
-    pub last_round_seed:String,
+    pub salt:[u8;32],
     pub lastmove:u8,
     pub new_game_hash:[u8;32],
 }
 
+// Protocol config. Version 0 records only hold is_init and rent (9 bytes)
+// and are read with the DEFAULT_* values below.
+pub const RENT_VERSION: u8 = 1;
+pub const LEGACY_RENT_LEN: usize = 9;
+pub const DEFAULT_WIN_FEE_BPS: u16 = 200;
+pub const DEFAULT_TIMEOUT_FEE_BPS: u16 = 100;
+pub const DEFAULT_MOVE_TIMEOUT: u64 = 120;
+pub const CHAT_LINE_LEN: u16 = 50;//chat lines are stored at fixed width
+
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub struct UpdateRent {
     pub is_init:u8,
     pub rent:u64,
+    pub version:u8,
+    pub win_fee_bps:u16,//host fee when a game is won
+    pub timeout_fee_bps:u16,//host fee when a game is claimed over time
+    pub move_timeout:u64,//seconds
+    pub max_chat_len:u16,
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
@@ -90,7 +148,10 @@ pub struct Tournament{
     pub number_of_counterfinders:u8,
     pub finderfinder:u8,
     pub game_ends:u8,
-    pub bump:u8
+    pub bump:u8,
+    pub ruleset:u8,
+    pub moves:u8,
+    pub mint:[u8;32],//zero for SOL, otherwise entrance fees and prizes are paid in this token
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
@@ -104,6 +165,7 @@ pub struct TournamentAccount{
     pub opponent_played_on:u64,
     pub is_playing:u8,
     pub waiting_opponent_to_join:u8,
+    pub prize_claimed:u8,//1 once the tournament winner has been paid winner_get
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
@@ -165,3 +227,118 @@ pub struct ChatGlobal{
     pub chat_line_20:String,
 }
 
+
+pub const CONFIG_SEED: &[u8] = b"config";
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct Config{
+    pub is_init:u8,
+    pub authority:[u8;32],
+    pub pending_authority:[u8;32],//all zero when no transfer is in progress
+    pub bump:u8,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct InitConfig{
+    pub bump:u8,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct ProposeAuthority{
+    pub new_authority:[u8;32],
+}
+
+// Games are created by InitGame at the PDA of [GAME_SEED, initializer, gameseed].
+pub const GAME_SEED: &[u8] = b"game";
+
+// Stakes of token games and tournaments sit in a token account at the PDA of
+// [ESCROW_SEED, game or tournament account], which is its own token authority.
+pub const ESCROW_SEED: &[u8] = b"escrow";
+
+pub const LOBBY_SEED: &[u8] = b"lobby";
+pub const LOBBY_PAGE_LEN: usize = 32;//games listed on one lobby page
+
+// One page of the lobby of open games, at the PDA of [LOBBY_SEED, page as
+// little endian u16]. Pages are numbered from 1 and created on first use.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct Lobby{
+    pub is_init:u8,
+    pub page:u16,
+    pub bump:u8,
+    pub games:[LobbyEntry;LOBBY_PAGE_LEN],
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub struct LobbyEntry{
+    pub game:[u8;32],//all zero for a free slot
+    pub lamports:u64,
+    pub game_ends:u8,
+    pub created:u64,
+}
+
+pub const QUEUE_SEED: &[u8] = b"queue";
+pub const QUEUE_GAME_ENDS: u8 = 1;//matchmade games are single classic rounds
+
+// Lowest stake of each matchmaking bracket in lamports, a bracket runs up to
+// the next one and the last is open ended.
+pub const STAKE_BRACKETS: [u64; 11] = [
+    0,
+    10_000_000,
+    20_000_000,
+    50_000_000,
+    100_000_000,
+    200_000_000,
+    500_000_000,
+    1_000_000_000,
+    2_000_000_000,
+    5_000_000_000,
+    10_000_000_000,
+];
+
+// Index in STAKE_BRACKETS of the bracket holding `lamports`.
+pub fn stake_bracket(lamports: u64) -> u8 {
+    (STAKE_BRACKETS.iter().filter(|&&low| low <= lamports).count() - 1) as u8
+}
+
+// Matchmaking queue of one stake bracket, at the PDA of [QUEUE_SEED,
+// bracket]. At most one game waits in it.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct MatchQueue{
+    pub is_init:u8,
+    pub bracket:u8,
+    pub bump:u8,
+    pub game:[u8;32],//all zero when nobody is waiting
+    pub player:[u8;32],//initializer of the waiting game
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct Enqueue{
+    pub bump:u8,//of the player's own game PDA, used when nobody is waiting
+    pub gameseed:String,
+    pub lamports:u64,//stake of the player's own game, picks the bracket
+    pub game_hash:[u8;32],//commitment bound to the game passed with the instruction
+}
+
+pub const PROFILE_SEED: &[u8] = b"profile";
+pub const PROFILE_MOVES: usize = 5;//moves 1..5 are counted one by one, larger cyclic moves together
+
+// Lifetime record of one wallet, at the PDA of [PROFILE_SEED, player].
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default, Clone, PartialEq)]
+pub struct PlayerProfile{
+    pub is_init:u8,
+    pub player:[u8;32],
+    pub bump:u8,
+    pub games_played:u32,
+    pub wins:u32,
+    pub losses:u32,
+    pub draws:u32,
+    pub rounds_played:u32,
+    pub lamports_wagered:u64,//stakes and tournament entrance fees
+    pub lamports_won:u64,//everything paid back out, returned stakes included
+    pub tournaments_joined:u32,
+    pub best_level:u8,//highest tournament bracket level reached
+    pub move_counts:[u32;PROFILE_MOVES],
+    pub other_moves:u32,
+    pub rating:u16,//Elo, starts at rating::DEFAULT_RATING
+    pub ranked_games:u32,
+}
@@ -0,0 +1,77 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use std::io;
+use crate::error::GameError;
+
+/// Where a casual (GameState) or tournament (TGameState) game is in its
+/// lifecycle. Handlers only ever change it through `transition`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameStatus {
+  Open,//created, waiting for the guest or the tournament opponent
+  Active{turn:u8},//0 both still to reveal, 1 initializer still to move, 2 guest still to move
+  Finished{winner:u8},//0 draw, 1 initializer, 2 guest
+  Aborted,
+  TimedOut,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+  Join{turn:u8},//the guest joins, `turn` is who moves first as in Active
+  Reveal{player:u8},//a casual player reveals, the round is complete once both have
+  Move{player:u8},//a tournament player moves and hands the turn to the other
+  End{winner:u8},//the match is decided, winner as in Finished
+  Abort,
+  Timeout,
+}
+
+/// The status a game moves to on `event`, or the error explaining why the
+/// event is not allowed in `status`.
+pub fn transition(status: GameStatus, event: Event) -> Result<GameStatus, GameError> {
+  match (status, event) {
+    (GameStatus::Finished{..} | GameStatus::Aborted | GameStatus::TimedOut, _) => Err(GameError::GameFinished),
+    (GameStatus::Open, Event::Join{turn}) => Ok(GameStatus::Active{turn}),
+    (GameStatus::Open, Event::Abort) => Ok(GameStatus::Aborted),
+    (GameStatus::Open, _) => Err(GameError::GameNotActive),
+    (GameStatus::Active{..}, Event::Join{..} | Event::Abort) => Err(GameError::GameNotOpen),
+    (GameStatus::Active{turn:0}, Event::Reveal{player}) => Ok(GameStatus::Active{turn: other(player)}),
+    (GameStatus::Active{turn}, Event::Reveal{player}) if turn == player => Ok(GameStatus::Active{turn:0}),
+    (GameStatus::Active{turn}, Event::Move{player}) if turn == player => Ok(GameStatus::Active{turn: other(player)}),
+    (GameStatus::Active{..}, Event::Reveal{..} | Event::Move{..}) => Err(GameError::WrongTurn),
+    (GameStatus::Active{..}, Event::End{winner}) => Ok(GameStatus::Finished{winner}),
+    // nobody has revealed yet, either player can still move the round forward
+    (GameStatus::Active{turn:0}, Event::Timeout) => Err(GameError::NoMovePending),
+    (GameStatus::Active{..}, Event::Timeout) => Ok(GameStatus::TimedOut),
+  }
+}
+
+fn other(player: u8) -> u8 {
+  if player == 1 {2} else {1}
+}
+
+// Always two bytes, the variant then its field or 0, so that game accounts
+// keep the size they were created with whatever state they are in.
+impl BorshSerialize for GameStatus {
+  fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+    let bytes: [u8; 2] = match *self {
+      GameStatus::Open => [0, 0],
+      GameStatus::Active{turn} => [1, turn],
+      GameStatus::Finished{winner} => [2, winner],
+      GameStatus::Aborted => [3, 0],
+      GameStatus::TimedOut => [4, 0],
+    };
+    writer.write_all(&bytes)
+  }
+}
+
+impl BorshDeserialize for GameStatus {
+  fn deserialize_reader<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+    let [tag, field]: [u8; 2] = <[u8; 2]>::deserialize_reader(reader)?;
+    match tag {
+      0 => Ok(GameStatus::Open),
+      1 => Ok(GameStatus::Active{turn:field}),
+      2 => Ok(GameStatus::Finished{winner:field}),
+      3 => Ok(GameStatus::Aborted),
+      4 => Ok(GameStatus::TimedOut),
+      _ => Err(io::Error::new(io::ErrorKind::InvalidData, "unknown game status")),
+    }
+  }
+}
@@ -0,0 +1,223 @@
+#![allow(dead_code)]
+
+use std::sync::Once;
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use rps::account::AccountData;
+use rps::commitment::commitment;
+use rps::error::GameError;
+use rps::event::{GameEvent, LOG_PREFIX};
+use rps::processor::Processor;
+use rps::state::{UpdateRent, CHAT_LINE_LEN, DEFAULT_MOVE_TIMEOUT, DEFAULT_TIMEOUT_FEE_BPS, DEFAULT_WIN_FEE_BPS, RENT_VERSION};
+use solana_program::{
+  account_info::AccountInfo,
+  bpf_loader_upgradeable,
+  clock::Clock,
+  entrypoint::ProgramResult,
+  instruction::{Instruction, InstructionError},
+  program_stubs::{set_syscall_stubs, SyscallStubs},
+  keccak,
+  pubkey::Pubkey,
+  rent::Rent,
+};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+  account::Account,
+  signature::{Keypair, Signer},
+  system_program,
+  transaction::{Transaction, TransactionError},
+};
+
+pub const PLAYER_FUNDS: u64 = 100_000_000_000;
+
+pub fn program_test(program_id: &Pubkey) -> ProgramTest {
+  ProgramTest::new("rps", *program_id, processor!(Processor::process))
+}
+
+pub fn add_funded(test: &mut ProgramTest, key: &Pubkey) {
+  test.add_account(*key, Account {
+    lamports: PLAYER_FUNDS,
+    data: vec![],
+    owner: system_program::id(),
+    executable: false,
+    rent_epoch: 0,
+  });
+}
+
+/// ProgramData of the program under test, naming `authority` as the one who
+/// can upgrade it and so send InitConfig.
+pub fn add_program_data(test: &mut ProgramTest, program_id: &Pubkey, authority: &Pubkey) {
+  let mut data: Vec<u8> = Vec::new();
+  data.extend_from_slice(&3u32.to_le_bytes());
+  data.extend_from_slice(&0u64.to_le_bytes());
+  data.push(1);
+  data.extend_from_slice(authority.as_ref());
+  test.add_account(rps::client::find_program_data_address(program_id), Account {
+    lamports: Rent::default().minimum_balance(data.len()),
+    data,
+    owner: bpf_loader_upgradeable::id(),
+    executable: false,
+    rent_epoch: 0,
+  });
+}
+
+pub fn add_program_account<T: AccountData>(test: &mut ProgramTest, program_id: &Pubkey, key: &Pubkey, value: &T) {
+  let data = value.to_account_data().unwrap();
+  test.add_account(*key, Account {
+    lamports: Rent::default().minimum_balance(data.len()),
+    data,
+    owner: *program_id,
+    executable: false,
+    rent_epoch: 0,
+  });
+}
+
+/// Protocol config with the default fees, timeout and chat length.
+pub fn rent_record(rent: u64) -> UpdateRent {
+  UpdateRent{
+    is_init: 1,
+    rent,
+    version: RENT_VERSION,
+    win_fee_bps: DEFAULT_WIN_FEE_BPS,
+    timeout_fee_bps: DEFAULT_TIMEOUT_FEE_BPS,
+    move_timeout: DEFAULT_MOVE_TIMEOUT,
+    max_chat_len: CHAT_LINE_LEN,
+  }
+}
+
+pub fn add_rent_account(test: &mut ProgramTest, program_id: &Pubkey, key: &Pubkey, rent: u64) {
+  add_program_account(test, program_id, key, &rent_record(rent));
+}
+
+/// Deterministic per-round salt, real clients draw 32 random bytes.
+pub fn salt(round: u16) -> [u8; 32] {
+  keccak::hashv(&[b"test-salt", &round.to_le_bytes()]).0
+}
+
+/// Commitment `player` publishes before revealing `mymove` in `round` of `game`.
+pub fn commit(game: &Pubkey, round: u16, player: &Pubkey, mymove: u8) -> [u8; 32] {
+  commitment(game, round, player, mymove, &salt(round))
+}
+
+pub async fn process(
+  context: &mut ProgramTestContext,
+  instructions: &[Instruction],
+  signers: &[&Keypair]) -> Result<(), BanksClientError> {
+
+  let blockhash = context.get_new_latest_blockhash().await.unwrap();
+
+  let mut all_signers: Vec<&Keypair> = vec![&context.payer];
+  all_signers.extend_from_slice(signers);
+
+  let transaction = Transaction::new_signed_with_payer(
+    instructions,
+    Some(&context.payer.pubkey()),
+    &all_signers,
+    blockhash,
+  );
+  context.banks_client.process_transaction(transaction).await
+}
+
+// solana-program-test only prints sol_log_data for natively built programs.
+// This forwards it to the transaction log, behind the usual "Program log: ".
+struct LogDataStubs(Box<dyn SyscallStubs>);
+
+impl SyscallStubs for LogDataStubs {
+  fn sol_log(&self, message: &str) {self.0.sol_log(message)}
+  fn sol_log_data(&self, fields: &[&[u8]]) {
+    let encoded: Vec<String> = fields.iter().map(|field| BASE64_STANDARD.encode(field)).collect();
+    self.0.sol_log(&format!("{}{}", LOG_PREFIX, encoded.join(" ")))
+  }
+  fn sol_invoke_signed(&self, instruction: &Instruction, account_infos: &[AccountInfo], signers_seeds: &[&[&[u8]]]) -> ProgramResult {
+    self.0.sol_invoke_signed(instruction, account_infos, signers_seeds)
+  }
+  fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {self.0.sol_get_clock_sysvar(var_addr)}
+  fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {self.0.sol_get_epoch_schedule_sysvar(var_addr)}
+  fn sol_get_epoch_rewards_sysvar(&self, var_addr: *mut u8) -> u64 {self.0.sol_get_epoch_rewards_sysvar(var_addr)}
+  fn sol_get_fees_sysvar(&self, var_addr: *mut u8) -> u64 {self.0.sol_get_fees_sysvar(var_addr)}
+  fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {self.0.sol_get_rent_sysvar(var_addr)}
+  fn sol_get_last_restart_slot(&self, var_addr: *mut u8) -> u64 {self.0.sol_get_last_restart_slot(var_addr)}
+  fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {self.0.sol_get_return_data()}
+  fn sol_set_return_data(&self, data: &[u8]) {self.0.sol_set_return_data(data)}
+  fn sol_get_stack_height(&self) -> u64 {self.0.sol_get_stack_height()}
+}
+
+// Like `process`, returning the events the program logged.
+pub async fn process_events(
+  context: &mut ProgramTestContext,
+  instructions: &[Instruction],
+  signers: &[&Keypair]) -> Vec<GameEvent> {
+
+  // program-test installs its own stubs when the first context starts
+  static WRAP: Once = Once::new();
+  WRAP.call_once(|| {
+    let inner: Box<dyn SyscallStubs> = set_syscall_stubs(Box::new(DefaultStubs));
+    set_syscall_stubs(Box::new(LogDataStubs(inner)));
+  });
+
+  let blockhash = context.get_new_latest_blockhash().await.unwrap();
+
+  let mut all_signers: Vec<&Keypair> = vec![&context.payer];
+  all_signers.extend_from_slice(signers);
+
+  let transaction = Transaction::new_signed_with_payer(
+    instructions,
+    Some(&context.payer.pubkey()),
+    &all_signers,
+    blockhash,
+  );
+  let result = context.banks_client.process_transaction_with_metadata(transaction).await.unwrap();
+  result.result.unwrap();
+  let logs: Vec<String> = result.metadata.unwrap().log_messages.iter()
+    .map(|line| line.strip_prefix("Program log: ").unwrap_or(line).to_string())
+    .collect();
+  GameEvent::from_logs(&logs)
+}
+
+struct DefaultStubs;
+impl SyscallStubs for DefaultStubs {}
+
+pub async fn balance(context: &mut ProgramTestContext, key: &Pubkey) -> u64 {
+  context.banks_client.get_balance(*key).await.unwrap()
+}
+
+pub async fn account_data(context: &mut ProgramTestContext, key: &Pubkey) -> Option<Vec<u8>> {
+  context.banks_client.get_account(*key).await.unwrap().map(|account| account.data)
+}
+
+/// Replaces the data of an existing account, keeping its lamports and owner.
+pub async fn overwrite(context: &mut ProgramTestContext, key: &Pubkey, data: Vec<u8>) {
+  let mut account = context.banks_client.get_account(*key).await.unwrap().unwrap();
+  account.data = data;
+  context.set_account(key, &account.into());
+}
+
+pub async fn warp_seconds(context: &mut ProgramTestContext, seconds: i64) {
+  let mut clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+  clock.unix_timestamp += seconds;
+  context.set_sysvar(&clock);
+}
+
+pub fn assert_game_error(result: Result<(), BanksClientError>, error: GameError) {
+  match result.unwrap_err().unwrap() {
+    TransactionError::InstructionError(_, InstructionError::Custom(code)) => assert_eq!(code, error as u32),
+    other => panic!("expected {:?}, got {:?}", error, other),
+  }
+}
+
+pub fn set_program_account<T: AccountData>(context: &mut ProgramTestContext, program_id: &Pubkey, key: &Pubkey, value: &T) {
+  let data = value.to_account_data().unwrap();
+  let account = Account {
+    lamports: Rent::default().minimum_balance(data.len()),
+    data,
+    owner: *program_id,
+    executable: false,
+    rent_epoch: 0,
+  };
+  context.set_account(key, &account.into());
+}
+
+pub async fn now(context: &mut ProgramTestContext) -> u64 {
+  let clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+  clock.unix_timestamp as u64
+}
@@ -0,0 +1,237 @@
+mod common;
+
+use borsh::BorshSerialize;
+use common::*;
+use rps::account::AccountData;
+use rps::client;
+use rps::error::GameError;
+use rps::state::{Config, InitConfig, ProposeAuthority, UpdateRent, RENT_VERSION};
+use solana_program::{pubkey::Pubkey, rent::Rent, system_instruction};
+use solana_program_test::{BanksClientError, ProgramTestContext};
+use solana_sdk::{account::Account, signature::{Keypair, Signer}};
+
+struct Admin {
+  program_id: Pubkey,
+  authority: Keypair,
+  successor: Keypair,
+  config: Pubkey,
+  rent_data: Pubkey,
+}
+
+async fn setup() -> (ProgramTestContext, Admin) {
+  let program_id = Pubkey::new_unique();
+  let authority = Keypair::new();
+  let successor = Keypair::new();
+  let rent_data = Pubkey::new_unique();
+  let (config, bump) = client::find_config_address(&program_id);
+
+  let mut test = program_test(&program_id);
+  add_funded(&mut test, &authority.pubkey());
+  add_funded(&mut test, &successor.pubkey());
+  add_program_data(&mut test, &program_id, &authority.pubkey());
+  // version 0 record: is_init and rent only
+  let legacy = (1u8, 1u64).try_to_vec().unwrap();
+  test.add_account(rent_data, Account {
+    lamports: Rent::default().minimum_balance(legacy.len()),
+    data: legacy,
+    owner: program_id,
+    executable: false,
+    rent_epoch: 0,
+  });
+  let mut context = test.start_with_context().await;
+
+  let init = client::init_config(&program_id, &authority.pubkey(), &config, InitConfig{ bump });
+  process(&mut context, &[init], &[&authority]).await.unwrap();
+
+  (context, Admin{ program_id, authority, successor, config, rent_data })
+}
+
+async fn update_rent(context: &mut ProgramTestContext, admin: &Admin, signer: &Keypair, rent: u64) -> Result<(), BanksClientError> {
+  update_protocol(context, admin, signer, rent_record(rent)).await
+}
+
+async fn update_protocol(context: &mut ProgramTestContext, admin: &Admin, signer: &Keypair, r: UpdateRent) -> Result<(), BanksClientError> {
+  let ix = client::update_rent(&admin.program_id, &admin.rent_data, &signer.pubkey(), &admin.config, r);
+  process(context, &[ix], &[signer]).await
+}
+
+async fn read_config(context: &mut ProgramTestContext, admin: &Admin) -> Config {
+  Config::load(&account_data(context, &admin.config).await.unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn init_config_records_authority() {
+  let (mut context, admin) = setup().await;
+
+  let config = read_config(&mut context, &admin).await;
+  assert_eq!(config.is_init, 1);
+  assert_eq!(config.authority, admin.authority.pubkey().to_bytes());
+  assert_eq!(config.pending_authority, [0; 32]);
+
+  let (_, bump) = client::find_config_address(&admin.program_id);
+  let again = client::init_config(&admin.program_id, &admin.successor.pubkey(), &admin.config, InitConfig{ bump });
+  assert!(process(&mut context, &[again], &[&admin.successor]).await.is_err());
+}
+
+#[tokio::test]
+async fn only_the_upgrade_authority_can_init_config() {
+  let program_id = Pubkey::new_unique();
+  let authority = Keypair::new();
+  let stranger = Keypair::new();
+  let (config, bump) = client::find_config_address(&program_id);
+
+  let mut test = program_test(&program_id);
+  add_funded(&mut test, &authority.pubkey());
+  add_funded(&mut test, &stranger.pubkey());
+  add_program_data(&mut test, &program_id, &authority.pubkey());
+  let mut context = test.start_with_context().await;
+
+  let first = client::init_config(&program_id, &stranger.pubkey(), &config, InitConfig{ bump });
+  assert_game_error(process(&mut context, &[first], &[&stranger]).await, GameError::Unauthorized);
+  assert!(account_data(&mut context, &config).await.is_none());
+
+  let init = client::init_config(&program_id, &authority.pubkey(), &config, InitConfig{ bump });
+  process(&mut context, &[init], &[&authority]).await.unwrap();
+  assert_eq!(Config::load(&account_data(&mut context, &config).await.unwrap()).unwrap().authority, authority.pubkey().to_bytes());
+}
+
+#[tokio::test]
+async fn a_prefunded_config_can_still_be_created() {
+  let program_id = Pubkey::new_unique();
+  let authority = Keypair::new();
+  let (config, bump) = client::find_config_address(&program_id);
+
+  let mut test = program_test(&program_id);
+  add_funded(&mut test, &authority.pubkey());
+  add_program_data(&mut test, &program_id, &authority.pubkey());
+  let mut context = test.start_with_context().await;
+
+  // the least anyone can send to an empty address
+  let grief = system_instruction::transfer(&context.payer.pubkey(), &config, Rent::default().minimum_balance(0));
+  process(&mut context, &[grief], &[]).await.unwrap();
+
+  let init = client::init_config(&program_id, &authority.pubkey(), &config, InitConfig{ bump });
+  process(&mut context, &[init], &[&authority]).await.unwrap();
+  assert_eq!(Config::load(&account_data(&mut context, &config).await.unwrap()).unwrap().authority, authority.pubkey().to_bytes());
+}
+
+#[tokio::test]
+async fn only_authority_can_update_rent() {
+  let (mut context, admin) = setup().await;
+
+  update_rent(&mut context, &admin, &admin.authority, 5_000).await.unwrap();
+  let data = account_data(&mut context, &admin.rent_data).await.unwrap();
+  assert_eq!(UpdateRent::load(&data).unwrap().rent, 5_000);
+
+  assert_game_error(update_rent(&mut context, &admin, &admin.successor, 7_000).await, GameError::Unauthorized);
+}
+
+#[tokio::test]
+async fn update_rent_upgrades_legacy_record() {
+  let (mut context, admin) = setup().await;
+  assert_eq!(account_data(&mut context, &admin.rent_data).await.unwrap().len(), 9);
+
+  let mut record = rent_record(5_000);
+  record.version = 0;
+  record.win_fee_bps = 300;
+  update_protocol(&mut context, &admin, &admin.authority, record).await.unwrap();
+
+  let data = account_data(&mut context, &admin.rent_data).await.unwrap();
+  let stored = UpdateRent::load(&data).unwrap();
+  assert_eq!((stored.version, stored.rent, stored.win_fee_bps), (RENT_VERSION, 5_000, 300));
+  assert_eq!(balance(&mut context, &admin.rent_data).await, Rent::default().minimum_balance(data.len()));
+}
+
+#[tokio::test]
+async fn close_account_recovers_accounts_from_before_the_header() {
+  let (mut context, admin) = setup().await;
+  let legacy = Pubkey::new_unique();
+  let held = 3_000_000_000;
+  context.set_account(&legacy, &Account {
+    lamports: held,
+    data: vec![1; 64],
+    owner: admin.program_id,
+    executable: false,
+    rent_epoch: 0,
+  }.into());
+
+  let before = balance(&mut context, &admin.authority.pubkey()).await;
+  let close = client::close_account(&admin.program_id, &admin.authority.pubkey(), &legacy, &admin.config);
+  process(&mut context, &[close], &[&admin.authority]).await.unwrap();
+  assert_eq!(balance(&mut context, &admin.authority.pubkey()).await, before + held);
+  assert_eq!(balance(&mut context, &legacy).await, 0);
+}
+
+#[tokio::test]
+async fn update_rent_rejects_invalid_limits() {
+  let (mut context, admin) = setup().await;
+
+  let mut record = rent_record(5_000);
+  record.win_fee_bps = 10_001;
+  assert_game_error(update_protocol(&mut context, &admin, &admin.authority, record).await, GameError::InvalidConfig);
+
+  let mut record = rent_record(5_000);
+  record.move_timeout = 0;
+  assert_game_error(update_protocol(&mut context, &admin, &admin.authority, record).await, GameError::InvalidConfig);
+
+  let mut record = rent_record(5_000);
+  record.max_chat_len = 51;
+  assert_game_error(update_protocol(&mut context, &admin, &admin.authority, record).await, GameError::InvalidConfig);
+}
+
+#[tokio::test]
+async fn authority_transfer_takes_two_steps() {
+  let (mut context, admin) = setup().await;
+
+  let propose = client::propose_authority(
+    &admin.program_id,
+    &admin.authority.pubkey(),
+    &admin.config,
+    ProposeAuthority{ new_authority: admin.successor.pubkey().to_bytes() },
+  );
+  process(&mut context, &[propose], &[&admin.authority]).await.unwrap();
+
+  // nothing changes until the successor accepts
+  let config = read_config(&mut context, &admin).await;
+  assert_eq!(config.authority, admin.authority.pubkey().to_bytes());
+  assert_eq!(config.pending_authority, admin.successor.pubkey().to_bytes());
+  assert_game_error(update_rent(&mut context, &admin, &admin.successor, 7_000).await, GameError::Unauthorized);
+
+  let accept = client::accept_authority(&admin.program_id, &admin.successor.pubkey(), &admin.config);
+  process(&mut context, &[accept], &[&admin.successor]).await.unwrap();
+
+  let config = read_config(&mut context, &admin).await;
+  assert_eq!(config.authority, admin.successor.pubkey().to_bytes());
+  assert_eq!(config.pending_authority, [0; 32]);
+
+  update_rent(&mut context, &admin, &admin.successor, 7_000).await.unwrap();
+  assert_game_error(update_rent(&mut context, &admin, &admin.authority, 5_000).await, GameError::Unauthorized);
+}
+
+#[tokio::test]
+async fn accept_requires_pending_authority() {
+  let (mut context, admin) = setup().await;
+
+  let accept = client::accept_authority(&admin.program_id, &admin.successor.pubkey(), &admin.config);
+  assert_game_error(process(&mut context, &[accept], &[&admin.successor]).await, GameError::NotPendingAuthority);
+
+  let propose = client::propose_authority(
+    &admin.program_id,
+    &admin.authority.pubkey(),
+    &admin.config,
+    ProposeAuthority{ new_authority: admin.successor.pubkey().to_bytes() },
+  );
+  process(&mut context, &[propose], &[&admin.authority]).await.unwrap();
+
+  let intruder = Keypair::new();
+  let accept = client::accept_authority(&admin.program_id, &intruder.pubkey(), &admin.config);
+  assert_game_error(process(&mut context, &[accept], &[&intruder]).await, GameError::NotPendingAuthority);
+
+  let propose = client::propose_authority(
+    &admin.program_id,
+    &admin.successor.pubkey(),
+    &admin.config,
+    ProposeAuthority{ new_authority: admin.successor.pubkey().to_bytes() },
+  );
+  assert_game_error(process(&mut context, &[propose], &[&admin.successor]).await, GameError::Unauthorized);
+}
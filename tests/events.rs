@@ -0,0 +1,194 @@
+mod common;
+
+use common::*;
+use rps::client;
+use rps::event::GameEvent;
+use rps::rules;
+use rps::state::{Chat, Commit, Init, InitializerPlay};
+use solana_program::{instruction::Instruction, pubkey::Pubkey, rent::Rent};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::signature::{Keypair, Signer};
+
+const STAKE: u64 = 1_000_000_000;
+const GAME_SEED: &str = "game000001";
+const GAME_STATE_LEN: usize = 849;
+const ROCK: u8 = 1;
+const PAPER: u8 = 3;
+
+struct Game {
+  program_id: Pubkey,
+  initializer: Keypair,
+  guest: Keypair,
+  host: Keypair,
+  rent_data: Pubkey,
+  game_state: Pubkey,
+  bump: u8,
+}
+
+async fn setup() -> (ProgramTestContext, Game) {
+  let program_id = Pubkey::new_unique();
+  let initializer = Keypair::new();
+  let guest = Keypair::new();
+  let host = Keypair::new();
+  let rent_data = Pubkey::new_unique();
+
+  let mut test = program_test(&program_id);
+  add_funded(&mut test, &initializer.pubkey());
+  add_funded(&mut test, &guest.pubkey());
+  add_funded(&mut test, &host.pubkey());
+  add_rent_account(&mut test, &program_id, &rent_data, Rent::default().minimum_balance(GAME_STATE_LEN));
+
+  let (game_state, bump) = client::find_game_address(&program_id, &initializer.pubkey(), GAME_SEED);
+  let context = test.start_with_context().await;
+
+  (context, Game{ program_id, initializer, guest, host, rent_data, game_state, bump })
+}
+
+fn init_game(game: &Game, first_move: u8) -> Instruction {
+  client::init_game(
+    &game.program_id,
+    &game.initializer.pubkey(),
+    &game.game_state,
+    &game.host.pubkey(),
+    &game.rent_data,
+    Init{
+      bump: game.bump,
+      game_ends: 1,
+      gameseed: GAME_SEED.to_string(),
+      lamports: STAKE,
+      game_hash: commit(&game.game_state, 0, &game.initializer.pubkey(), first_move),
+      max_rounds: 0,
+      sudden_death: 0,
+      ruleset: rules::CLASSIC,
+      moves: 0,
+      ranked: 0,
+      min_rating: 0,
+      max_rating: 0,
+      invited: [0; 32],
+      allowlist_root: [0; 32],
+      mint: [0; 32],
+      lobby_page: 0,
+    },
+  )
+}
+
+async fn join_game(context: &mut ProgramTestContext, game: &Game, mymove: u8) -> Vec<GameEvent> {
+  let join = client::join_game(
+    &game.program_id,
+    &game.guest.pubkey(),
+    &game.game_state,
+    &game.host.pubkey(),
+    Commit{ game_hash: commit(&game.game_state, 0, &game.guest.pubkey(), mymove), proof: vec![] },
+  );
+  process_events(context, &[join], &[&game.guest]).await
+}
+
+fn opening(lastmove: u8) -> InitializerPlay {
+  InitializerPlay{ salt: salt(0), lastmove, new_game_hash: [0; 32] }
+}
+
+#[tokio::test]
+async fn a_game_logs_every_step() {
+  let (mut context, game) = setup().await;
+  let game_key = game.game_state.to_bytes();
+
+  let events = process_events(&mut context, &[init_game(&game, ROCK)], &[&game.initializer]).await;
+  assert_eq!(events, vec![GameEvent::GameCreated{
+    game: game_key,
+    initializer: game.initializer.pubkey().to_bytes(),
+    host: game.host.pubkey().to_bytes(),
+    lamports: STAKE,
+    game_ends: 1,
+    ruleset: rules::CLASSIC,
+  }]);
+
+  let events = join_game(&mut context, &game, PAPER).await;
+  assert_eq!(events, vec![GameEvent::GameJoined{ game: game_key, guest: game.guest.pubkey().to_bytes() }]);
+
+  let chat = client::chat_local(
+    &game.program_id,
+    &game.initializer.pubkey(),
+    &game.guest.pubkey(),
+    &game.guest.pubkey(),
+    &game.game_state,
+    &game.rent_data,
+    Chat{ chat: format!("{:<50}", "gl") },
+  );
+  let events = process_events(&mut context, &[chat], &[&game.guest]).await;
+  assert_eq!(events, vec![GameEvent::ChatPosted{ channel: game_key, speaker: game.guest.pubkey().to_bytes(), text: format!("{:<50}", "gl") }]);
+
+  let play = client::initializer_play(
+    &game.program_id,
+    &game.initializer.pubkey(),
+    &game.guest.pubkey(),
+    &game.host.pubkey(),
+    &game.game_state,
+    &game.rent_data,
+    opening(ROCK),
+  );
+  let events = process_events(&mut context, &[play], &[&game.initializer]).await;
+  assert_eq!(events, vec![GameEvent::MoveRevealed{ game: game_key, player: game.initializer.pubkey().to_bytes(), round: 0 }]);
+
+  let play = client::guest_play(
+    &game.program_id,
+    &game.initializer.pubkey(),
+    &game.guest.pubkey(),
+    &game.host.pubkey(),
+    &game.game_state,
+    &game.rent_data,
+    opening(PAPER),
+  );
+  let events = process_events(&mut context, &[play], &[&game.guest]).await;
+  assert_eq!(events, vec![
+    GameEvent::MoveRevealed{ game: game_key, player: game.guest.pubkey().to_bytes(), round: 0 },
+    GameEvent::RoundResolved{ game: game_key, round: 0, initializer_move: ROCK, guest_move: PAPER, winner: 2, score_i: 0, score_g: 1 },
+    GameEvent::GameWon{ game: game_key, winner: 2, host_fee: STAKE / 50 },
+  ]);
+}
+
+#[tokio::test]
+async fn abort_and_timeout_are_logged() {
+  let (mut context, game) = setup().await;
+  let game_key = game.game_state.to_bytes();
+
+  process_events(&mut context, &[init_game(&game, ROCK)], &[&game.initializer]).await;
+  let abort = client::abort(&game.program_id, &game.initializer.pubkey(), &game.game_state);
+  let events = process_events(&mut context, &[abort], &[&game.initializer]).await;
+  assert_eq!(events, vec![GameEvent::Aborted{ game: game_key, refund: STAKE + Rent::default().minimum_balance(GAME_STATE_LEN) }]);
+
+  process_events(&mut context, &[init_game(&game, ROCK)], &[&game.initializer]).await;
+  join_game(&mut context, &game, PAPER).await;
+  let play = client::guest_play(
+    &game.program_id,
+    &game.initializer.pubkey(),
+    &game.guest.pubkey(),
+    &game.host.pubkey(),
+    &game.game_state,
+    &game.rent_data,
+    opening(PAPER),
+  );
+  process_events(&mut context, &[play], &[&game.guest]).await;
+
+  warp_seconds(&mut context, 121).await;
+  let claim = client::claim_victory(
+    &game.program_id,
+    &game.initializer.pubkey(),
+    &game.guest.pubkey(),
+    &game.host.pubkey(),
+    &game.game_state,
+    &game.rent_data,
+  );
+  let events = process_events(&mut context, &[claim], &[]).await;
+  assert_eq!(events, vec![GameEvent::TimeoutClaimed{ game: game_key, winner: game.guest.pubkey().to_bytes(), host_fee: STAKE / 100 }]);
+}
+
+#[test]
+fn unrelated_log_lines_are_skipped() {
+  let logs = [
+    "Program 11111111111111111111111111111111 invoke [1]",
+    "Program log: hello",
+    "Program data: !!!!",
+    "Program data: AAAA",
+  ];
+  assert_eq!(GameEvent::from_logs(&logs), vec![]);
+}
@@ -0,0 +1,211 @@
+use proptest::prelude::*;
+use rps::error::GameError;
+use rps::instruction::GameInstruction;
+use rps::rules::MAX_MOVES;
+use rps::state::{Chat, Commit, CounterFinder, Enqueue, FinderFinder, Init, InitConfig, InitTournamentCounter, InitializerPlay, Join, ProposeAuthority, Tournament, UpdateRent};
+
+fn init() -> impl Strategy<Value = Init> {
+  (
+    (any::<u8>(), any::<u8>(), ".{0,16}", any::<u64>(), any::<[u8; 32]>()),
+    (any::<u8>(), any::<u8>(), any::<u8>(), any::<u8>(), any::<u8>(), any::<u16>(), any::<u16>()),
+    (any::<[u8; 32]>(), any::<[u8; 32]>(), any::<[u8; 32]>(), any::<u16>()),
+  ).prop_map(
+    |((bump, game_ends, gameseed, lamports, game_hash), (max_rounds, sudden_death, ruleset, moves, ranked, min_rating, max_rating), (invited, allowlist_root, mint, lobby_page))| Init{
+      bump,
+      game_ends,
+      gameseed,
+      lamports,
+      game_hash,
+      max_rounds,
+      sudden_death,
+      ruleset,
+      moves,
+      ranked,
+      min_rating,
+      max_rating,
+      invited,
+      allowlist_root,
+      mint,
+      lobby_page,
+    },
+  )
+}
+
+fn join() -> impl Strategy<Value = Join> {
+  (1..=MAX_MOVES).prop_map(|mymove| Join{ mymove })
+}
+
+fn commit() -> impl Strategy<Value = Commit> {
+  (any::<[u8; 32]>(), prop::collection::vec(any::<[u8; 32]>(), 0..4)).prop_map(|(game_hash, proof)| Commit{ game_hash, proof })
+}
+
+fn initializer_play() -> impl Strategy<Value = InitializerPlay> {
+  (any::<[u8; 32]>(), 1..=MAX_MOVES, any::<[u8; 32]>()).prop_map(
+    |(salt, lastmove, new_game_hash)| InitializerPlay{ salt, lastmove, new_game_hash },
+  )
+}
+
+fn tournament() -> impl Strategy<Value = Tournament> {
+  (
+    (any::<u8>(), ".{0,16}", any::<u64>(), any::<u8>(), any::<u64>(), any::<u64>(), any::<u64>()),
+    (any::<[u8; 30]>(), any::<u64>(), any::<u8>(), any::<u8>(), any::<u8>(), any::<u8>(), any::<u8>(), any::<u8>()),
+    (any::<u8>(), any::<u8>(), any::<[u8; 32]>()),
+  ).prop_map(|(
+    (is_init, tournament_id, entrance_fee, tournament_size, rent, starts_at, time_is_up),
+    (lvl_get, winner_get, host_get, number_of_counters, number_of_counterfinders, finderfinder, game_ends, bump),
+    (ruleset, moves, mint),
+  )| Tournament{
+    is_init,
+    tournament_id,
+    entrance_fee,
+    tournament_size,
+    rent,
+    starts_at,
+    time_is_up,
+    lvl_get,
+    winner_get,
+    host_get,
+    number_of_counters,
+    number_of_counterfinders,
+    finderfinder,
+    game_ends,
+    bump,
+    ruleset,
+    moves,
+    mint,
+  })
+}
+
+fn counter_finder() -> impl Strategy<Value = CounterFinder> {
+  (any::<u8>(), prop::collection::vec(any::<u8>(), 256), ".{0,16}").prop_map(
+    |(finder_no, counters, tournament_id)| CounterFinder{
+      finder_no,
+      counters: counters.try_into().unwrap(),
+      tournament_id,
+    },
+  )
+}
+
+fn update_rent() -> impl Strategy<Value = UpdateRent> {
+  (any::<u8>(), any::<u64>(), any::<u8>(), any::<u16>(), any::<u16>(), any::<u64>(), any::<u16>()).prop_map(
+    |(is_init, rent, version, win_fee_bps, timeout_fee_bps, move_timeout, max_chat_len)| UpdateRent{
+      is_init,
+      rent,
+      version,
+      win_fee_bps,
+      timeout_fee_bps,
+      move_timeout,
+      max_chat_len,
+    },
+  )
+}
+
+fn game_instruction() -> impl Strategy<Value = GameInstruction> {
+  prop_oneof![
+    init().prop_map(|init| GameInstruction::InitGame{ init }),
+    commit().prop_map(|join| GameInstruction::JoinGame{ join }),
+    initializer_play().prop_map(|play| GameInstruction::IPlay{ play }),
+    initializer_play().prop_map(|play| GameInstruction::GPlay{ play }),
+    Just(GameInstruction::ClaimVictory),
+    Just(GameInstruction::Abort),
+    tournament().prop_map(|t| GameInstruction::InitTournament{ t }),
+    init().prop_map(|init| GameInstruction::JoinTournament{ init }),
+    init().prop_map(|init| GameInstruction::InitTournamentMatch{ init }),
+    join().prop_map(|join| GameInstruction::TournamentMatchAccept{ join }),
+    initializer_play().prop_map(|play| GameInstruction::TournamentInPlay{ play }),
+    initializer_play().prop_map(|play| GameInstruction::TournamentGuPlay{ play }),
+    Just(GameInstruction::EliminateForIn),
+    Just(GameInstruction::EliminateForMv),
+    (any::<u16>(), any::<u32>(), ".{0,16}", any::<u8>(), any::<u64>()).prop_map(
+      |(counter_no, capacity, tournament_id, bump, rent)| GameInstruction::InitCounter{
+        t_counter: InitTournamentCounter{ counter_no, capacity, tournament_id, bump, rent },
+      },
+    ),
+    counter_finder().prop_map(|c_finder| GameInstruction::UpdateCounterFinder{ c_finder }),
+    (any::<[u8; 32]>(), ".{0,16}").prop_map(|(counters, tournament_id)| GameInstruction::UpdateFinderFinder{
+      f_finder: FinderFinder{ counters, tournament_id },
+    }),
+    Just(GameInstruction::CloseAccount),
+    ".{0,50}".prop_map(|chat| GameInstruction::ChatGlobal{ chat: Chat{ chat } }),
+    ".{0,50}".prop_map(|chat| GameInstruction::ChatLocal{ chat: Chat{ chat } }),
+    update_rent().prop_map(|r| GameInstruction::UpRent{ r }),
+    Just(GameInstruction::WinnerClaim),
+    any::<u8>().prop_map(|bump| GameInstruction::InitConfig{ c: InitConfig{ bump } }),
+    any::<[u8; 32]>().prop_map(|new_authority| GameInstruction::ProposeAuthority{ p: ProposeAuthority{ new_authority } }),
+    Just(GameInstruction::AcceptAuthority),
+    (any::<u8>(), ".{0,16}", any::<u64>(), any::<[u8; 32]>()).prop_map(|(bump, gameseed, lamports, game_hash)| GameInstruction::Enqueue{
+      q: Enqueue{ bump, gameseed, lamports, game_hash },
+    }),
+  ]
+}
+
+proptest! {
+  #[test]
+  fn pack_unpack_round_trip(instruction in game_instruction()) {
+    let packed = instruction.pack().unwrap();
+    prop_assert_eq!(GameInstruction::unpack(&packed).unwrap(), instruction);
+  }
+
+  #[test]
+  fn unpack_pack_round_trip(instruction in game_instruction()) {
+    let packed = instruction.pack().unwrap();
+    prop_assert_eq!(GameInstruction::unpack(&packed).unwrap().pack().unwrap(), packed);
+  }
+}
+
+#[test]
+fn tags_match_table() {
+  let tags: Vec<u8> = vec![
+    GameInstruction::ClaimVictory.pack().unwrap()[0],
+    GameInstruction::Abort.pack().unwrap()[0],
+    GameInstruction::EliminateForIn.pack().unwrap()[0],
+    GameInstruction::EliminateForMv.pack().unwrap()[0],
+    GameInstruction::CloseAccount.pack().unwrap()[0],
+    GameInstruction::WinnerClaim.pack().unwrap()[0],
+    GameInstruction::AcceptAuthority.pack().unwrap()[0],
+  ];
+  assert_eq!(tags, vec![4, 5, 13, 14, 18, 22, 25]);
+  assert_eq!(GameInstruction::JoinGame{ join: Commit{ game_hash: [2; 32], proof: vec![] } }.pack().unwrap()[0], 1);
+  assert_eq!(GameInstruction::GPlay{ play: InitializerPlay{ salt: [0; 32], lastmove: 3, new_game_hash: [0; 32] } }.pack().unwrap()[0], 3);
+  assert_eq!(GameInstruction::TournamentMatchAccept{ join: Join{ mymove: 1 } }.pack().unwrap(), vec![9, 1]);
+}
+
+#[test]
+fn reserved_and_unknown_tags_are_rejected() {
+  assert!(GameInstruction::unpack(&[]).is_err());
+  assert!(GameInstruction::unpack(&[10]).is_err());
+  assert!(GameInstruction::unpack(&[27]).is_err());
+  assert!(GameInstruction::unpack(&[255]).is_err());
+}
+
+#[test]
+fn truncated_payload_is_rejected() {
+  let packed = GameInstruction::UpRent{ r: UpdateRent{
+    is_init: 1,
+    rent: 5_000,
+    version: 1,
+    win_fee_bps: 200,
+    timeout_fee_bps: 100,
+    move_timeout: 120,
+    max_chat_len: 50,
+  } }.pack().unwrap();
+  assert!(GameInstruction::unpack(&packed[..packed.len() - 1]).is_err());
+}
+
+#[test]
+fn out_of_range_moves_are_rejected() {
+  for mymove in [0, MAX_MOVES + 1, 255] {
+    let join = Join{ mymove };
+    let play = InitializerPlay{ salt: [7; 32], lastmove: mymove, new_game_hash: [0; 32] };
+    let instructions = [
+      GameInstruction::TournamentMatchAccept{ join },
+      GameInstruction::GPlay{ play: play.clone() },
+      GameInstruction::IPlay{ play: play.clone() },
+      GameInstruction::TournamentInPlay{ play: play.clone() },
+      GameInstruction::TournamentGuPlay{ play },
+    ];
+    for instruction in instructions {
+      assert_eq!(GameInstruction::unpack(&instruction.pack().unwrap()), Err(GameError::InvalidMove.into()));
+    }
+  }
+}
@@ -0,0 +1,770 @@
+mod common;
+
+use common::*;
+use rps::account::{AccountData, DISCRIMINATOR_LEN, HEADER_LEN};
+use rps::client;
+use rps::error::GameError;
+use rps::rules;
+use rps::state::{Chat, ChatGlobal, Commit, GameState, Init, InitializerPlay, RoundRecord, TGameState, UpdateRent, HISTORY_LEN};
+use rps::status::GameStatus;
+use solana_program::{pubkey::Pubkey, rent::Rent, system_instruction};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::signature::{Keypair, Signer};
+
+const STAKE: u64 = 1_000_000_000;
+const GAME_SEED: &str = "game000001";
+// header 9 + host 32 + status 2 + gameseed 14 + lamports 8 + initializer/gamehash/guest/guest_hash 128
+// + initializer_move..moves 11 + ranked/min_rating/max_rating 5 + invited/allowlist_root 64 + mint 32 + bump 1 + lobby_page 2 + queued 1 + lastplaytime 8 + 16 round records 208 + six 50 character chat lines 324
+const GAME_STATE_LEN: usize = 849;
+
+// 1 rock, 2 scissors, 3 paper
+const ROCK: u8 = 1;
+const SCISSORS: u8 = 2;
+const PAPER: u8 = 3;
+
+struct Game {
+  program_id: Pubkey,
+  initializer: Keypair,
+  guest: Keypair,
+  host: Keypair,
+  rent_data: Pubkey,
+  game_state: Pubkey,
+  bump: u8,
+  rent: u64,
+}
+
+async fn setup() -> (ProgramTestContext, Game) {
+  setup_with(|_| {}).await
+}
+
+async fn setup_with(protocol: fn(&mut UpdateRent)) -> (ProgramTestContext, Game) {
+  let program_id = Pubkey::new_unique();
+  let initializer = Keypair::new();
+  let guest = Keypair::new();
+  let host = Keypair::new();
+  let rent_data = Pubkey::new_unique();
+  let rent = Rent::default().minimum_balance(GAME_STATE_LEN);
+
+  let mut test = program_test(&program_id);
+  add_funded(&mut test, &initializer.pubkey());
+  add_funded(&mut test, &guest.pubkey());
+  add_funded(&mut test, &host.pubkey());
+  let mut record = rent_record(rent);
+  protocol(&mut record);
+  add_program_account(&mut test, &program_id, &rent_data, &record);
+
+  let (game_state, bump) = client::find_game_address(&program_id, &initializer.pubkey(), GAME_SEED);
+  let context = test.start_with_context().await;
+
+  (context, Game{ program_id, initializer, guest, host, rent_data, game_state, bump, rent })
+}
+
+async fn init_game(context: &mut ProgramTestContext, game: &Game, game_ends: u8, first_move: u8) {
+  init_match(context, game, (game_ends, 0, 0), first_move).await.unwrap();
+}
+
+// format is (game_ends, max_rounds, sudden_death)
+async fn init_match(
+  context: &mut ProgramTestContext,
+  game: &Game,
+  format: (u8, u8, u8),
+  first_move: u8) -> Result<(), solana_program_test::BanksClientError> {
+
+  init_with_rules(context, game, format, (rules::CLASSIC, 0), first_move).await
+}
+
+// rules is (ruleset, moves)
+async fn init_with_rules(
+  context: &mut ProgramTestContext,
+  game: &Game,
+  format: (u8, u8, u8),
+  rules: (u8, u8),
+  first_move: u8) -> Result<(), solana_program_test::BanksClientError> {
+
+  let init = client::init_game(
+    &game.program_id,
+    &game.initializer.pubkey(),
+    &game.game_state,
+    &game.host.pubkey(),
+    &game.rent_data,
+    Init{
+      bump: game.bump,
+      game_ends: format.0,
+      gameseed: GAME_SEED.to_string(),
+      lamports: STAKE,
+      game_hash: commit(&game.game_state, 0, &game.initializer.pubkey(), first_move),
+      max_rounds: format.1,
+      sudden_death: format.2,
+      ruleset: rules.0,
+      moves: rules.1,
+      ranked: 0,
+      min_rating: 0,
+      max_rating: 0,
+      invited: [0; 32],
+      allowlist_root: [0; 32],
+      mint: [0; 32],
+      lobby_page: 0,
+    },
+  );
+  process(context, &[init], &[&game.initializer]).await
+}
+
+// Joins committing `mymove` for the first round.
+async fn join_game(context: &mut ProgramTestContext, game: &Game, mymove: u8) {
+  let join = client::join_game(
+    &game.program_id,
+    &game.guest.pubkey(),
+    &game.game_state,
+    &game.host.pubkey(),
+    Commit{ game_hash: commit(&game.game_state, 0, &game.guest.pubkey(), mymove), proof: vec![] },
+  );
+  process(context, &[join], &[&game.guest]).await.unwrap();
+}
+
+// Reveals the move committed for the current round and commits `next` for the one after.
+async fn reveal(context: &mut ProgramTestContext, game: &Game, last: u8, next: u8) {
+  let round = game_state(context, game).await.rounds_played;
+  let play = client::initializer_play(
+    &game.program_id,
+    &game.initializer.pubkey(),
+    &game.guest.pubkey(),
+    &game.host.pubkey(),
+    &game.game_state,
+    &game.rent_data,
+    InitializerPlay{
+      salt: salt(round),
+      lastmove: last,
+      new_game_hash: commit(&game.game_state, round + 1, &game.initializer.pubkey(), next),
+    },
+  );
+  process(context, &[play], &[&game.initializer]).await.unwrap();
+}
+
+async fn try_reveal(
+  context: &mut ProgramTestContext,
+  game: &Game,
+  salt: [u8; 32],
+  lastmove: u8) -> Result<(), solana_program_test::BanksClientError> {
+
+  let play = client::initializer_play(
+    &game.program_id,
+    &game.initializer.pubkey(),
+    &game.guest.pubkey(),
+    &game.host.pubkey(),
+    &game.game_state,
+    &game.rent_data,
+    InitializerPlay{ salt, lastmove, new_game_hash: [0; 32] },
+  );
+  process(context, &[play], &[&game.initializer]).await
+}
+
+async fn guest_reveal(context: &mut ProgramTestContext, game: &Game, last: u8, next: u8) {
+  let round = game_state(context, game).await.rounds_played;
+  let play = InitializerPlay{
+    salt: salt(round),
+    lastmove: last,
+    new_game_hash: commit(&game.game_state, round + 1, &game.guest.pubkey(), next),
+  };
+  try_guest_play(context, game, play).await.unwrap();
+}
+
+async fn try_guest_play(
+  context: &mut ProgramTestContext,
+  game: &Game,
+  play: InitializerPlay) -> Result<(), solana_program_test::BanksClientError> {
+
+  let play = client::guest_play(
+    &game.program_id,
+    &game.initializer.pubkey(),
+    &game.guest.pubkey(),
+    &game.host.pubkey(),
+    &game.game_state,
+    &game.rent_data,
+    play,
+  );
+  process(context, &[play], &[&game.guest]).await
+}
+
+async fn claim_victory(context: &mut ProgramTestContext, game: &Game) -> Result<(), solana_program_test::BanksClientError> {
+  let claim = client::claim_victory(
+    &game.program_id,
+    &game.initializer.pubkey(),
+    &game.guest.pubkey(),
+    &game.host.pubkey(),
+    &game.game_state,
+    &game.rent_data,
+  );
+  process(context, &[claim], &[]).await
+}
+
+async fn game_state(context: &mut ProgramTestContext, game: &Game) -> GameState {
+  let data = account_data(context, &game.game_state).await.unwrap();
+  GameState::load(&data).unwrap()
+}
+
+#[tokio::test]
+async fn initializer_wins_best_of_three() {
+  let (mut context, game) = setup().await;
+
+  init_game(&mut context, &game, 3, PAPER).await;
+  assert_eq!(balance(&mut context, &game.initializer.pubkey()).await, PLAYER_FUNDS - STAKE - game.rent);
+  assert_eq!(balance(&mut context, &game.game_state).await, STAKE + game.rent);
+  let state = game_state(&mut context, &game).await;
+  assert_eq!((state.status, state.game_ends), (GameStatus::Open, 3));
+
+  join_game(&mut context, &game, ROCK).await;
+  assert_eq!(balance(&mut context, &game.guest.pubkey()).await, PLAYER_FUNDS - STAKE);
+  assert_eq!(balance(&mut context, &game.game_state).await, 2 * STAKE + game.rent);
+  let state = game_state(&mut context, &game).await;
+  assert_eq!(state.status, GameStatus::Active{turn:0});
+  assert_eq!(state.guest, game.guest.pubkey().to_bytes());
+
+  // paper covers rock, the round only resolves once both moves are open
+  reveal(&mut context, &game, PAPER, SCISSORS).await;
+  let state = game_state(&mut context, &game).await;
+  assert_eq!((state.score_i, state.score_g, state.status), (0, 0, GameStatus::Active{turn:2}));
+  guest_reveal(&mut context, &game, ROCK, SCISSORS).await;
+  let state = game_state(&mut context, &game).await;
+  assert_eq!((state.score_i, state.score_g, state.status), (1, 0, GameStatus::Active{turn:0}));
+
+  // draw, the guest may reveal first
+  guest_reveal(&mut context, &game, SCISSORS, PAPER).await;
+  let state = game_state(&mut context, &game).await;
+  assert_eq!(state.status, GameStatus::Active{turn:1});
+  reveal(&mut context, &game, SCISSORS, SCISSORS).await;
+  let state = game_state(&mut context, &game).await;
+  assert_eq!((state.score_i, state.score_g), (1, 0));
+
+  // scissors cut paper
+  reveal(&mut context, &game, SCISSORS, ROCK).await;
+  guest_reveal(&mut context, &game, PAPER, SCISSORS).await;
+  let state = game_state(&mut context, &game).await;
+  assert_eq!((state.score_i, state.score_g), (2, 0));
+
+  let history = client::round_history(&state);
+  let rounds: Vec<(u16, u8, u8, u8)> = history.iter().map(|r| (r.round, r.initializer_move, r.guest_move, r.winner)).collect();
+  assert_eq!(rounds, vec![(0, PAPER, ROCK, 1), (1, SCISSORS, SCISSORS, 0), (2, SCISSORS, PAPER, 1)]);
+  assert!(history.iter().all(|r| r.time > 0));
+
+  // rock crushes scissors, game over
+  reveal(&mut context, &game, ROCK, ROCK).await;
+  guest_reveal(&mut context, &game, SCISSORS, ROCK).await;
+
+  let host_fee = STAKE / 50;
+  assert_eq!(balance(&mut context, &game.host.pubkey()).await, PLAYER_FUNDS + host_fee);
+  assert_eq!(balance(&mut context, &game.guest.pubkey()).await, PLAYER_FUNDS - STAKE);
+  assert_eq!(balance(&mut context, &game.initializer.pubkey()).await, PLAYER_FUNDS + STAKE - host_fee);
+  assert_eq!(balance(&mut context, &game.game_state).await, 0);
+}
+
+#[tokio::test]
+async fn guest_wins_single_round() {
+  let (mut context, game) = setup().await;
+
+  init_game(&mut context, &game, 1, ROCK).await;
+  join_game(&mut context, &game, PAPER).await;
+  reveal(&mut context, &game, ROCK, ROCK).await;
+  guest_reveal(&mut context, &game, PAPER, PAPER).await;
+
+  let host_fee = STAKE / 50;
+  assert_eq!(balance(&mut context, &game.host.pubkey()).await, PLAYER_FUNDS + host_fee);
+  assert_eq!(balance(&mut context, &game.guest.pubkey()).await, PLAYER_FUNDS + STAKE - host_fee);
+  assert_eq!(balance(&mut context, &game.initializer.pubkey()).await, PLAYER_FUNDS - STAKE);
+  assert_eq!(balance(&mut context, &game.game_state).await, 0);
+}
+
+#[tokio::test]
+async fn a_finished_game_is_closed() {
+  let (mut context, game) = setup().await;
+
+  init_game(&mut context, &game, 1, ROCK).await;
+  join_game(&mut context, &game, PAPER).await;
+  reveal(&mut context, &game, ROCK, ROCK).await;
+
+  let play = client::guest_play(
+    &game.program_id,
+    &game.initializer.pubkey(),
+    &game.guest.pubkey(),
+    &game.host.pubkey(),
+    &game.game_state,
+    &game.rent_data,
+    InitializerPlay{ salt: salt(0), lastmove: PAPER, new_game_hash: [0; 32] },
+  );
+  let claim = client::claim_victory(
+    &game.program_id,
+    &game.initializer.pubkey(),
+    &game.guest.pubkey(),
+    &game.host.pubkey(),
+    &game.game_state,
+    &game.rent_data,
+  );
+  // not even later in the transaction that finished it
+  assert_game_error(process(&mut context, &[play.clone(), claim], &[&game.guest]).await, GameError::GameFinished);
+
+  process(&mut context, &[play], &[&game.guest]).await.unwrap();
+  assert_eq!(account_data(&mut context, &game.game_state).await, None);
+  assert_game_error(try_reveal(&mut context, &game, salt(0), ROCK).await, GameError::GameFinished);
+}
+
+#[tokio::test]
+async fn the_guest_must_sign_to_join() {
+  let (mut context, game) = setup().await;
+  init_game(&mut context, &game, 1, ROCK).await;
+
+  let mut join = client::join_game(
+    &game.program_id,
+    &game.guest.pubkey(),
+    &game.game_state,
+    &game.host.pubkey(),
+    Commit{ game_hash: commit(&game.game_state, 0, &game.guest.pubkey(), PAPER), proof: vec![] },
+  );
+  join.accounts[0].is_signer = false;
+  assert_game_error(process(&mut context, &[join], &[]).await, GameError::NotSigner);
+
+  join_game(&mut context, &game, PAPER).await;
+}
+
+#[tokio::test]
+async fn wrong_reveal_is_rejected() {
+  let (mut context, game) = setup().await;
+
+  init_game(&mut context, &game, 1, ROCK).await;
+  join_game(&mut context, &game, PAPER).await;
+
+  assert_game_error(try_reveal(&mut context, &game, salt(0), SCISSORS).await, GameError::HashMismatch);
+  assert_game_error(try_reveal(&mut context, &game, salt(1), ROCK).await, GameError::HashMismatch);
+  try_reveal(&mut context, &game, salt(0), ROCK).await.unwrap();
+
+  // each player opens only their own commitment
+  let play = InitializerPlay{ salt: salt(0), lastmove: ROCK, new_game_hash: [0; 32] };
+  assert_game_error(try_guest_play(&mut context, &game, play).await, GameError::HashMismatch);
+  let play = InitializerPlay{ salt: salt(0), lastmove: PAPER, new_game_hash: [0; 32] };
+  try_guest_play(&mut context, &game, play).await.unwrap();
+}
+
+#[tokio::test]
+async fn revealing_twice_in_a_round_is_rejected() {
+  let (mut context, game) = setup().await;
+
+  init_game(&mut context, &game, 3, ROCK).await;
+  join_game(&mut context, &game, PAPER).await;
+  reveal(&mut context, &game, ROCK, ROCK).await;
+
+  assert_game_error(try_reveal(&mut context, &game, salt(0), ROCK).await, GameError::WrongTurn);
+}
+
+#[tokio::test]
+async fn commitment_is_bound_to_game_and_round() {
+  let (mut context, game) = setup().await;
+
+  // a valid commitment for round 1 cannot open round 0
+  let init = client::init_game(
+    &game.program_id,
+    &game.initializer.pubkey(),
+    &game.game_state,
+    &game.host.pubkey(),
+    &game.rent_data,
+    Init{
+      bump: game.bump,
+      game_ends: 1,
+      gameseed: GAME_SEED.to_string(),
+      lamports: STAKE,
+      game_hash: commit(&game.game_state, 1, &game.initializer.pubkey(), ROCK),
+      max_rounds: 0,
+      sudden_death: 0,
+      ruleset: rules::CLASSIC,
+      moves: 0,
+      ranked: 0,
+      min_rating: 0,
+      max_rating: 0,
+      invited: [0; 32],
+      allowlist_root: [0; 32],
+      mint: [0; 32],
+      lobby_page: 0,
+    },
+  );
+  process(&mut context, &[init], &[&game.initializer]).await.unwrap();
+  join_game(&mut context, &game, PAPER).await;
+  assert_game_error(try_reveal(&mut context, &game, salt(1), ROCK).await, GameError::HashMismatch);
+
+  // nor can one made for another game
+  let other_game = Pubkey::new_unique();
+  let mut state = game_state(&mut context, &game).await;
+  state.gamehash = commit(&other_game, 0, &game.initializer.pubkey(), ROCK);
+  overwrite(&mut context, &game.game_state, state.to_account_data().unwrap()).await;
+  assert_game_error(try_reveal(&mut context, &game, salt(0), ROCK).await, GameError::HashMismatch);
+}
+
+#[tokio::test]
+async fn a_game_is_only_read_under_its_own_header() {
+  let (mut context, game) = setup().await;
+
+  init_game(&mut context, &game, 1, ROCK).await;
+  join_game(&mut context, &game, PAPER).await;
+  let data = account_data(&mut context, &game.game_state).await.unwrap();
+
+  // the same bytes tagged as a tournament game
+  let mut other_type = data.clone();
+  other_type[..DISCRIMINATOR_LEN].copy_from_slice(&TGameState::DISCRIMINATOR);
+  overwrite(&mut context, &game.game_state, other_type).await;
+  assert_game_error(try_reveal(&mut context, &game, salt(0), ROCK).await, GameError::WrongAccountType);
+
+  // or written by a newer layout than the program knows
+  let mut newer = data.clone();
+  newer[DISCRIMINATOR_LEN] = GameState::VERSION + 1;
+  overwrite(&mut context, &game.game_state, newer).await;
+  assert_game_error(try_reveal(&mut context, &game, salt(0), ROCK).await, GameError::UnsupportedVersion);
+
+  // or written before accounts had a header
+  overwrite(&mut context, &game.game_state, data[HEADER_LEN..].to_vec()).await;
+  assert_game_error(try_reveal(&mut context, &game, salt(0), ROCK).await, GameError::WrongAccountType);
+
+  overwrite(&mut context, &game.game_state, data).await;
+  try_reveal(&mut context, &game, salt(0), ROCK).await.unwrap();
+}
+
+#[tokio::test]
+async fn guest_claims_victory_when_initializer_times_out() {
+  let (mut context, game) = setup().await;
+
+  init_game(&mut context, &game, 3, ROCK).await;
+  join_game(&mut context, &game, PAPER).await;
+  guest_reveal(&mut context, &game, PAPER, PAPER).await;
+
+  warp_seconds(&mut context, 60).await;
+  assert_game_error(claim_victory(&mut context, &game).await, GameError::TimeoutNotReached);
+
+  warp_seconds(&mut context, 61).await;
+  claim_victory(&mut context, &game).await.unwrap();
+
+  let host_fee = STAKE / 100;
+  assert_eq!(balance(&mut context, &game.host.pubkey()).await, PLAYER_FUNDS + host_fee);
+  assert_eq!(balance(&mut context, &game.guest.pubkey()).await, PLAYER_FUNDS + STAKE - host_fee);
+  assert_eq!(balance(&mut context, &game.initializer.pubkey()).await, PLAYER_FUNDS - STAKE);
+  assert_eq!(balance(&mut context, &game.game_state).await, 0);
+}
+
+#[tokio::test]
+async fn initializer_claims_victory_when_guest_times_out() {
+  let (mut context, game) = setup().await;
+
+  init_game(&mut context, &game, 3, ROCK).await;
+  join_game(&mut context, &game, ROCK).await;
+  reveal(&mut context, &game, ROCK, ROCK).await;
+
+  warp_seconds(&mut context, 121).await;
+  claim_victory(&mut context, &game).await.unwrap();
+
+  let host_fee = STAKE / 100;
+  assert_eq!(balance(&mut context, &game.host.pubkey()).await, PLAYER_FUNDS + host_fee);
+  assert_eq!(balance(&mut context, &game.guest.pubkey()).await, PLAYER_FUNDS - STAKE);
+  assert_eq!(balance(&mut context, &game.initializer.pubkey()).await, PLAYER_FUNDS + STAKE - host_fee);
+  assert_eq!(balance(&mut context, &game.game_state).await, 0);
+}
+
+#[tokio::test]
+async fn nobody_wins_on_time_before_a_reveal() {
+  let (mut context, game) = setup().await;
+
+  init_game(&mut context, &game, 3, ROCK).await;
+  join_game(&mut context, &game, PAPER).await;
+
+  warp_seconds(&mut context, 121).await;
+  assert_game_error(claim_victory(&mut context, &game).await, GameError::NoMovePending);
+}
+
+#[tokio::test]
+async fn initializer_aborts_open_game() {
+  let (mut context, game) = setup().await;
+
+  init_game(&mut context, &game, 1, ROCK).await;
+  let abort = client::abort(&game.program_id, &game.initializer.pubkey(), &game.game_state);
+  process(&mut context, &[abort], &[&game.initializer]).await.unwrap();
+
+  assert_eq!(balance(&mut context, &game.initializer.pubkey()).await, PLAYER_FUNDS);
+  assert_eq!(balance(&mut context, &game.game_state).await, 0);
+}
+
+#[tokio::test]
+async fn abort_after_join_is_rejected() {
+  let (mut context, game) = setup().await;
+
+  init_game(&mut context, &game, 1, ROCK).await;
+  join_game(&mut context, &game, ROCK).await;
+
+  let abort = client::abort(&game.program_id, &game.initializer.pubkey(), &game.game_state);
+  assert_game_error(process(&mut context, &[abort], &[&game.initializer]).await, GameError::GameNotOpen);
+  assert_eq!(balance(&mut context, &game.game_state).await, 2 * STAKE + game.rent);
+}
+
+#[tokio::test]
+async fn host_fee_follows_protocol_config() {
+  let (mut context, game) = setup_with(|r| r.win_fee_bps = 500).await;
+
+  init_game(&mut context, &game, 1, ROCK).await;
+  join_game(&mut context, &game, PAPER).await;
+  reveal(&mut context, &game, ROCK, ROCK).await;
+  guest_reveal(&mut context, &game, PAPER, PAPER).await;
+
+  let host_fee = STAKE / 20;
+  assert_eq!(balance(&mut context, &game.host.pubkey()).await, PLAYER_FUNDS + host_fee);
+  assert_eq!(balance(&mut context, &game.guest.pubkey()).await, PLAYER_FUNDS + STAKE - host_fee);
+}
+
+#[tokio::test]
+async fn move_timeout_follows_protocol_config() {
+  let (mut context, game) = setup_with(|r| {
+    r.move_timeout = 30;
+    r.timeout_fee_bps = 0;
+  }).await;
+
+  init_game(&mut context, &game, 3, ROCK).await;
+  join_game(&mut context, &game, PAPER).await;
+  guest_reveal(&mut context, &game, PAPER, PAPER).await;
+
+  warp_seconds(&mut context, 29).await;
+  assert_game_error(claim_victory(&mut context, &game).await, GameError::TimeoutNotReached);
+
+  warp_seconds(&mut context, 2).await;
+  claim_victory(&mut context, &game).await.unwrap();
+
+  assert_eq!(balance(&mut context, &game.host.pubkey()).await, PLAYER_FUNDS);
+  assert_eq!(balance(&mut context, &game.guest.pubkey()).await, PLAYER_FUNDS + STAKE);
+}
+
+#[tokio::test]
+async fn chat_length_follows_protocol_config() {
+  let (mut context, game) = setup_with(|r| r.max_chat_len = 10).await;
+
+  init_game(&mut context, &game, 1, ROCK).await;
+  join_game(&mut context, &game, PAPER).await;
+
+  let chat = client::chat_local(
+    &game.program_id,
+    &game.initializer.pubkey(),
+    &game.guest.pubkey(),
+    &game.guest.pubkey(),
+    &game.game_state,
+    &game.rent_data,
+    Chat{ chat: "hello there".to_string() },
+  );
+  assert_game_error(process(&mut context, &[chat], &[&game.guest]).await, GameError::ChatTooLong);
+}
+
+#[tokio::test]
+async fn global_chat_length_follows_protocol_config() {
+  let (mut context, game) = setup_with(|r| r.max_chat_len = 10).await;
+  let channel = Pubkey::new_unique();
+  let line = || "XXXXXXXXXX".to_string();
+  let lines = ChatGlobal{
+    chat_line_1: line(), chat_line_2: line(), chat_line_3: line(), chat_line_4: line(), chat_line_5: line(),
+    chat_line_6: line(), chat_line_7: line(), chat_line_8: line(), chat_line_9: line(), chat_line_10: line(),
+    chat_line_11: line(), chat_line_12: line(), chat_line_13: line(), chat_line_14: line(), chat_line_15: line(),
+    chat_line_16: line(), chat_line_17: line(), chat_line_18: line(), chat_line_19: line(), chat_line_20: line(),
+  };
+  set_program_account(&mut context, &game.program_id, &channel, &lines);
+
+  let chat = client::chat_global(&game.program_id, &channel, &game.rent_data, Chat{ chat: "hello there".to_string() });
+  assert_game_error(process(&mut context, &[chat], &[]).await, GameError::ChatTooLong);
+
+  let chat = client::chat_global(&game.program_id, &channel, &game.rent_data, Chat{ chat: "hello, bye".to_string() });
+  process(&mut context, &[chat], &[]).await.unwrap();
+  let posted = ChatGlobal::load(&account_data(&mut context, &channel).await.unwrap()).unwrap();
+  assert_eq!(posted.chat_line_20, "hello, bye");
+}
+
+#[tokio::test]
+async fn the_speaker_must_sign_to_chat() {
+  let (mut context, game) = setup().await;
+
+  init_game(&mut context, &game, 1, ROCK).await;
+  join_game(&mut context, &game, PAPER).await;
+
+  let mut chat = client::chat_local(
+    &game.program_id,
+    &game.initializer.pubkey(),
+    &game.guest.pubkey(),
+    &game.guest.pubkey(),
+    &game.game_state,
+    &game.rent_data,
+    Chat{ chat: "hello".to_string() },
+  );
+  chat.accounts[2].is_signer = false;
+  assert_game_error(process(&mut context, &[chat], &[]).await, GameError::NotSigner);
+}
+
+#[tokio::test]
+async fn first_to_two() {
+  let (mut context, game) = setup().await;
+
+  init_game(&mut context, &game, 2, PAPER).await;
+  join_game(&mut context, &game, ROCK).await;
+  reveal(&mut context, &game, PAPER, PAPER).await;
+  guest_reveal(&mut context, &game, ROCK, ROCK).await;
+  reveal(&mut context, &game, PAPER, PAPER).await;
+  guest_reveal(&mut context, &game, ROCK, ROCK).await;
+
+  let host_fee = STAKE / 50;
+  assert_eq!(balance(&mut context, &game.initializer.pubkey()).await, PLAYER_FUNDS + STAKE - host_fee);
+  assert_eq!(balance(&mut context, &game.game_state).await, 0);
+}
+
+#[tokio::test]
+async fn tie_at_round_cap_is_a_draw() {
+  let (mut context, game) = setup().await;
+
+  init_match(&mut context, &game, (2, 2, 0), PAPER).await.unwrap();
+  join_game(&mut context, &game, ROCK).await;
+  reveal(&mut context, &game, PAPER, ROCK).await;
+  guest_reveal(&mut context, &game, ROCK, PAPER).await;
+  reveal(&mut context, &game, ROCK, ROCK).await;
+  guest_reveal(&mut context, &game, PAPER, PAPER).await;
+
+  let host_fee = STAKE / 50;
+  assert_eq!(balance(&mut context, &game.host.pubkey()).await, PLAYER_FUNDS + host_fee);
+  assert_eq!(balance(&mut context, &game.guest.pubkey()).await, PLAYER_FUNDS - host_fee / 2);
+  assert_eq!(balance(&mut context, &game.initializer.pubkey()).await, PLAYER_FUNDS - host_fee / 2);
+  assert_eq!(balance(&mut context, &game.game_state).await, 0);
+}
+
+#[tokio::test]
+async fn leader_wins_at_round_cap() {
+  let (mut context, game) = setup().await;
+
+  init_match(&mut context, &game, (3, 3, 0), PAPER).await.unwrap();
+  join_game(&mut context, &game, ROCK).await;
+  reveal(&mut context, &game, PAPER, ROCK).await;
+  guest_reveal(&mut context, &game, ROCK, ROCK).await;
+  reveal(&mut context, &game, ROCK, ROCK).await;
+  guest_reveal(&mut context, &game, ROCK, ROCK).await;
+  reveal(&mut context, &game, ROCK, ROCK).await;
+  guest_reveal(&mut context, &game, ROCK, ROCK).await;
+
+  let host_fee = STAKE / 50;
+  assert_eq!(balance(&mut context, &game.initializer.pubkey()).await, PLAYER_FUNDS + STAKE - host_fee);
+  assert_eq!(balance(&mut context, &game.guest.pubkey()).await, PLAYER_FUNDS - STAKE);
+}
+
+#[tokio::test]
+async fn sudden_death_plays_past_round_cap() {
+  let (mut context, game) = setup().await;
+
+  init_match(&mut context, &game, (2, 2, 1), PAPER).await.unwrap();
+  join_game(&mut context, &game, ROCK).await;
+  reveal(&mut context, &game, PAPER, ROCK).await;
+  guest_reveal(&mut context, &game, ROCK, PAPER).await;
+  reveal(&mut context, &game, ROCK, ROCK).await;
+  guest_reveal(&mut context, &game, PAPER, ROCK).await;
+
+  // tied at the cap, a drawn round keeps the game going
+  reveal(&mut context, &game, ROCK, ROCK).await;
+  guest_reveal(&mut context, &game, ROCK, PAPER).await;
+  let state = game_state(&mut context, &game).await;
+  assert_eq!((state.score_i, state.score_g, state.rounds_played), (1, 1, 3));
+
+  reveal(&mut context, &game, ROCK, ROCK).await;
+  guest_reveal(&mut context, &game, PAPER, PAPER).await;
+
+  let host_fee = STAKE / 50;
+  assert_eq!(balance(&mut context, &game.guest.pubkey()).await, PLAYER_FUNDS + STAKE - host_fee);
+  assert_eq!(balance(&mut context, &game.game_state).await, 0);
+}
+
+#[tokio::test]
+async fn history_keeps_the_latest_rounds() {
+  let (mut context, game) = setup().await;
+
+  init_game(&mut context, &game, 1, ROCK).await;
+  join_game(&mut context, &game, ROCK).await;
+  for _ in 0..HISTORY_LEN + 2 {
+    reveal(&mut context, &game, ROCK, ROCK).await;
+    guest_reveal(&mut context, &game, ROCK, ROCK).await;
+  }
+
+  let history: Vec<RoundRecord> = client::round_history(&game_state(&mut context, &game).await);
+  assert_eq!(history.len(), HISTORY_LEN);
+  assert_eq!(history.first().unwrap().round, 2);
+  assert_eq!(history.last().unwrap().round, HISTORY_LEN as u16 + 1);
+  assert!(history.iter().all(|r| (r.initializer_move, r.guest_move, r.winner) == (ROCK, ROCK, 0)));
+}
+
+#[tokio::test]
+async fn invalid_match_format_is_rejected() {
+  let (mut context, game) = setup().await;
+
+  assert_game_error(init_match(&mut context, &game, (0, 0, 0), ROCK).await, GameError::InvalidMatchFormat);
+  assert_game_error(init_match(&mut context, &game, (3, 2, 0), ROCK).await, GameError::InvalidMatchFormat);
+  assert_game_error(init_match(&mut context, &game, (3, 5, 2), ROCK).await, GameError::InvalidMatchFormat);
+}
+
+#[tokio::test]
+async fn init_creates_the_game_at_its_pda() {
+  let (mut context, game) = setup().await;
+
+  init_game(&mut context, &game, 1, ROCK).await;
+  assert_eq!(balance(&mut context, &game.game_state).await, STAKE + game.rent);
+  assert_eq!(account_data(&mut context, &game.game_state).await.unwrap().len(), GAME_STATE_LEN);
+}
+
+#[tokio::test]
+async fn init_rejects_an_address_that_is_not_the_pda() {
+  let (mut context, game) = setup().await;
+  let seeded = Pubkey::create_with_seed(&game.initializer.pubkey(), GAME_SEED, &game.program_id).unwrap();
+  let game = Game{ game_state: seeded, ..game };
+
+  assert_game_error(init_match(&mut context, &game, (1, 0, 0), ROCK).await, GameError::InvalidGameAccount);
+}
+
+#[tokio::test]
+async fn spock_vaporizes_rock() {
+  let (mut context, game) = setup().await;
+  const SPOCK: u8 = 5;
+
+  init_with_rules(&mut context, &game, (1, 0, 0), (rules::RPSLS, 0), ROCK).await.unwrap();
+  join_game(&mut context, &game, SPOCK).await;
+  reveal(&mut context, &game, ROCK, ROCK).await;
+  guest_reveal(&mut context, &game, SPOCK, ROCK).await;
+
+  let host_fee = STAKE / 50;
+  assert_eq!(balance(&mut context, &game.guest.pubkey()).await, PLAYER_FUNDS + STAKE - host_fee);
+}
+
+#[tokio::test]
+async fn move_outside_ruleset_is_rejected() {
+  let (mut context, game) = setup().await;
+
+  init_game(&mut context, &game, 1, ROCK).await;
+  join_game(&mut context, &game, 4).await;
+  reveal(&mut context, &game, ROCK, ROCK).await;
+
+  let play = InitializerPlay{ salt: salt(0), lastmove: 4, new_game_hash: [0; 32] };
+  assert_game_error(try_guest_play(&mut context, &game, play).await, GameError::InvalidMove);
+}
+
+#[tokio::test]
+async fn unknown_ruleset_is_rejected() {
+  let (mut context, game) = setup().await;
+
+  assert_game_error(
+    init_with_rules(&mut context, &game, (1, 0, 0), (7, 0), ROCK).await,
+    GameError::InvalidRuleset,
+  );
+  assert_game_error(
+    init_with_rules(&mut context, &game, (1, 0, 0), (rules::CYCLIC, 4), ROCK).await,
+    GameError::InvalidRuleset,
+  );
+}
+
+#[tokio::test]
+async fn a_prefunded_game_address_can_still_be_created() {
+  let (mut context, game) = setup().await;
+  // the least anyone can send to an empty address
+  let dust = Rent::default().minimum_balance(0);
+  let grief = system_instruction::transfer(&game.host.pubkey(), &game.game_state, dust);
+  process(&mut context, &[grief], &[&game.host]).await.unwrap();
+
+  init_game(&mut context, &game, 1, ROCK).await;
+  assert_eq!(balance(&mut context, &game.game_state).await, STAKE + game.rent);
+  assert_eq!(balance(&mut context, &game.initializer.pubkey()).await, PLAYER_FUNDS - STAKE - game.rent + dust);
+  assert_eq!(game_state(&mut context, &game).await.status, GameStatus::Open);
+}
@@ -0,0 +1,175 @@
+mod common;
+
+use common::*;
+use rps::account::AccountData;
+use rps::client;
+use rps::error::GameError;
+use rps::rules;
+use rps::state::{Commit, GameState, Init, Lobby, LobbyEntry, LOBBY_PAGE_LEN};
+use solana_program::{pubkey::Pubkey, rent::Rent, system_instruction};
+use solana_program_test::{BanksClientError, ProgramTestContext};
+use solana_sdk::signature::{Keypair, Signer};
+
+const STAKE: u64 = 1_000_000;
+const GAME_STATE_LEN: usize = 849;
+const ROCK: u8 = 1;
+const PAPER: u8 = 3;
+
+struct Players {
+  program_id: Pubkey,
+  initializer: Keypair,
+  guest: Keypair,
+  host: Keypair,
+  rent_data: Pubkey,
+}
+
+async fn start() -> (ProgramTestContext, Players) {
+  let program_id = Pubkey::new_unique();
+  let initializer = Keypair::new();
+  let guest = Keypair::new();
+  let host = Keypair::new();
+  let rent_data = Pubkey::new_unique();
+
+  let mut test = program_test(&program_id);
+  add_funded(&mut test, &initializer.pubkey());
+  add_funded(&mut test, &guest.pubkey());
+  add_funded(&mut test, &host.pubkey());
+  add_rent_account(&mut test, &program_id, &rent_data, Rent::default().minimum_balance(GAME_STATE_LEN));
+
+  let context = test.start_with_context().await;
+  (context, Players{ program_id, initializer, guest, host, rent_data })
+}
+
+// The n-th game of the initializer, with a gameseed of its own.
+fn game_address(players: &Players, n: usize) -> (Pubkey, u8) {
+  client::find_game_address(&players.program_id, &players.initializer.pubkey(), &gameseed(n))
+}
+
+fn gameseed(n: usize) -> String {
+  format!("game{:06}", n)
+}
+
+async fn init_game(context: &mut ProgramTestContext, players: &Players, n: usize, page: u16) -> Result<(), BanksClientError> {
+  let (game_state, bump) = game_address(players, n);
+  let init = client::init_game(
+    &players.program_id,
+    &players.initializer.pubkey(),
+    &game_state,
+    &players.host.pubkey(),
+    &players.rent_data,
+    Init{
+      bump,
+      game_ends: 3,
+      gameseed: gameseed(n),
+      lamports: STAKE,
+      game_hash: commit(&game_state, 0, &players.initializer.pubkey(), ROCK),
+      max_rounds: 0,
+      sudden_death: 0,
+      ruleset: rules::CLASSIC,
+      moves: 0,
+      ranked: 0,
+      min_rating: 0,
+      max_rating: 0,
+      invited: [0; 32],
+      allowlist_root: [0; 32],
+      mint: [0; 32],
+      lobby_page: page,
+    },
+  );
+  process(context, &[client::with_lobby(init, page)], &[&players.initializer]).await
+}
+
+// Joins the n-th game, passing `page` as its lobby page if there is one.
+async fn join_game(context: &mut ProgramTestContext, players: &Players, n: usize, page: Option<u16>) -> Result<(), BanksClientError> {
+  let (game_state, _) = game_address(players, n);
+  let join = client::join_game(
+    &players.program_id,
+    &players.guest.pubkey(),
+    &game_state,
+    &players.host.pubkey(),
+    Commit{ game_hash: commit(&game_state, 0, &players.guest.pubkey(), PAPER), proof: vec![] },
+  );
+  let join = match page {
+    Some(page) => client::with_lobby(join, page),
+    None => join,
+  };
+  process(context, &[join], &[&players.guest]).await
+}
+
+async fn open_games(context: &mut ProgramTestContext, players: &Players, page: u16) -> Vec<Pubkey> {
+  lobby(context, players, page).await.iter().map(|entry| Pubkey::new_from_array(entry.game)).collect()
+}
+
+async fn lobby(context: &mut ProgramTestContext, players: &Players, page: u16) -> Vec<LobbyEntry> {
+  let (address, _) = client::find_lobby_address(&players.program_id, page);
+  let data = account_data(context, &address).await.unwrap();
+  client::open_games(&Lobby::load(&data).unwrap())
+}
+
+#[tokio::test]
+async fn open_games_are_listed_until_joined() {
+  let (mut context, players) = start().await;
+  init_game(&mut context, &players, 0, 1).await.unwrap();
+  init_game(&mut context, &players, 1, 1).await.unwrap();
+
+  let listed = lobby(&mut context, &players, 1).await;
+  assert_eq!(listed.len(), 2);
+  assert_eq!(listed[0].game, game_address(&players, 0).0.to_bytes());
+  assert_eq!((listed[0].lamports, listed[0].game_ends), (STAKE, 3));
+  assert_eq!(listed[0].created, now(&mut context).await);
+
+  join_game(&mut context, &players, 0, Some(1)).await.unwrap();
+  assert_eq!(open_games(&mut context, &players, 1).await, vec![game_address(&players, 1).0]);
+
+  let data = account_data(&mut context, &game_address(&players, 0).0).await.unwrap();
+  assert_eq!(GameState::load(&data).unwrap().lobby_page, 0);
+}
+
+#[tokio::test]
+async fn aborting_a_listed_game_frees_its_slot() {
+  let (mut context, players) = start().await;
+  init_game(&mut context, &players, 0, 1).await.unwrap();
+
+  let (game_state, _) = game_address(&players, 0);
+  let abort = client::with_lobby(client::abort(&players.program_id, &players.initializer.pubkey(), &game_state), 1);
+  process(&mut context, &[abort], &[&players.initializer]).await.unwrap();
+  assert_eq!(open_games(&mut context, &players, 1).await, vec![]);
+
+  init_game(&mut context, &players, 1, 1).await.unwrap();
+  assert_eq!(open_games(&mut context, &players, 1).await, vec![game_address(&players, 1).0]);
+}
+
+#[tokio::test]
+async fn a_listed_game_is_joined_with_its_lobby_page() {
+  let (mut context, players) = start().await;
+  init_game(&mut context, &players, 0, 1).await.unwrap();
+  init_game(&mut context, &players, 1, 2).await.unwrap();
+
+  assert_game_error(join_game(&mut context, &players, 0, None).await, GameError::InvalidLobby);
+  assert_game_error(join_game(&mut context, &players, 0, Some(2)).await, GameError::InvalidLobby);
+  join_game(&mut context, &players, 0, Some(1)).await.unwrap();
+}
+
+#[tokio::test]
+async fn a_full_page_takes_no_more_games() {
+  let (mut context, players) = start().await;
+  for n in 0..LOBBY_PAGE_LEN {
+    init_game(&mut context, &players, n, 1).await.unwrap();
+  }
+
+  assert_game_error(init_game(&mut context, &players, LOBBY_PAGE_LEN, 1).await, GameError::LobbyFull);
+  init_game(&mut context, &players, LOBBY_PAGE_LEN, 2).await.unwrap();
+  assert_eq!(open_games(&mut context, &players, 1).await.len(), LOBBY_PAGE_LEN);
+}
+
+#[tokio::test]
+async fn a_prefunded_page_can_still_be_created() {
+  let (mut context, players) = start().await;
+  let (page, _) = client::find_lobby_address(&players.program_id, 1);
+  // the least anyone can send to an empty address
+  let grief = system_instruction::transfer(&players.guest.pubkey(), &page, Rent::default().minimum_balance(0));
+  process(&mut context, &[grief], &[&players.guest]).await.unwrap();
+
+  init_game(&mut context, &players, 0, 1).await.unwrap();
+  assert_eq!(open_games(&mut context, &players, 1).await, vec![game_address(&players, 0).0]);
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use common::*;
+use rps::client;
+use rps::error::GameError;
+use rps::rules::MAX_MOVES;
+use rps::state::{InitializerPlay, Join};
+use solana_program::{instruction::Instruction, pubkey::Pubkey};
+use solana_sdk::signature::{Keypair, Signer};
+
+// Out-of-range moves are rejected while decoding, before any account is read,
+// so unrelated placeholder accounts are enough to reach every entry point.
+fn entry_points(program_id: &Pubkey, player: &Pubkey, mymove: u8) -> Vec<(&'static str, Instruction)> {
+  let other = || Pubkey::new_unique();
+  let play = InitializerPlay{ salt: [0; 32], lastmove: mymove, new_game_hash: [0; 32] };
+  vec![
+    ("guest_play", client::guest_play(program_id, &other(), player, &other(), &other(), &other(), play.clone())),
+    ("initializer_play", client::initializer_play(program_id, player, &other(), &other(), &other(), &other(), play.clone())),
+    ("tournament_match_accept", client::tournament_match_accept(
+      program_id, &other(), &other(), &other(), &other(), player, &other(), Join{ mymove },
+    )),
+    ("tournament_initializer_play", client::tournament_initializer_play(
+      program_id, player, &other(), &other(), &other(), &other(), &other(), play.clone(),
+    )),
+    ("tournament_guest_play", client::tournament_guest_play(
+      program_id, &other(), &other(), player, &other(), &other(), &other(), play,
+    )),
+  ]
+}
+
+#[tokio::test]
+async fn every_entry_point_rejects_out_of_range_moves() {
+  let program_id = Pubkey::new_unique();
+  let player = Keypair::new();
+
+  let mut test = program_test(&program_id);
+  add_funded(&mut test, &player.pubkey());
+  let mut context = test.start_with_context().await;
+
+  for mymove in [0, MAX_MOVES + 1, 255] {
+    for (name, ix) in entry_points(&program_id, &player.pubkey(), mymove) {
+      let result = process(&mut context, &[ix], &[&player]).await;
+      assert!(result.is_err(), "{} accepted move {}", name, mymove);
+      assert_game_error(result, GameError::InvalidMove);
+    }
+  }
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use common::*;
+use rps::allowlist;
+use rps::client;
+use rps::error::GameError;
+use rps::rules;
+use rps::state::{Commit, Init};
+use solana_program::{pubkey::Pubkey, rent::Rent};
+use solana_program_test::{BanksClientError, ProgramTestContext};
+use solana_sdk::signature::{Keypair, Signer};
+
+const STAKE: u64 = 1_000_000_000;
+const GAME_SEED: &str = "game000001";
+const GAME_STATE_LEN: usize = 849;
+const ROCK: u8 = 1;
+const PAPER: u8 = 3;
+
+struct Game {
+  program_id: Pubkey,
+  initializer: Keypair,
+  friend: Keypair,
+  stranger: Keypair,
+  host: Keypair,
+  rent_data: Pubkey,
+  game_state: Pubkey,
+  bump: u8,
+}
+
+async fn start() -> (ProgramTestContext, Game) {
+  let program_id = Pubkey::new_unique();
+  let initializer = Keypair::new();
+  let friend = Keypair::new();
+  let stranger = Keypair::new();
+  let host = Keypair::new();
+  let rent_data = Pubkey::new_unique();
+
+  let mut test = program_test(&program_id);
+  add_funded(&mut test, &initializer.pubkey());
+  add_funded(&mut test, &friend.pubkey());
+  add_funded(&mut test, &stranger.pubkey());
+  add_funded(&mut test, &host.pubkey());
+  add_rent_account(&mut test, &program_id, &rent_data, Rent::default().minimum_balance(GAME_STATE_LEN));
+
+  let (game_state, bump) = client::find_game_address(&program_id, &initializer.pubkey(), GAME_SEED);
+  let context = test.start_with_context().await;
+
+  (context, Game{ program_id, initializer, friend, stranger, host, rent_data, game_state, bump })
+}
+
+async fn init_game(context: &mut ProgramTestContext, game: &Game, invited: [u8; 32], allowlist_root: [u8; 32]) {
+  let init = client::init_game(
+    &game.program_id,
+    &game.initializer.pubkey(),
+    &game.game_state,
+    &game.host.pubkey(),
+    &game.rent_data,
+    Init{
+      bump: game.bump,
+      game_ends: 1,
+      gameseed: GAME_SEED.to_string(),
+      lamports: STAKE,
+      game_hash: commit(&game.game_state, 0, &game.initializer.pubkey(), ROCK),
+      max_rounds: 0,
+      sudden_death: 0,
+      ruleset: rules::CLASSIC,
+      moves: 0,
+      ranked: 0,
+      min_rating: 0,
+      max_rating: 0,
+      invited,
+      allowlist_root,
+      mint: [0; 32],
+      lobby_page: 0,
+    },
+  );
+  process(context, &[init], &[&game.initializer]).await.unwrap();
+}
+
+async fn join_game(context: &mut ProgramTestContext, game: &Game, guest: &Keypair, proof: Vec<[u8; 32]>) -> Result<(), BanksClientError> {
+  let join = client::join_game(
+    &game.program_id,
+    &guest.pubkey(),
+    &game.game_state,
+    &game.host.pubkey(),
+    Commit{ game_hash: commit(&game.game_state, 0, &guest.pubkey(), PAPER), proof },
+  );
+  process(context, &[join], &[guest]).await
+}
+
+#[test]
+fn every_listed_player_has_a_proof() {
+  let players: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).collect();
+  let root: [u8; 32] = allowlist::root(&players);
+
+  for player in &players {
+    let proof: Vec<[u8; 32]> = allowlist::proof(&players, player).unwrap();
+    assert!(allowlist::verify(&root, player, &proof));
+  }
+  assert_eq!(allowlist::proof(&players, &Pubkey::new_unique()), None);
+  assert_eq!(allowlist::root(&[]), [0; 32]);
+}
+
+#[test]
+fn a_proof_only_works_for_its_own_player() {
+  let players: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+  let root: [u8; 32] = allowlist::root(&players);
+  let proof: Vec<[u8; 32]> = allowlist::proof(&players, &players[0]).unwrap();
+
+  assert!(!allowlist::verify(&root, &players[1], &proof));
+  assert!(!allowlist::verify(&root, &Pubkey::new_unique(), &proof));
+
+  let mut tampered: Vec<[u8; 32]> = proof.clone();
+  tampered[0][0] ^= 1;
+  assert!(!allowlist::verify(&root, &players[0], &tampered));
+}
+
+#[tokio::test]
+async fn only_the_invited_guest_can_join() {
+  let (mut context, game) = start().await;
+  init_game(&mut context, &game, game.friend.pubkey().to_bytes(), [0; 32]).await;
+
+  assert_game_error(join_game(&mut context, &game, &game.stranger, vec![]).await, GameError::NotInvited);
+  join_game(&mut context, &game, &game.friend, vec![]).await.unwrap();
+}
+
+#[tokio::test]
+async fn allowlisted_players_join_with_a_proof() {
+  let (mut context, game) = start().await;
+  let players: Vec<Pubkey> = vec![Pubkey::new_unique(), game.friend.pubkey(), Pubkey::new_unique()];
+  init_game(&mut context, &game, [0; 32], allowlist::root(&players)).await;
+
+  // a listed player's proof does not let someone else in
+  let borrowed: Vec<[u8; 32]> = allowlist::proof(&players, &game.friend.pubkey()).unwrap();
+  assert_game_error(join_game(&mut context, &game, &game.stranger, borrowed.clone()).await, GameError::NotInvited);
+  join_game(&mut context, &game, &game.friend, borrowed).await.unwrap();
+}
@@ -0,0 +1,270 @@
+mod common;
+
+use common::*;
+use rps::account::AccountData;
+use rps::client;
+use rps::error::GameError;
+use rps::rating;
+use rps::rules;
+use rps::state::{Commit, Init, InitializerPlay, PlayerProfile};
+use solana_program::{instruction::Instruction, pubkey::Pubkey, rent::Rent, system_instruction};
+use solana_program_test::{BanksClientError, ProgramTestContext};
+use solana_sdk::signature::{Keypair, Signer};
+
+const STAKE: u64 = 1_000_000_000;
+const GAME_SEED: &str = "game000001";
+const GAME_STATE_LEN: usize = 849;
+const ROCK: u8 = 1;
+const PAPER: u8 = 3;
+
+struct Game {
+  program_id: Pubkey,
+  initializer: Keypair,
+  guest: Keypair,
+  host: Keypair,
+  rent_data: Pubkey,
+  game_state: Pubkey,
+  bump: u8,
+}
+
+impl Game {
+  fn players(&self) -> [Pubkey; 2] {
+    [self.initializer.pubkey(), self.guest.pubkey()]
+  }
+}
+
+async fn start() -> (ProgramTestContext, Game) {
+  let program_id = Pubkey::new_unique();
+  let initializer = Keypair::new();
+  let guest = Keypair::new();
+  let host = Keypair::new();
+  let rent_data = Pubkey::new_unique();
+
+  let mut test = program_test(&program_id);
+  add_funded(&mut test, &initializer.pubkey());
+  add_funded(&mut test, &guest.pubkey());
+  add_funded(&mut test, &host.pubkey());
+  add_rent_account(&mut test, &program_id, &rent_data, Rent::default().minimum_balance(GAME_STATE_LEN));
+
+  let (game_state, bump) = client::find_game_address(&program_id, &initializer.pubkey(), GAME_SEED);
+  let context = test.start_with_context().await;
+
+  (context, Game{ program_id, initializer, guest, host, rent_data, game_state, bump })
+}
+
+// Ranked games take a rating band, 0 for no bound.
+async fn init_game(context: &mut ProgramTestContext, game: &Game, game_ends: u8, ranked: u8, band: (u16, u16)) -> Result<(), BanksClientError> {
+  let init = client::init_game(
+    &game.program_id,
+    &game.initializer.pubkey(),
+    &game.game_state,
+    &game.host.pubkey(),
+    &game.rent_data,
+    Init{
+      bump: game.bump,
+      game_ends,
+      gameseed: GAME_SEED.to_string(),
+      lamports: STAKE,
+      game_hash: commit(&game.game_state, 0, &game.initializer.pubkey(), ROCK),
+      max_rounds: 0,
+      sudden_death: 0,
+      ruleset: rules::CLASSIC,
+      moves: 0,
+      ranked,
+      min_rating: band.0,
+      max_rating: band.1,
+      invited: [0; 32],
+      allowlist_root: [0; 32],
+      mint: [0; 32],
+      lobby_page: 0,
+    },
+  );
+  process(context, &[init], &[&game.initializer]).await
+}
+
+async fn join_game(context: &mut ProgramTestContext, game: &Game, profile: bool) -> Result<(), BanksClientError> {
+  let join = client::join_game(
+    &game.program_id,
+    &game.guest.pubkey(),
+    &game.game_state,
+    &game.host.pubkey(),
+    Commit{ game_hash: commit(&game.game_state, 0, &game.guest.pubkey(), PAPER), proof: vec![] },
+  );
+  let join = if profile {client::with_profiles(join, &[game.guest.pubkey()])} else {join};
+  process(context, &[join], &[&game.guest]).await
+}
+
+async fn setup(game_ends: u8) -> (ProgramTestContext, Game) {
+  let (mut context, game) = start().await;
+  init_game(&mut context, &game, game_ends, 0, (0, 0)).await.unwrap();
+  join_game(&mut context, &game, false).await.unwrap();
+  (context, game)
+}
+
+fn opening(lastmove: u8) -> InitializerPlay {
+  InitializerPlay{ salt: salt(0), lastmove, new_game_hash: [0; 32] }
+}
+
+fn initializer_play(game: &Game, lastmove: u8) -> Instruction {
+  client::initializer_play(
+    &game.program_id,
+    &game.initializer.pubkey(),
+    &game.guest.pubkey(),
+    &game.host.pubkey(),
+    &game.game_state,
+    &game.rent_data,
+    opening(lastmove),
+  )
+}
+
+fn guest_play(game: &Game, lastmove: u8) -> Instruction {
+  client::guest_play(
+    &game.program_id,
+    &game.initializer.pubkey(),
+    &game.guest.pubkey(),
+    &game.host.pubkey(),
+    &game.game_state,
+    &game.rent_data,
+    opening(lastmove),
+  )
+}
+
+async fn profile(context: &mut ProgramTestContext, game: &Game, player: &Pubkey) -> Option<PlayerProfile> {
+  let (address, _) = client::find_profile_address(&game.program_id, player);
+  account_data(context, &address).await.map(|data| PlayerProfile::load(&data).unwrap())
+}
+
+#[tokio::test]
+async fn the_first_reveal_creates_both_profiles() {
+  let (mut context, game) = setup(1).await;
+  let players = game.players();
+
+  let play = client::with_profiles(initializer_play(&game, ROCK), &players);
+  process(&mut context, &[play], &[&game.initializer]).await.unwrap();
+
+  for player in &players {
+    let created = profile(&mut context, &game, player).await.unwrap();
+    assert_eq!(created.is_init, 1);
+    assert_eq!(created.player, player.to_bytes());
+    assert_eq!(created.rounds_played, 0);
+  }
+}
+
+#[tokio::test]
+async fn a_finished_game_is_counted_in_both_profiles() {
+  let (mut context, game) = setup(1).await;
+  let players = game.players();
+
+  process(&mut context, &[client::with_profiles(initializer_play(&game, ROCK), &players)], &[&game.initializer]).await.unwrap();
+  process(&mut context, &[client::with_profiles(guest_play(&game, PAPER), &players)], &[&game.guest]).await.unwrap();
+
+  let loser = profile(&mut context, &game, &players[0]).await.unwrap();
+  assert_eq!((loser.games_played, loser.wins, loser.losses, loser.draws), (1, 0, 1, 0));
+  assert_eq!(loser.rounds_played, 1);
+  assert_eq!(loser.move_counts, [1, 0, 0, 0, 0]);
+  assert_eq!((loser.lamports_wagered, loser.lamports_won), (STAKE, 0));
+
+  let winner = profile(&mut context, &game, &players[1]).await.unwrap();
+  assert_eq!((winner.games_played, winner.wins, winner.losses, winner.draws), (1, 1, 0, 0));
+  assert_eq!(winner.move_counts, [0, 0, 1, 0, 0]);
+  assert_eq!((winner.lamports_wagered, winner.lamports_won), (STAKE, 2 * STAKE - STAKE / 50));
+}
+
+#[tokio::test]
+async fn a_timeout_claim_updates_existing_profiles() {
+  let (mut context, game) = setup(1).await;
+  let players = game.players();
+
+  process(&mut context, &[client::with_profiles(guest_play(&game, PAPER), &players)], &[&game.guest]).await.unwrap();
+
+  warp_seconds(&mut context, 121).await;
+  let claim = client::claim_victory(
+    &game.program_id,
+    &game.initializer.pubkey(),
+    &game.guest.pubkey(),
+    &game.host.pubkey(),
+    &game.game_state,
+    &game.rent_data,
+  );
+  process(&mut context, &[client::with_profiles(claim, &players)], &[]).await.unwrap();
+
+  let idle = profile(&mut context, &game, &players[0]).await.unwrap();
+  assert_eq!((idle.games_played, idle.losses, idle.rounds_played), (1, 1, 0));
+  let claimer = profile(&mut context, &game, &players[1]).await.unwrap();
+  assert_eq!((claimer.games_played, claimer.wins), (1, 1));
+  assert_eq!(claimer.lamports_won, 2 * STAKE - STAKE / 100);
+}
+
+#[tokio::test]
+async fn plays_without_profiles_leave_them_alone() {
+  let (mut context, game) = setup(1).await;
+
+  process(&mut context, &[initializer_play(&game, ROCK)], &[&game.initializer]).await.unwrap();
+  process(&mut context, &[guest_play(&game, PAPER)], &[&game.guest]).await.unwrap();
+
+  assert_eq!(profile(&mut context, &game, &game.initializer.pubkey()).await, None);
+  assert_eq!(profile(&mut context, &game, &game.guest.pubkey()).await, None);
+}
+
+#[tokio::test]
+async fn another_players_profile_is_rejected() {
+  let (mut context, game) = setup(1).await;
+  let [initializer, guest] = game.players();
+
+  let play = client::with_profiles(initializer_play(&game, ROCK), &[guest, initializer]);
+  let result: Result<(), BanksClientError> = process(&mut context, &[play], &[&game.initializer]).await;
+  assert_game_error(result, GameError::InvalidProfile);
+}
+
+#[tokio::test]
+async fn a_ranked_game_settles_both_ratings() {
+  let (mut context, game) = start().await;
+  let players = game.players();
+  init_game(&mut context, &game, 1, 1, (1000, 1400)).await.unwrap();
+  join_game(&mut context, &game, true).await.unwrap();
+  assert_eq!(profile(&mut context, &game, &players[1]).await.unwrap().rating, rating::DEFAULT_RATING);
+
+  process(&mut context, &[client::with_profiles(initializer_play(&game, ROCK), &players)], &[&game.initializer]).await.unwrap();
+  process(&mut context, &[client::with_profiles(guest_play(&game, PAPER), &players)], &[&game.guest]).await.unwrap();
+
+  let loser = profile(&mut context, &game, &players[0]).await.unwrap();
+  let winner = profile(&mut context, &game, &players[1]).await.unwrap();
+  assert_eq!((loser.rating, loser.ranked_games), (rating::DEFAULT_RATING - 16, 1));
+  assert_eq!((winner.rating, winner.ranked_games), (rating::DEFAULT_RATING + 16, 1));
+}
+
+#[tokio::test]
+async fn a_ranked_game_cannot_settle_without_profiles() {
+  let (mut context, game) = start().await;
+  init_game(&mut context, &game, 1, 1, (0, 0)).await.unwrap();
+  join_game(&mut context, &game, true).await.unwrap();
+
+  process(&mut context, &[initializer_play(&game, ROCK)], &[&game.initializer]).await.unwrap();
+  let result: Result<(), BanksClientError> = process(&mut context, &[guest_play(&game, PAPER)], &[&game.guest]).await;
+  assert_game_error(result, GameError::InvalidProfile);
+}
+
+#[tokio::test]
+async fn players_outside_the_band_cannot_join() {
+  let (mut context, game) = start().await;
+  assert_game_error(init_game(&mut context, &game, 1, 0, (1000, 0)).await, GameError::InvalidRatingBand);
+  assert_game_error(init_game(&mut context, &game, 1, 1, (1400, 1000)).await, GameError::InvalidRatingBand);
+
+  init_game(&mut context, &game, 1, 1, (1500, 0)).await.unwrap();
+  assert_game_error(join_game(&mut context, &game, true).await, GameError::RatingOutOfBand);
+  assert_game_error(join_game(&mut context, &game, false).await, GameError::InvalidProfile);
+}
+
+#[tokio::test]
+async fn a_prefunded_profile_can_still_be_created() {
+  let (mut context, game) = start().await;
+  let guest = game.guest.pubkey();
+  let (address, _) = client::find_profile_address(&game.program_id, &guest);
+  // the least anyone can send to an empty address
+  let grief = system_instruction::transfer(&game.host.pubkey(), &address, Rent::default().minimum_balance(0));
+  process(&mut context, &[grief], &[&game.host]).await.unwrap();
+
+  init_game(&mut context, &game, 1, 1, (0, 0)).await.unwrap();
+  join_game(&mut context, &game, true).await.unwrap();
+  assert_eq!(profile(&mut context, &game, &guest).await.unwrap().rating, rating::DEFAULT_RATING);
+}
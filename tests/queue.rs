@@ -0,0 +1,197 @@
+mod common;
+
+use common::*;
+use rps::account::AccountData;
+use rps::client;
+use rps::error::GameError;
+use rps::state::{stake_bracket, Enqueue, GameState, InitializerPlay, MatchQueue, STAKE_BRACKETS};
+use rps::status::GameStatus;
+use solana_program::{pubkey::Pubkey, rent::Rent, system_instruction};
+use solana_program_test::{BanksClientError, ProgramTestContext};
+use solana_sdk::signature::{Keypair, Signer};
+
+const STAKE: u64 = 1_000_000_000;
+const GAME_SEED: &str = "game000001";
+const GAME_STATE_LEN: usize = 849;
+const ROCK: u8 = 1;
+const PAPER: u8 = 3;
+
+struct Players {
+  program_id: Pubkey,
+  first: Keypair,
+  second: Keypair,
+  host: Keypair,
+  rent_data: Pubkey,
+}
+
+async fn start() -> (ProgramTestContext, Players) {
+  let program_id = Pubkey::new_unique();
+  let first = Keypair::new();
+  let second = Keypair::new();
+  let host = Keypair::new();
+  let rent_data = Pubkey::new_unique();
+
+  let mut test = program_test(&program_id);
+  add_funded(&mut test, &first.pubkey());
+  add_funded(&mut test, &second.pubkey());
+  add_funded(&mut test, &host.pubkey());
+  add_rent_account(&mut test, &program_id, &rent_data, Rent::default().minimum_balance(GAME_STATE_LEN));
+
+  let context = test.start_with_context().await;
+  (context, Players{ program_id, first, second, host, rent_data })
+}
+
+async fn queue(context: &mut ProgramTestContext, players: &Players, lamports: u64) -> Option<MatchQueue> {
+  let (address, _) = client::find_queue_address(&players.program_id, lamports);
+  // an address that only holds lamports has no queue yet
+  account_data(context, &address).await.filter(|data| !data.is_empty()).map(|data| MatchQueue::load(&data).unwrap())
+}
+
+async fn game_state(context: &mut ProgramTestContext, game: &Pubkey) -> GameState {
+  GameState::load(&account_data(context, game).await.unwrap()).unwrap()
+}
+
+// Enqueues `player` committing to `game`, returns the game.
+async fn enqueue_into(
+  context: &mut ProgramTestContext,
+  players: &Players,
+  player: &Keypair,
+  lamports: u64,
+  (game, bump): (Pubkey, u8),
+  mymove: u8) -> Result<Pubkey, BanksClientError> {
+
+  let enqueue = client::enqueue(
+    &players.program_id,
+    &player.pubkey(),
+    &game,
+    &players.host.pubkey(),
+    &players.rent_data,
+    Enqueue{
+      bump,
+      gameseed: GAME_SEED.to_string(),
+      lamports,
+      game_hash: commit(&game, 0, &player.pubkey(), mymove),
+    },
+  );
+  process(context, &[enqueue], &[player]).await.map(|_| game)
+}
+
+// Enqueues `player` into the game the queue currently points them to.
+async fn enqueue(context: &mut ProgramTestContext, players: &Players, player: &Keypair, lamports: u64, mymove: u8) -> Result<Pubkey, BanksClientError> {
+  let waiting = queue(context, players, lamports).await;
+  let game = client::queued_game(&players.program_id, &player.pubkey(), GAME_SEED, waiting.as_ref());
+  enqueue_into(context, players, player, lamports, game, mymove).await
+}
+
+#[tokio::test]
+async fn the_second_player_joins_the_waiting_game() {
+  let (mut context, players) = start().await;
+
+  let game = enqueue(&mut context, &players, &players.first, STAKE, ROCK).await.unwrap();
+  let waiting = queue(&mut context, &players, STAKE).await.unwrap();
+  assert_eq!((waiting.game, waiting.player), (game.to_bytes(), players.first.pubkey().to_bytes()));
+  assert_eq!(game_state(&mut context, &game).await.queued, 1);
+
+  assert_eq!(enqueue(&mut context, &players, &players.second, STAKE, PAPER).await.unwrap(), game);
+  assert_eq!(queue(&mut context, &players, STAKE).await.unwrap().game, [0; 32]);
+  let state = game_state(&mut context, &game).await;
+  assert_eq!((state.status, state.queued), (GameStatus::Active{turn:0}, 0));
+  assert_eq!(state.guest, players.second.pubkey().to_bytes());
+  assert_eq!(balance(&mut context, &game).await, 2 * STAKE + Rent::default().minimum_balance(GAME_STATE_LEN));
+
+  let (initializer, guest) = (players.first.pubkey(), players.second.pubkey());
+  let opening = |lastmove: u8| InitializerPlay{ salt: salt(0), lastmove, new_game_hash: [0; 32] };
+  let play = client::initializer_play(&players.program_id, &initializer, &guest, &players.host.pubkey(), &game, &players.rent_data, opening(ROCK));
+  process(&mut context, &[play], &[&players.first]).await.unwrap();
+  let play = client::guest_play(&players.program_id, &initializer, &guest, &players.host.pubkey(), &game, &players.rent_data, opening(PAPER));
+  process(&mut context, &[play], &[&players.second]).await.unwrap();
+  assert_eq!(balance(&mut context, &players.second.pubkey()).await, PLAYER_FUNDS + STAKE - STAKE / 50);
+}
+
+#[tokio::test]
+async fn a_player_is_not_paired_with_themselves() {
+  let (mut context, players) = start().await;
+  enqueue(&mut context, &players, &players.first, STAKE, ROCK).await.unwrap();
+
+  assert_game_error(enqueue(&mut context, &players, &players.first, STAKE, ROCK).await.map(|_| ()), GameError::AlreadyQueued);
+}
+
+#[tokio::test]
+async fn joining_takes_the_game_that_is_waiting() {
+  let (mut context, players) = start().await;
+  enqueue(&mut context, &players, &players.first, STAKE, ROCK).await.unwrap();
+
+  // a commitment to the player's own game is stale once someone is waiting
+  let own = client::find_game_address(&players.program_id, &players.second.pubkey(), GAME_SEED);
+  let result = enqueue_into(&mut context, &players, &players.second, STAKE, own, PAPER).await;
+  assert_game_error(result.map(|_| ()), GameError::InvalidQueue);
+}
+
+#[test]
+fn brackets_run_from_their_lowest_stake_to_the_next() {
+  assert_eq!(stake_bracket(0), 0);
+  assert_eq!(stake_bracket(STAKE - 1), stake_bracket(STAKE / 2));
+  assert_eq!(stake_bracket(STAKE), stake_bracket(STAKE / 2) + 1);
+  assert_eq!(stake_bracket(u64::MAX) as usize, STAKE_BRACKETS.len() - 1);
+}
+
+#[tokio::test]
+async fn stakes_of_one_bracket_are_paired_at_the_waiting_stake() {
+  let (mut context, players) = start().await;
+  let game = enqueue(&mut context, &players, &players.first, STAKE, ROCK).await.unwrap();
+
+  assert_eq!(enqueue(&mut context, &players, &players.second, STAKE + STAKE / 2, PAPER).await.unwrap(), game);
+  assert_eq!(game_state(&mut context, &game).await.lamports, STAKE);
+  assert_eq!(balance(&mut context, &players.second.pubkey()).await, PLAYER_FUNDS - STAKE);
+}
+
+#[tokio::test]
+async fn brackets_are_queued_apart() {
+  let (mut context, players) = start().await;
+
+  let first = enqueue(&mut context, &players, &players.first, STAKE, ROCK).await.unwrap();
+  let second = enqueue(&mut context, &players, &players.second, 2 * STAKE, PAPER).await.unwrap();
+  assert_ne!(first, second);
+  assert_eq!(queue(&mut context, &players, STAKE).await.unwrap().game, first.to_bytes());
+  assert_eq!(queue(&mut context, &players, 2 * STAKE).await.unwrap().game, second.to_bytes());
+}
+
+#[tokio::test]
+async fn aborting_a_queued_game_empties_the_queue() {
+  let (mut context, players) = start().await;
+  let game = enqueue(&mut context, &players, &players.first, STAKE, ROCK).await.unwrap();
+
+  let abort = || client::abort(&players.program_id, &players.first.pubkey(), &game);
+  assert_game_error(process(&mut context, &[abort()], &[&players.first]).await, GameError::InvalidQueue);
+  process(&mut context, &[client::with_queue(abort(), STAKE)], &[&players.first]).await.unwrap();
+  assert_eq!(queue(&mut context, &players, STAKE).await.unwrap().game, [0; 32]);
+
+  // the next player opens their own game
+  let own = enqueue(&mut context, &players, &players.second, STAKE, PAPER).await.unwrap();
+  assert_eq!(own, client::find_game_address(&players.program_id, &players.second.pubkey(), GAME_SEED).0);
+}
+
+#[tokio::test]
+async fn a_prefunded_queue_can_still_be_created() {
+  let (mut context, players) = start().await;
+  let (address, _) = client::find_queue_address(&players.program_id, STAKE);
+  let grief = system_instruction::transfer(&players.second.pubkey(), &address, Rent::default().minimum_balance(0));
+  process(&mut context, &[grief], &[&players.second]).await.unwrap();
+
+  let game = enqueue(&mut context, &players, &players.first, STAKE, ROCK).await.unwrap();
+  assert_eq!(queue(&mut context, &players, STAKE).await.unwrap().game, game.to_bytes());
+}
+
+#[tokio::test]
+async fn the_second_stake_goes_straight_into_the_game() {
+  let (mut context, players) = start().await;
+  let game = enqueue(&mut context, &players, &players.first, STAKE, ROCK).await.unwrap();
+  let (address, _) = client::find_queue_address(&players.program_id, STAKE);
+  let queue_rent = balance(&mut context, &address).await;
+  let game_before = balance(&mut context, &game).await;
+
+  enqueue(&mut context, &players, &players.second, STAKE, PAPER).await.unwrap();
+  assert_eq!(balance(&mut context, &players.second.pubkey()).await, PLAYER_FUNDS - STAKE);
+  assert_eq!(balance(&mut context, &game).await, game_before + STAKE);
+  assert_eq!(balance(&mut context, &address).await, queue_rent);
+}
@@ -0,0 +1,34 @@
+use rps::rating::{self, DEFAULT_RATING, RATING_FLOOR};
+use rps::rules::Outcome;
+
+#[test]
+fn expected_scores() {
+  assert_eq!(rating::expected(1200, 1200), 500);
+  assert_eq!(rating::expected(1400, 1200), 760);
+  assert_eq!(rating::expected(1200, 1400), 240);
+  // between table steps, and past the 400 point cap
+  assert_eq!(rating::expected(1210, 1200), 514);
+  assert_eq!(rating::expected(2400, 1200), 909);
+  for diff in 0..=800u16 {
+    assert_eq!(rating::expected(1200 + diff, 1200) + rating::expected(1200, 1200 + diff), 1000);
+  }
+}
+
+#[test]
+fn settling_games() {
+  assert_eq!(rating::settle(DEFAULT_RATING, DEFAULT_RATING, Outcome::InitializerWins), (1216, 1184));
+  assert_eq!(rating::settle(DEFAULT_RATING, DEFAULT_RATING, Outcome::GuestWins), (1184, 1216));
+  assert_eq!(rating::settle(DEFAULT_RATING, DEFAULT_RATING, Outcome::Draw), (1200, 1200));
+  // an upset moves more than a win by the favourite
+  assert_eq!(rating::settle(1400, 1200, Outcome::InitializerWins), (1408, 1192));
+  assert_eq!(rating::settle(1400, 1200, Outcome::GuestWins), (1376, 1224));
+  assert_eq!(rating::settle(RATING_FLOOR, 1200, Outcome::GuestWins).0, RATING_FLOOR);
+}
+
+#[test]
+fn rating_bands() {
+  assert!(rating::in_band(1200, 0, 0));
+  assert!(rating::in_band(1200, 1200, 1200));
+  assert!(!rating::in_band(1199, 1200, 0));
+  assert!(!rating::in_band(1401, 0, 1400));
+}
@@ -0,0 +1,80 @@
+use rps::error::GameError;
+use rps::rules::{self, Outcome};
+
+// 1 rock, 2 scissors, 3 paper, 4 lizard, 5 spock
+const ROCK: u8 = 1;
+const SCISSORS: u8 = 2;
+const PAPER: u8 = 3;
+const LIZARD: u8 = 4;
+const SPOCK: u8 = 5;
+
+#[test]
+fn classic_rules() {
+  let wins = [(ROCK, SCISSORS), (SCISSORS, PAPER), (PAPER, ROCK)];
+  for (a, b) in wins {
+    assert_eq!(rules::resolve(rules::CLASSIC, 0, a, b), Ok(Outcome::InitializerWins));
+    assert_eq!(rules::resolve(rules::CLASSIC, 0, b, a), Ok(Outcome::GuestWins));
+  }
+  for a in 1..=3 {
+    assert_eq!(rules::resolve(rules::CLASSIC, 0, a, a), Ok(Outcome::Draw));
+  }
+  assert_eq!(rules::resolve(rules::CLASSIC, 0, LIZARD, ROCK), Err(GameError::InvalidMove));
+  assert_eq!(rules::resolve(rules::CLASSIC, 0, ROCK, 0), Err(GameError::InvalidMove));
+}
+
+#[test]
+fn rpsls_rules() {
+  let wins = [
+    (ROCK, SCISSORS), (ROCK, LIZARD),
+    (SCISSORS, PAPER), (SCISSORS, LIZARD),
+    (PAPER, ROCK), (PAPER, SPOCK),
+    (LIZARD, PAPER), (LIZARD, SPOCK),
+    (SPOCK, ROCK), (SPOCK, SCISSORS),
+  ];
+  for (a, b) in wins {
+    assert_eq!(rules::resolve(rules::RPSLS, 0, a, b), Ok(Outcome::InitializerWins));
+    assert_eq!(rules::resolve(rules::RPSLS, 0, b, a), Ok(Outcome::GuestWins));
+  }
+  assert_eq!(rules::resolve(rules::RPSLS, 0, 6, ROCK), Err(GameError::InvalidMove));
+}
+
+#[test]
+fn cyclic_rules_are_balanced() {
+  for count in [3u8, 7, 9, 25, rules::MAX_MOVES] {
+    for a in 1..=count {
+      let mut beaten = 0;
+      for b in 1..=count {
+        let forward = rules::resolve(rules::CYCLIC, count, a, b).unwrap();
+        let backward = rules::resolve(rules::CYCLIC, count, b, a).unwrap();
+        match forward {
+          Outcome::Draw => assert_eq!(a, b),
+          Outcome::InitializerWins => {
+            assert_eq!(backward, Outcome::GuestWins);
+            beaten += 1;
+          }
+          Outcome::GuestWins => assert_eq!(backward, Outcome::InitializerWins),
+        }
+      }
+      assert_eq!(beaten, (count - 1) / 2);
+    }
+  }
+}
+
+#[test]
+fn cyclic_three_matches_classic() {
+  for a in 1..=3 {
+    for b in 1..=3 {
+      assert_eq!(rules::resolve(rules::CYCLIC, 3, a, b), rules::resolve(rules::CLASSIC, 0, a, b));
+    }
+  }
+}
+
+#[test]
+fn invalid_rulesets() {
+  assert_eq!(rules::move_count(3, 0), None);
+  assert_eq!(rules::move_count(rules::CYCLIC, 1), None);
+  assert_eq!(rules::move_count(rules::CYCLIC, 4), None);
+  assert_eq!(rules::move_count(rules::CYCLIC, rules::MAX_MOVES + 2), None);
+  assert_eq!(rules::resolve(rules::CYCLIC, 4, 1, 2), Err(GameError::InvalidRuleset));
+  assert!(!rules::is_valid_move(3, 0, ROCK));
+}
@@ -0,0 +1,64 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use rps::error::GameError;
+use rps::status::{self, Event, GameStatus};
+
+const INITIALIZER: u8 = 1;
+const GUEST: u8 = 2;
+
+fn step(from: GameStatus, events: &[Event]) -> Result<GameStatus, GameError> {
+  events.iter().try_fold(from, |at, event| status::transition(at, *event))
+}
+
+#[test]
+fn a_casual_round_needs_both_reveals() {
+  let active: GameStatus = status::transition(GameStatus::Open, Event::Join{turn:0}).unwrap();
+  assert_eq!(active, GameStatus::Active{turn:0});
+
+  assert_eq!(step(active, &[Event::Reveal{player:INITIALIZER}]), Ok(GameStatus::Active{turn:GUEST}));
+  assert_eq!(step(active, &[Event::Reveal{player:GUEST}]), Ok(GameStatus::Active{turn:INITIALIZER}));
+  assert_eq!(step(active, &[Event::Reveal{player:GUEST}, Event::Reveal{player:INITIALIZER}]), Ok(GameStatus::Active{turn:0}));
+  assert_eq!(step(active, &[Event::Reveal{player:GUEST}, Event::Reveal{player:GUEST}]), Err(GameError::WrongTurn));
+  assert_eq!(step(active, &[Event::End{winner:0}]), Ok(GameStatus::Finished{winner:0}));
+}
+
+#[test]
+fn a_tournament_match_alternates_moves() {
+  let active: GameStatus = status::transition(GameStatus::Open, Event::Join{turn:INITIALIZER}).unwrap();
+
+  assert_eq!(step(active, &[Event::Move{player:INITIALIZER}]), Ok(GameStatus::Active{turn:GUEST}));
+  assert_eq!(step(active, &[Event::Move{player:INITIALIZER}, Event::Move{player:GUEST}]), Ok(active));
+  assert_eq!(step(active, &[Event::Move{player:GUEST}]), Err(GameError::WrongTurn));
+  assert_eq!(step(active, &[Event::Timeout]), Ok(GameStatus::TimedOut));
+}
+
+#[test]
+fn illegal_transitions_are_rejected() {
+  let active: GameStatus = GameStatus::Active{turn:0};
+
+  assert_eq!(status::transition(GameStatus::Open, Event::Reveal{player:INITIALIZER}), Err(GameError::GameNotActive));
+  assert_eq!(status::transition(GameStatus::Open, Event::Timeout), Err(GameError::GameNotActive));
+  assert_eq!(status::transition(active, Event::Join{turn:0}), Err(GameError::GameNotOpen));
+  assert_eq!(status::transition(active, Event::Abort), Err(GameError::GameNotOpen));
+  assert_eq!(status::transition(active, Event::Timeout), Err(GameError::NoMovePending));
+  for over in [GameStatus::Finished{winner:GUEST}, GameStatus::Aborted, GameStatus::TimedOut] {
+    assert_eq!(status::transition(over, Event::Reveal{player:GUEST}), Err(GameError::GameFinished));
+    assert_eq!(status::transition(over, Event::Abort), Err(GameError::GameFinished));
+  }
+}
+
+#[test]
+fn every_status_takes_two_bytes() {
+  let all = [
+    GameStatus::Open,
+    GameStatus::Active{turn:GUEST},
+    GameStatus::Finished{winner:INITIALIZER},
+    GameStatus::Aborted,
+    GameStatus::TimedOut,
+  ];
+  for status in all {
+    let data: Vec<u8> = status.try_to_vec().unwrap();
+    assert_eq!(data.len(), 2);
+    assert_eq!(GameStatus::try_from_slice(&data).unwrap(), status);
+  }
+  assert!(GameStatus::try_from_slice(&[5, 0]).is_err());
+}
@@ -0,0 +1,309 @@
+mod common;
+
+use common::*;
+use rps::client;
+use rps::error::GameError;
+use rps::rules;
+use rps::state::{Commit, Init, InitConfig, InitializerPlay, Tournament, TournamentCounter};
+use solana_program::{program_pack::Pack, pubkey::Pubkey, rent::Rent, system_instruction};
+use solana_program_test::{BanksClientError, ProgramTestContext};
+use solana_sdk::signature::{Keypair, Signer};
+
+const STAKE: u64 = 5_000;
+const TOKENS: u64 = 1_000_000;
+const GAME_SEED: &str = "game000001";
+const GAME_STATE_LEN: usize = 849;
+const ROCK: u8 = 1;
+const PAPER: u8 = 3;
+
+struct Game {
+  program_id: Pubkey,
+  initializer: Keypair,
+  guest: Keypair,
+  host: Keypair,
+  rent_data: Pubkey,
+  game_state: Pubkey,
+  bump: u8,
+  mint: Pubkey,
+  // token accounts of the initializer, the guest and the host
+  tokens: [Pubkey; 3],
+}
+
+async fn create_mint(context: &mut ProgramTestContext) -> Pubkey {
+  let mint = Keypair::new();
+  let payer = context.payer.pubkey();
+  let create = system_instruction::create_account(
+    &payer,
+    &mint.pubkey(),
+    Rent::default().minimum_balance(spl_token::state::Mint::LEN),
+    spl_token::state::Mint::LEN as u64,
+    &spl_token::id(),
+  );
+  let init = spl_token::instruction::initialize_mint2(&spl_token::id(), &mint.pubkey(), &payer, None, 0).unwrap();
+  process(context, &[create, init], &[&mint]).await.unwrap();
+  mint.pubkey()
+}
+
+// A token account of `owner` holding `amount` freshly minted tokens.
+async fn token_account(context: &mut ProgramTestContext, mint: &Pubkey, owner: &Pubkey, amount: u64) -> Pubkey {
+  let account = Keypair::new();
+  let payer = context.payer.pubkey();
+  let create = system_instruction::create_account(
+    &payer,
+    &account.pubkey(),
+    Rent::default().minimum_balance(spl_token::state::Account::LEN),
+    spl_token::state::Account::LEN as u64,
+    &spl_token::id(),
+  );
+  let init = spl_token::instruction::initialize_account3(&spl_token::id(), &account.pubkey(), mint, owner).unwrap();
+  let mint_to = spl_token::instruction::mint_to(&spl_token::id(), mint, &account.pubkey(), &payer, &[], amount).unwrap();
+  process(context, &[create, init, mint_to], &[&account]).await.unwrap();
+  account.pubkey()
+}
+
+async fn token_balance(context: &mut ProgramTestContext, account: &Pubkey) -> Option<u64> {
+  account_data(context, account).await.map(|data| spl_token::state::Account::unpack(&data).unwrap().amount)
+}
+
+async fn setup() -> (ProgramTestContext, Game) {
+  let program_id = Pubkey::new_unique();
+  let initializer = Keypair::new();
+  let guest = Keypair::new();
+  let host = Keypair::new();
+  let rent_data = Pubkey::new_unique();
+
+  let mut test = program_test(&program_id);
+  add_funded(&mut test, &initializer.pubkey());
+  add_funded(&mut test, &guest.pubkey());
+  add_funded(&mut test, &host.pubkey());
+  add_rent_account(&mut test, &program_id, &rent_data, Rent::default().minimum_balance(GAME_STATE_LEN));
+
+  let (game_state, bump) = client::find_game_address(&program_id, &initializer.pubkey(), GAME_SEED);
+  let mut context = test.start_with_context().await;
+
+  let mint = create_mint(&mut context).await;
+  let tokens = [
+    token_account(&mut context, &mint, &initializer.pubkey(), TOKENS).await,
+    token_account(&mut context, &mint, &guest.pubkey(), TOKENS).await,
+    token_account(&mut context, &mint, &host.pubkey(), 0).await,
+  ];
+
+  (context, Game{ program_id, initializer, guest, host, rent_data, game_state, bump, mint, tokens })
+}
+
+async fn init_game(context: &mut ProgramTestContext, game: &Game) {
+  let init = client::init_game(
+    &game.program_id,
+    &game.initializer.pubkey(),
+    &game.game_state,
+    &game.host.pubkey(),
+    &game.rent_data,
+    Init{
+      bump: game.bump,
+      game_ends: 1,
+      gameseed: GAME_SEED.to_string(),
+      lamports: STAKE,
+      game_hash: commit(&game.game_state, 0, &game.initializer.pubkey(), ROCK),
+      max_rounds: 0,
+      sudden_death: 0,
+      ruleset: rules::CLASSIC,
+      moves: 0,
+      ranked: 0,
+      min_rating: 0,
+      max_rating: 0,
+      invited: [0; 32],
+      allowlist_root: [0; 32],
+      mint: game.mint.to_bytes(),
+      lobby_page: 0,
+    },
+  );
+  let init = client::with_new_escrow(init, &game.game_state, &game.mint, &[game.tokens[0]]);
+  process(context, &[init], &[&game.initializer]).await.unwrap();
+}
+
+async fn join_game(context: &mut ProgramTestContext, game: &Game, guest_tokens: &Pubkey) -> Result<(), BanksClientError> {
+  let join = client::join_game(
+    &game.program_id,
+    &game.guest.pubkey(),
+    &game.game_state,
+    &game.host.pubkey(),
+    Commit{ game_hash: commit(&game.game_state, 0, &game.guest.pubkey(), PAPER), proof: vec![] },
+  );
+  let join = client::with_tokens(join, &game.game_state, &[*guest_tokens]);
+  process(context, &[join], &[&game.guest]).await
+}
+
+fn opening(lastmove: u8) -> InitializerPlay {
+  InitializerPlay{ salt: salt(0), lastmove, new_game_hash: [0; 32] }
+}
+
+#[tokio::test]
+async fn a_token_game_pays_out_in_tokens() {
+  let (mut context, game) = setup().await;
+  let (escrow, _) = client::find_escrow_address(&game.program_id, &game.game_state);
+
+  init_game(&mut context, &game).await;
+  join_game(&mut context, &game, &game.tokens[1]).await.unwrap();
+  assert_eq!(token_balance(&mut context, &escrow).await, Some(2 * STAKE));
+
+  let play = client::initializer_play(
+    &game.program_id,
+    &game.initializer.pubkey(),
+    &game.guest.pubkey(),
+    &game.host.pubkey(),
+    &game.game_state,
+    &game.rent_data,
+    opening(ROCK),
+  );
+  process(&mut context, &[client::with_tokens(play, &game.game_state, &game.tokens)], &[&game.initializer]).await.unwrap();
+  let play = client::guest_play(
+    &game.program_id,
+    &game.initializer.pubkey(),
+    &game.guest.pubkey(),
+    &game.host.pubkey(),
+    &game.game_state,
+    &game.rent_data,
+    opening(PAPER),
+  );
+  process(&mut context, &[client::with_tokens(play, &game.game_state, &game.tokens)], &[&game.guest]).await.unwrap();
+
+  let host_fee: u64 = STAKE / 50;
+  assert_eq!(token_balance(&mut context, &game.tokens[0]).await, Some(TOKENS - STAKE));
+  assert_eq!(token_balance(&mut context, &game.tokens[1]).await, Some(TOKENS + STAKE - host_fee));
+  assert_eq!(token_balance(&mut context, &game.tokens[2]).await, Some(host_fee));
+  // the escrow and the game account are closed back to the initializer
+  assert_eq!(account_data(&mut context, &escrow).await, None);
+  assert_eq!(balance(&mut context, &game.game_state).await, 0);
+}
+
+#[tokio::test]
+async fn aborting_a_token_game_refunds_the_stake() {
+  let (mut context, game) = setup().await;
+  let (escrow, _) = client::find_escrow_address(&game.program_id, &game.game_state);
+
+  init_game(&mut context, &game).await;
+  assert_eq!(token_balance(&mut context, &game.tokens[0]).await, Some(TOKENS - STAKE));
+
+  let abort = client::abort(&game.program_id, &game.initializer.pubkey(), &game.game_state);
+  process(&mut context, &[client::with_tokens(abort, &game.game_state, &[game.tokens[0]])], &[&game.initializer]).await.unwrap();
+
+  assert_eq!(token_balance(&mut context, &game.tokens[0]).await, Some(TOKENS));
+  assert_eq!(account_data(&mut context, &escrow).await, None);
+}
+
+#[tokio::test]
+async fn a_prefunded_escrow_can_still_be_created() {
+  let (mut context, game) = setup().await;
+  let (escrow, _) = client::find_escrow_address(&game.program_id, &game.game_state);
+  // the least anyone can send to an empty address
+  let grief = system_instruction::transfer(&game.host.pubkey(), &escrow, Rent::default().minimum_balance(0));
+  process(&mut context, &[grief], &[&game.host]).await.unwrap();
+
+  init_game(&mut context, &game).await;
+  assert_eq!(token_balance(&mut context, &escrow).await, Some(STAKE));
+}
+
+#[tokio::test]
+async fn stakes_only_come_from_the_players_own_token_account() {
+  let (mut context, game) = setup().await;
+  init_game(&mut context, &game).await;
+
+  let result: Result<(), BanksClientError> = join_game(&mut context, &game, &game.tokens[0]).await;
+  assert_game_error(result, GameError::InvalidTokenAccount);
+}
+
+const TOURNAMENT_ID: &str = "tok01";
+const FEE: u64 = 1_000;
+const TIME_IS_UP: u64 = 300;
+
+fn player_seed(player_no: u32) -> String {
+  let mut seed: String = format!("{:p>10}", player_no).chars().rev().collect();
+  seed += TOURNAMENT_ID;
+  seed
+}
+
+#[tokio::test]
+async fn a_token_tournament_pays_rewards_and_the_prize_in_tokens() {
+  let program_id = Pubkey::new_unique();
+  let authority = Keypair::new();
+  let players = [Keypair::new(), Keypair::new()];
+  let counter = Pubkey::new_unique();
+
+  let mut test = program_test(&program_id);
+  add_funded(&mut test, &authority.pubkey());
+  add_program_data(&mut test, &program_id, &authority.pubkey());
+  for player in &players {
+    add_funded(&mut test, &player.pubkey());
+  }
+  let mut context = test.start_with_context().await;
+
+  let mint = create_mint(&mut context).await;
+  let mut tokens: Vec<Pubkey> = Vec::new();
+  for player in &players {
+    tokens.push(token_account(&mut context, &mint, &player.pubkey(), TOKENS).await);
+  }
+
+  let (config, bump) = client::find_config_address(&program_id);
+  process(&mut context, &[client::init_config(&program_id, &authority.pubkey(), &config, InitConfig{ bump })], &[&authority]).await.unwrap();
+
+  let (tournament, bump) = Pubkey::find_program_address(&[TOURNAMENT_ID.as_bytes()], &program_id);
+  let mut lvl_get = [0u8; 30];
+  lvl_get[0] = 1;
+  let t = Tournament{
+    is_init: 1,
+    tournament_id: TOURNAMENT_ID.to_string(),
+    entrance_fee: FEE,
+    tournament_size: 1,
+    rent: Rent::default().minimum_balance(200),
+    starts_at: now(&mut context).await,
+    time_is_up: TIME_IS_UP,
+    lvl_get,
+    winner_get: FEE,
+    host_get: 0,
+    number_of_counters: 2,
+    number_of_counterfinders: 1,
+    finderfinder: 0,
+    game_ends: 1,
+    bump,
+    ruleset: 0,
+    moves: 0,
+    mint: mint.to_bytes(),
+  };
+  let init = client::init_tournament(&program_id, &authority.pubkey(), &tournament, &config, t.clone());
+  process(&mut context, &[client::with_new_escrow(init, &tournament, &mint, &[])], &[&authority]).await.unwrap();
+  set_program_account(&mut context, &program_id, &counter, &TournamentCounter{
+    empty_tournament_id: format!("E{}", TOURNAMENT_ID),
+    counter_no: 1,
+    player_participating: 0,
+    capacity: 2,
+  });
+
+  let mut tour_accs: Vec<Pubkey> = Vec::new();
+  for (i, player) in players.iter().enumerate() {
+    let (tour_acc, bump) = Pubkey::find_program_address(&[player_seed(i as u32 + 1).as_bytes()], &program_id);
+    let join = client::join_tournament(
+      &program_id,
+      &player.pubkey(),
+      &tour_acc,
+      &tournament,
+      &counter,
+      Init{ bump, game_ends: 0, gameseed: String::new(), lamports: 0, game_hash: [0; 32], max_rounds: 0, sudden_death: 0, ruleset: 0, moves: 0, ranked: 0, min_rating: 0, max_rating: 0, invited: [0; 32], allowlist_root: [0; 32], mint: [0; 32], lobby_page: 0 },
+    );
+    process(&mut context, &[client::with_tokens(join, &tournament, &[tokens[i]])], &[player]).await.unwrap();
+    tour_accs.push(tour_acc);
+  }
+  let (escrow, _) = client::find_escrow_address(&program_id, &tournament);
+  assert_eq!(token_balance(&mut context, &escrow).await, Some(2 * FEE));
+  assert_eq!(token_balance(&mut context, &tokens[0]).await, Some(TOKENS - FEE));
+
+  // player 1 never sets up the first match and is knocked out with the level 0 reward
+  warp_seconds(&mut context, TIME_IS_UP as i64 + 1).await;
+  let eliminate = client::eliminate_for_initialize(&program_id, &players[0].pubkey(), &tour_accs[0], &players[1].pubkey(), &tour_accs[1], &tournament);
+  process(&mut context, &[client::with_tokens(eliminate, &tournament, &[tokens[0]])], &[]).await.unwrap();
+  assert_eq!(token_balance(&mut context, &tokens[0]).await, Some(TOKENS));
+
+  let claim = client::winner_claim(&program_id, &players[1].pubkey(), &tour_accs[1], &tournament);
+  process(&mut context, &[client::with_tokens(claim, &tournament, &[tokens[1]])], &[]).await.unwrap();
+  assert_eq!(token_balance(&mut context, &tokens[1]).await, Some(TOKENS));
+  assert_eq!(token_balance(&mut context, &escrow).await, Some(0));
+}
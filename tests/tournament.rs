@@ -0,0 +1,624 @@
+mod common;
+
+use common::*;
+use rps::account::{AccountData, HEADER_LEN};
+use rps::client;
+use rps::error::GameError;
+use rps::status::GameStatus;
+use rps::state::{GameState, Init, InitializerPlay, Join, TGameState, Tournament, TournamentAccount, TournamentCounter};
+use solana_program::pubkey::Pubkey;
+use solana_program_test::ProgramTestContext;
+use solana_sdk::signature::{Keypair, Signer};
+
+const TOURNAMENT_ID: &str = "cup01";
+const FEE: u64 = 1_000_000_000;
+const TIME_IS_UP: u64 = 300;
+
+// 1 rock, 2 scissors, 3 paper
+const ROCK: u8 = 1;
+const SCISSORS: u8 = 2;
+const PAPER: u8 = 3;
+
+#[derive(Clone, Copy, Debug)]
+enum Outcome {
+  InitializerWins,
+  GuestWinsAfterDraw,
+  InitializerNoShow,
+  GuestMoveTimeout,
+}
+
+const OUTCOMES: [Outcome; 4] = [
+  Outcome::InitializerWins,
+  Outcome::GuestWinsAfterDraw,
+  Outcome::InitializerNoShow,
+  Outcome::GuestMoveTimeout,
+];
+
+struct Player {
+  wallet: Keypair,
+  tour_acc: Pubkey,
+  paid: u64,
+  received: u64,
+}
+
+struct Sim {
+  context: ProgramTestContext,
+  program_id: Pubkey,
+  tournament: Pubkey,
+  counter: Pubkey,
+  t: Tournament,
+  players: Vec<Player>,
+  games_created: u64,
+}
+
+fn player_seed(player_no: u32) -> String {
+  let str_player_no = player_no.to_string();
+  let mut padded = String::from("pppppppppp");
+  padded.replace_range(padded.len() - str_player_no.len().., &str_player_no);
+  let mut seed: String = padded.chars().rev().collect();
+  seed += TOURNAMENT_ID;
+  seed
+}
+
+fn game_id(a: u32, b: u32) -> String {
+  format!("{}{}v{}", TOURNAMENT_ID, a.min(b), a.max(b))
+}
+
+fn levels(size: usize) -> u8 {
+  size.trailing_zeros() as u8
+}
+
+async fn setup(size: usize, lvl_get: &[u8], winner_get: u64) -> Sim {
+  let program_id = Pubkey::new_unique();
+  let tournament = Pubkey::new_unique();
+  let counter = Pubkey::new_unique();
+
+  let mut test = program_test(&program_id);
+  let wallets: Vec<Keypair> = (0..size).map(|_| Keypair::new()).collect();
+  for wallet in &wallets {
+    add_funded(&mut test, &wallet.pubkey());
+  }
+  let mut context = test.start_with_context().await;
+
+  let mut lvl_get_arr = [0u8; 30];
+  lvl_get_arr[..lvl_get.len()].copy_from_slice(lvl_get);
+
+  let t = Tournament{
+    is_init: 1,
+    tournament_id: TOURNAMENT_ID.to_string(),
+    entrance_fee: FEE,
+    tournament_size: levels(size),
+    rent: FEE,
+    starts_at: now(&mut context).await,
+    time_is_up: TIME_IS_UP,
+    lvl_get: lvl_get_arr,
+    winner_get,
+    host_get: 0,
+    number_of_counters: size as u8,
+    number_of_counterfinders: 1,
+    finderfinder: 0,
+    game_ends: 1,
+    bump: 0,
+    ruleset: 0,
+    moves: 0,
+    mint: [0; 32],
+  };
+  set_program_account(&mut context, &program_id, &tournament, &t);
+  set_program_account(&mut context, &program_id, &counter, &TournamentCounter{
+    empty_tournament_id: format!("E{}", TOURNAMENT_ID),
+    counter_no: 1,
+    player_participating: 0,
+    capacity: size as u32,
+  });
+
+  let players = wallets.into_iter().enumerate().map(|(i, wallet)| {
+    let (tour_acc, _) = Pubkey::find_program_address(&[player_seed(i as u32 + 1).as_bytes()], &program_id);
+    Player{ wallet, tour_acc, paid: 0, received: 0 }
+  }).collect();
+
+  Sim{ context, program_id, tournament, counter, t, players, games_created: 0 }
+}
+
+impl Sim {
+  async fn tour_account(&mut self, player: usize) -> TournamentAccount {
+    let data = account_data(&mut self.context, &self.players[player].tour_acc).await.unwrap();
+    TournamentAccount::load(&data).unwrap()
+  }
+
+  async fn join(&mut self, player: usize) {
+    let (_, bump) = Pubkey::find_program_address(&[player_seed(player as u32 + 1).as_bytes()], &self.program_id);
+    let p = &self.players[player];
+    let ix = client::join_tournament(
+      &self.program_id,
+      &p.wallet.pubkey(),
+      &p.tour_acc,
+      &self.tournament,
+      &self.counter,
+      Init{ bump, game_ends: 0, gameseed: String::new(), lamports: 0, game_hash: [0; 32], max_rounds: 0, sudden_death: 0, ruleset: 0, moves: 0, ranked: 0, min_rating: 0, max_rating: 0, invited: [0; 32], allowlist_root: [0; 32], mint: [0; 32], lobby_page: 0 },
+    );
+    process(&mut self.context, &[ix], &[&p.wallet]).await.unwrap();
+    self.players[player].paid += self.t.rent;
+  }
+
+  async fn init_match(&mut self, initializer: usize, guest: usize, first_move: u8) -> Pubkey {
+    let a = self.tour_account(initializer).await.playerno_int;
+    let b = self.tour_account(guest).await.playerno_int;
+    let (game_state, bump) = Pubkey::find_program_address(&[game_id(a, b).as_bytes()], &self.program_id);
+    let p = &self.players[initializer];
+    let ix = client::init_tournament_match(
+      &self.program_id,
+      &p.wallet.pubkey(),
+      &p.tour_acc,
+      &game_state,
+      &self.tournament,
+      Init{ bump, game_ends: 0, gameseed: String::new(), lamports: 0, game_hash: commit(&game_state, 0, &p.wallet.pubkey(), first_move), max_rounds: 0, sudden_death: 0, ruleset: 0, moves: 0, ranked: 0, min_rating: 0, max_rating: 0, invited: [0; 32], allowlist_root: [0; 32], mint: [0; 32], lobby_page: 0 },
+    );
+    process(&mut self.context, &[ix], &[&p.wallet]).await.unwrap();
+    self.players[initializer].paid += self.t.rent;
+    self.games_created += 1;
+    game_state
+  }
+
+  async fn accept(&mut self, initializer: usize, guest: usize, game_state: &Pubkey, mymove: u8) {
+    self.try_accept(initializer, guest, game_state, mymove).await.unwrap();
+  }
+
+  async fn try_accept(&mut self, initializer: usize, guest: usize, game_state: &Pubkey, mymove: u8) -> Result<(), solana_program_test::BanksClientError> {
+    let (i, g) = (&self.players[initializer], &self.players[guest]);
+    let ix = client::tournament_match_accept(
+      &self.program_id,
+      &i.wallet.pubkey(),
+      &i.tour_acc,
+      game_state,
+      &g.tour_acc,
+      &g.wallet.pubkey(),
+      &self.tournament,
+      Join{ mymove },
+    );
+    process(&mut self.context, &[ix], &[&g.wallet]).await
+  }
+
+  async fn reveal(&mut self, initializer: usize, guest: usize, game_state: &Pubkey, last: u8, next: u8) {
+    self.try_reveal(initializer, guest, game_state, last, next).await.unwrap();
+  }
+
+  async fn try_reveal(&mut self, initializer: usize, guest: usize, game_state: &Pubkey, last: u8, next: u8) -> Result<(), solana_program_test::BanksClientError> {
+    let data = account_data(&mut self.context, game_state).await.unwrap();
+    let round = TGameState::load(&data).unwrap().rounds_played;
+    let (i, g) = (&self.players[initializer], &self.players[guest]);
+    let ix = client::tournament_initializer_play(
+      &self.program_id,
+      &i.wallet.pubkey(),
+      &i.tour_acc,
+      &g.wallet.pubkey(),
+      &g.tour_acc,
+      game_state,
+      &self.tournament,
+      InitializerPlay{ salt: salt(round), lastmove: last, new_game_hash: commit(game_state, round + 1, &i.wallet.pubkey(), next) },
+    );
+    process(&mut self.context, &[ix], &[&i.wallet]).await
+  }
+
+  async fn guest_play(&mut self, initializer: usize, guest: usize, game_state: &Pubkey, mymove: u8) {
+    self.try_guest_play(initializer, guest, game_state, mymove).await.unwrap();
+  }
+
+  async fn try_guest_play(&mut self, initializer: usize, guest: usize, game_state: &Pubkey, mymove: u8) -> Result<(), solana_program_test::BanksClientError> {
+    let (i, g) = (&self.players[initializer], &self.players[guest]);
+    let ix = client::tournament_guest_play(
+      &self.program_id,
+      &i.wallet.pubkey(),
+      &i.tour_acc,
+      &g.wallet.pubkey(),
+      &g.tour_acc,
+      game_state,
+      &self.tournament,
+      InitializerPlay{ salt: [0; 32], lastmove: mymove, new_game_hash: [0; 32] },
+    );
+    process(&mut self.context, &[ix], &[&g.wallet]).await
+  }
+
+  async fn eliminate_no_show(&mut self, eliminate: usize, opponent: usize) -> Result<(), solana_program_test::BanksClientError> {
+    let (e, o) = (&self.players[eliminate], &self.players[opponent]);
+    let ix = client::eliminate_for_initialize(
+      &self.program_id,
+      &e.wallet.pubkey(),
+      &e.tour_acc,
+      &o.wallet.pubkey(),
+      &o.tour_acc,
+      &self.tournament,
+    );
+    process(&mut self.context, &[ix], &[]).await
+  }
+
+  async fn eliminate_idle(&mut self, initializer: usize, guest: usize, game_state: &Pubkey) -> Result<(), solana_program_test::BanksClientError> {
+    let (i, g) = (&self.players[initializer], &self.players[guest]);
+    let ix = client::eliminate_for_move(
+      &self.program_id,
+      &i.wallet.pubkey(),
+      &i.tour_acc,
+      &g.wallet.pubkey(),
+      &g.tour_acc,
+      game_state,
+      &self.tournament,
+    );
+    process(&mut self.context, &[ix], &[]).await
+  }
+
+  // Plays one match between the lower numbered player a and its bracket
+  // opponent b, returning the winner.
+  async fn play_match(&mut self, a: usize, b: usize, outcome: Outcome) -> usize {
+    let level = self.tour_account(a).await.level as usize;
+    let reward = self.t.lvl_get[level] as u64 * self.t.entrance_fee;
+
+    let (winner, loser) = match outcome {
+      Outcome::InitializerWins => {
+        let game = self.init_match(a, b, ROCK).await;
+        self.accept(a, b, &game, SCISSORS).await;
+        self.reveal(a, b, &game, ROCK, ROCK).await;
+        assert_eq!(balance(&mut self.context, &game).await, 0);
+        (a, b)
+      }
+      Outcome::GuestWinsAfterDraw => {
+        let game = self.init_match(a, b, ROCK).await;
+        self.accept(a, b, &game, ROCK).await;
+        self.reveal(a, b, &game, ROCK, PAPER).await;
+        assert!(balance(&mut self.context, &game).await > 0);
+        self.guest_play(a, b, &game, SCISSORS).await;
+        self.reveal(a, b, &game, PAPER, ROCK).await;
+        assert_eq!(balance(&mut self.context, &game).await, 0);
+        (b, a)
+      }
+      Outcome::InitializerNoShow => {
+        assert_game_error(self.eliminate_no_show(a, b).await, GameError::TimeoutNotReached);
+        warp_seconds(&mut self.context, TIME_IS_UP as i64 + 1).await;
+        self.eliminate_no_show(a, b).await.unwrap();
+        (b, a)
+      }
+      Outcome::GuestMoveTimeout => {
+        let game = self.init_match(a, b, PAPER).await;
+        self.accept(a, b, &game, PAPER).await;
+        self.reveal(a, b, &game, PAPER, ROCK).await;
+        assert_game_error(self.eliminate_idle(a, b, &game).await, GameError::TimeoutNotReached);
+        warp_seconds(&mut self.context, TIME_IS_UP as i64 + 1).await;
+        self.eliminate_idle(a, b, &game).await.unwrap();
+        assert_eq!(balance(&mut self.context, &game).await, 0);
+        (a, b)
+      }
+    };
+
+    self.players[loser].received += reward;
+    assert_eq!(balance(&mut self.context, &self.players[loser].tour_acc).await, 0);
+
+    let advanced = self.tour_account(winner).await;
+    assert_eq!(advanced.level as usize, level + 1);
+    assert_eq!((advanced.is_playing, advanced.waiting_opponent_to_join), (0, 0));
+    winner
+  }
+}
+
+async fn run_tournament(size: usize, lvl_get: &[u8], winner_get: u64) {
+  let mut sim = setup(size, lvl_get, winner_get).await;
+
+  for player in 0..size {
+    sim.join(player).await;
+  }
+  let counter = TournamentCounter::load(&account_data(&mut sim.context, &sim.counter).await.unwrap()).unwrap();
+  assert_eq!(counter.player_participating, size as u32);
+  assert_eq!(counter.empty_tournament_id, format!("F{}", TOURNAMENT_ID));
+
+  let mut alive: Vec<usize> = (0..size).collect();
+  let mut played = 0;
+  for _ in 0..sim.t.tournament_size {
+    let mut next = Vec::new();
+    for pair in alive.chunks(2) {
+      next.push(sim.play_match(pair[0], pair[1], OUTCOMES[played % OUTCOMES.len()]).await);
+      played += 1;
+    }
+    alive = next;
+  }
+  assert_eq!(alive.len(), 1);
+  assert_eq!(played, size - 1);
+
+  let champion = alive[0];
+  let mut finalists = 0;
+  for player in 0..size {
+    if let Some(data) = account_data(&mut sim.context, &sim.players[player].tour_acc).await {
+      let account = TournamentAccount::load(&data).unwrap();
+      assert_eq!(account.level, sim.t.tournament_size);
+      assert_eq!(player, champion);
+      finalists += 1;
+    }
+  }
+  assert_eq!(finalists, 1);
+
+  let p = &sim.players[champion];
+  let claim = client::winner_claim(&sim.program_id, &p.wallet.pubkey(), &p.tour_acc, &sim.tournament);
+  process(&mut sim.context, &[claim], &[]).await.unwrap();
+  sim.players[champion].received += sim.t.winner_get;
+
+  let total_paid_out: u64 = sim.players.iter().map(|p| p.received).sum();
+  assert_eq!(total_paid_out, size as u64 * sim.t.entrance_fee);
+
+  for player in 0..size {
+    let p = &sim.players[player];
+    let (paid, received, wallet) = (p.paid, p.received, p.wallet.pubkey());
+    assert_eq!(balance(&mut sim.context, &wallet).await, PLAYER_FUNDS - paid + received, "player {}", player + 1);
+  }
+
+  let collected = size as u64 * sim.t.rent + sim.games_created * sim.t.rent;
+  let champion_acc = sim.players[champion].tour_acc;
+  assert_eq!(balance(&mut sim.context, &champion_acc).await, collected - total_paid_out);
+}
+
+#[tokio::test]
+async fn four_player_bracket() {
+  run_tournament(4, &[0, 1], 3 * FEE).await;
+}
+
+#[tokio::test]
+async fn eight_player_bracket() {
+  run_tournament(8, &[0, 1, 2], 4 * FEE).await;
+}
+
+#[tokio::test]
+async fn join_rejects_full_counter() {
+  let mut sim = setup(2, &[0], 2 * FEE).await;
+  sim.join(0).await;
+  sim.join(1).await;
+
+  let extra = Keypair::new();
+  let (tour_acc, bump) = Pubkey::find_program_address(&[player_seed(3).as_bytes()], &sim.program_id);
+  let fund = solana_program::system_instruction::transfer(&sim.context.payer.pubkey(), &extra.pubkey(), FEE * 2);
+  process(&mut sim.context, &[fund], &[]).await.unwrap();
+
+  let ix = client::join_tournament(
+    &sim.program_id,
+    &extra.pubkey(),
+    &tour_acc,
+    &sim.tournament,
+    &sim.counter,
+    Init{ bump, game_ends: 0, gameseed: String::new(), lamports: 0, game_hash: [0; 32], max_rounds: 0, sudden_death: 0, ruleset: 0, moves: 0, ranked: 0, min_rating: 0, max_rating: 0, invited: [0; 32], allowlist_root: [0; 32], mint: [0; 32], lobby_page: 0 },
+  );
+  assert_game_error(process(&mut sim.context, &[ix], &[&extra]).await, GameError::CapacityFull);
+}
+
+#[tokio::test]
+async fn tournament_accounts_are_sized_to_their_encoding() {
+  let mut sim = setup(2, &[0], 2 * FEE).await;
+  sim.join(0).await;
+  sim.join(1).await;
+
+  let tour_acc = sim.players[0].tour_acc;
+  let data = account_data(&mut sim.context, &tour_acc).await.unwrap();
+  assert_eq!(data.len(), TournamentAccount::load(&data).unwrap().space().unwrap());
+
+  let game = sim.init_match(0, 1, ROCK).await;
+  let data = account_data(&mut sim.context, &game).await.unwrap();
+  assert_eq!(data.len(), TGameState::load(&data).unwrap().space().unwrap());
+}
+
+#[tokio::test]
+async fn join_checks_the_entrance_fee_on_the_created_account() {
+  let mut sim = setup(2, &[0], 2 * FEE).await;
+  sim.join(0).await;
+  let tour_acc = sim.players[0].tour_acc;
+  assert!(balance(&mut sim.context, &tour_acc).await >= FEE);
+
+  sim.t.rent = FEE / 2;
+  let (program_id, tournament, t) = (sim.program_id, sim.tournament, sim.t.clone());
+  set_program_account(&mut sim.context, &program_id, &tournament, &t);
+
+  let (_, bump) = Pubkey::find_program_address(&[player_seed(2).as_bytes()], &sim.program_id);
+  let p = &sim.players[1];
+  let ix = client::join_tournament(
+    &sim.program_id,
+    &p.wallet.pubkey(),
+    &p.tour_acc,
+    &sim.tournament,
+    &sim.counter,
+    Init{ bump, game_ends: 0, gameseed: String::new(), lamports: 0, game_hash: [0; 32], max_rounds: 0, sudden_death: 0, ruleset: 0, moves: 0, ranked: 0, min_rating: 0, max_rating: 0, invited: [0; 32], allowlist_root: [0; 32], mint: [0; 32], lobby_page: 0 },
+  );
+  assert_game_error(process(&mut sim.context, &[ix], &[&p.wallet]).await, GameError::InsufficientFunds);
+}
+
+#[tokio::test]
+async fn only_bracket_opponents_play_each_other() {
+  let mut sim = setup(4, &[0, 1], 3 * FEE).await;
+  for player in 0..4 {
+    sim.join(player).await;
+  }
+
+  let game = sim.init_match(0, 1, ROCK).await;
+  assert_game_error(sim.try_accept(0, 2, &game, SCISSORS).await, GameError::OpponentMismatch);
+  sim.accept(0, 1, &game, SCISSORS).await;
+
+  // the game now names its guest, which is checked before the pairing
+  assert_game_error(sim.try_reveal(0, 2, &game, ROCK, ROCK).await, GameError::InvalidGuest);
+  assert_game_error(sim.eliminate_idle(0, 2, &game).await, GameError::InvalidGuest);
+  sim.reveal(0, 1, &game, ROCK, ROCK).await;
+  assert_eq!(sim.tour_account(0).await.level, 1);
+}
+
+#[tokio::test]
+async fn guest_moves_read_the_match_as_a_tournament_game() {
+  let mut sim = setup(2, &[0], 2 * FEE).await;
+  sim.join(0).await;
+  sim.join(1).await;
+
+  let game = sim.init_match(0, 1, ROCK).await;
+  sim.accept(0, 1, &game, ROCK).await;
+  sim.reveal(0, 1, &game, ROCK, PAPER).await;
+
+  let data = account_data(&mut sim.context, &game).await.unwrap();
+  let mut casual = data.clone();
+  casual[..GameState::DISCRIMINATOR.len()].copy_from_slice(&GameState::DISCRIMINATOR);
+  overwrite(&mut sim.context, &game, casual).await;
+  assert_game_error(sim.try_guest_play(0, 1, &game, SCISSORS).await, GameError::WrongAccountType);
+  warp_seconds(&mut sim.context, TIME_IS_UP as i64 + 1).await;
+  assert_game_error(sim.eliminate_idle(0, 1, &game).await, GameError::WrongAccountType);
+
+  overwrite(&mut sim.context, &game, data).await;
+  sim.guest_play(0, 1, &game, SCISSORS).await;
+}
+
+#[tokio::test]
+async fn guest_play_records_the_move_for_the_next_reveal() {
+  let mut sim = setup(2, &[0], 2 * FEE).await;
+  sim.join(0).await;
+  sim.join(1).await;
+
+  let game = sim.init_match(0, 1, ROCK).await;
+  sim.accept(0, 1, &game, ROCK).await;
+  sim.reveal(0, 1, &game, ROCK, PAPER).await;
+  let before = TGameState::load(&account_data(&mut sim.context, &game).await.unwrap()).unwrap();
+
+  sim.guest_play(0, 1, &game, SCISSORS).await;
+  let after = TGameState::load(&account_data(&mut sim.context, &game).await.unwrap()).unwrap();
+  assert_eq!(after.guest_move, SCISSORS);
+  assert_eq!((after.score_i, after.score_g, after.rounds_played), (before.score_i, before.score_g, before.rounds_played));
+  assert_eq!(after.status, GameStatus::Active{turn:1});
+}
+
+#[tokio::test]
+async fn the_move_clock_starts_when_the_match_is_accepted() {
+  let mut sim = setup(2, &[0], 2 * FEE).await;
+  sim.join(0).await;
+  sim.join(1).await;
+
+  let game = sim.init_match(0, 1, ROCK).await;
+  warp_seconds(&mut sim.context, TIME_IS_UP as i64 + 1).await;
+  sim.accept(0, 1, &game, SCISSORS).await;
+
+  let state = TGameState::load(&account_data(&mut sim.context, &game).await.unwrap()).unwrap();
+  assert_eq!(state.lastplaytime, now(&mut sim.context).await);
+  assert_game_error(sim.eliminate_idle(0, 1, &game).await, GameError::TimeoutNotReached);
+}
+
+#[tokio::test]
+async fn the_guest_wins_when_the_initializer_does_not_reveal() {
+  let mut sim = setup(2, &[0], 2 * FEE).await;
+  sim.join(0).await;
+  sim.join(1).await;
+
+  let game = sim.init_match(0, 1, ROCK).await;
+  sim.accept(0, 1, &game, SCISSORS).await;
+  assert_game_error(sim.eliminate_idle(0, 1, &game).await, GameError::TimeoutNotReached);
+  warp_seconds(&mut sim.context, TIME_IS_UP as i64 + 1).await;
+  sim.eliminate_idle(0, 1, &game).await.unwrap();
+
+  let loser = sim.players[0].tour_acc;
+  assert!(account_data(&mut sim.context, &loser).await.is_none());
+  assert_eq!(sim.tour_account(1).await.level, 1);
+}
+
+#[tokio::test]
+async fn a_winning_guest_collects_the_match_account() {
+  let mut sim = setup(4, &[0, 1], 3 * FEE).await;
+  for player in 0..4 {
+    sim.join(player).await;
+  }
+
+  let by_reveal = sim.init_match(0, 1, ROCK).await;
+  sim.accept(0, 1, &by_reveal, ROCK).await;
+  sim.reveal(0, 1, &by_reveal, ROCK, PAPER).await;
+  sim.guest_play(0, 1, &by_reveal, SCISSORS).await;
+
+  let by_timeout = sim.init_match(2, 3, ROCK).await;
+  sim.accept(2, 3, &by_timeout, SCISSORS).await;
+
+  // lvl_get[0] is 0, so the winner keeps everything both sides put in
+  let mut held = Vec::new();
+  for (game, initializer, guest) in [(by_reveal, 0, 1), (by_timeout, 2, 3)] {
+    let (i, g) = (sim.players[initializer].tour_acc, sim.players[guest].tour_acc);
+    held.push(balance(&mut sim.context, &game).await + balance(&mut sim.context, &i).await + balance(&mut sim.context, &g).await);
+  }
+
+  sim.reveal(0, 1, &by_reveal, PAPER, ROCK).await;
+  warp_seconds(&mut sim.context, TIME_IS_UP as i64 + 1).await;
+  sim.eliminate_idle(2, 3, &by_timeout).await.unwrap();
+
+  for (held, (game, winner)) in held.into_iter().zip([(by_reveal, 1), (by_timeout, 3)]) {
+    let tour_acc = sim.players[winner].tour_acc;
+    assert_eq!(balance(&mut sim.context, &game).await, 0);
+    assert_eq!(balance(&mut sim.context, &tour_acc).await, held);
+  }
+}
+
+#[tokio::test]
+async fn winners_are_paired_for_the_next_level() {
+  let mut sim = setup(4, &[0, 1], 3 * FEE).await;
+  for player in 0..4 {
+    sim.join(player).await;
+  }
+
+  sim.play_match(0, 1, Outcome::InitializerWins).await;
+  warp_seconds(&mut sim.context, TIME_IS_UP as i64 + 1).await;
+  sim.eliminate_no_show(2, 3).await.unwrap();
+  let advanced_at = now(&mut sim.context).await;
+
+  let (a, b) = (sim.tour_account(0).await, sim.tour_account(3).await);
+  assert_eq!((a.playerno_int, a.opponent), (2, 4));
+  assert_eq!((b.playerno_int, b.opponent), (4, 2));
+  assert_eq!(b.opponent_played_on, advanced_at);
+
+  let last = sim.init_match(0, 3, ROCK).await;
+  sim.accept(0, 3, &last, SCISSORS).await;
+}
+
+#[tokio::test]
+async fn the_prize_is_paid_once() {
+  let mut sim = setup(2, &[0], 2 * FEE).await;
+  sim.join(0).await;
+  sim.join(1).await;
+  sim.play_match(0, 1, Outcome::InitializerWins).await;
+
+  let p = &sim.players[0];
+  let (wallet, tour_acc) = (p.wallet.pubkey(), p.tour_acc);
+  let claim = client::winner_claim(&sim.program_id, &wallet, &tour_acc, &sim.tournament);
+  process(&mut sim.context, std::slice::from_ref(&claim), &[]).await.unwrap();
+  assert_eq!(sim.tour_account(0).await.prize_claimed, 1);
+  let paid = balance(&mut sim.context, &wallet).await;
+
+  assert_game_error(process(&mut sim.context, &[claim], &[]).await, GameError::PrizeAlreadyClaimed);
+  assert_eq!(balance(&mut sim.context, &wallet).await, paid);
+}
+
+#[tokio::test]
+async fn match_moves_need_the_player_to_sign() {
+  let mut sim = setup(2, &[0], 2 * FEE).await;
+  sim.join(0).await;
+  sim.join(1).await;
+
+  let game = sim.init_match(0, 1, ROCK).await;
+  sim.accept(0, 1, &game, ROCK).await;
+
+  let (i, g) = (&sim.players[0], &sim.players[1]);
+  let (initializer, initializer_tour_acc, guest, guest_tour_acc) = (i.wallet.pubkey(), i.tour_acc, g.wallet.pubkey(), g.tour_acc);
+  let play = InitializerPlay{ salt: salt(0), lastmove: ROCK, new_game_hash: commit(&game, 1, &initializer, PAPER) };
+  let mut reveal = client::tournament_initializer_play(&sim.program_id, &initializer, &initializer_tour_acc, &guest, &guest_tour_acc, &game, &sim.tournament, play);
+  reveal.accounts[0].is_signer = false;
+  assert_game_error(process(&mut sim.context, &[reveal], &[]).await, GameError::NotSigner);
+
+  sim.reveal(0, 1, &game, ROCK, PAPER).await;
+  let play = InitializerPlay{ salt: [0; 32], lastmove: SCISSORS, new_game_hash: [0; 32] };
+  let mut guest_play = client::tournament_guest_play(&sim.program_id, &initializer, &initializer_tour_acc, &guest, &guest_tour_acc, &game, &sim.tournament, play);
+  guest_play.accounts[2].is_signer = false;
+  assert_game_error(process(&mut sim.context, &[guest_play], &[]).await, GameError::NotSigner);
+}
+
+#[tokio::test]
+async fn tournament_accounts_from_before_the_header_are_rejected() {
+  let mut sim = setup(2, &[0], 2 * FEE).await;
+  sim.join(0).await;
+  sim.join(1).await;
+  let game = sim.init_match(0, 1, ROCK).await;
+
+  for key in [sim.tournament, sim.players[1].tour_acc] {
+    let data = account_data(&mut sim.context, &key).await.unwrap();
+    overwrite(&mut sim.context, &key, data[HEADER_LEN..].to_vec()).await;
+    assert_game_error(sim.try_accept(0, 1, &game, PAPER).await, GameError::WrongAccountType);
+    overwrite(&mut sim.context, &key, data).await;
+  }
+  sim.accept(0, 1, &game, PAPER).await;
+}